@@ -0,0 +1,19 @@
+#version 330 core
+
+in float frag_dist;
+
+out vec4 color;
+
+uniform vec4 border_color;
+uniform vec4 inner_color; // middle of the track
+uniform vec4 outer_color; // right before the border
+uniform float border_width; // fraction of the radius
+
+void main() {
+    float body_end = 1.0 - border_width;
+    float aa = fwidth(frag_dist);
+
+    vec4 body = mix(inner_color, outer_color, clamp(frag_dist / body_end, 0.0, 1.0));
+    color = mix(body, border_color, smoothstep(body_end - aa, body_end, frag_dist));
+    color.a *= 1.0 - smoothstep(1.0 - aa, 1.0, frag_dist);
+}
\ No newline at end of file
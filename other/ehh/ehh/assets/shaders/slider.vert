@@ -0,0 +1,15 @@
+#version 330 core
+
+layout (location = 0) in vec2 position;
+layout (location = 1) in float dist;
+
+out float frag_dist;
+
+uniform mat4 proj;
+
+void main() {
+    gl_Position = proj * vec4(position, 0.0, 1.0);
+    // closer to the middle of the track wins wherever it overlaps itself
+    gl_Position.z = dist - 1.0;
+    frag_dist = dist;
+}
\ No newline at end of file
@@ -0,0 +1,12 @@
+#version 330 core
+
+out vec4 color;
+
+uniform sampler2D body;
+uniform float alpha;
+
+void main() {
+    // the body was drawn with the same viewport, so pixels line up one to one
+    color = texelFetch(body, ivec2(gl_FragCoord.xy), 0);
+    color.a *= alpha;
+}
@@ -0,0 +1,7 @@
+#version 330 core
+
+// one triangle that covers the whole viewport, no vertex buffer needed
+void main() {
+    vec2 position = vec2((gl_VertexID & 1) * 4.0 - 1.0, (gl_VertexID & 2) * 2.0 - 1.0);
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,226 @@
+// compares timing lookups through the index with the old linear scans over every map in a folder
+// cargo bench --bench timing -- <songs folder>, the test maps are too small to say anything
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use ehh::{
+    beatmap::{HitObjectType, Mods, ParseMode, TimingIndex, TimingPoint},
+    library::walk_songs,
+    Beatmap,
+};
+
+const ROUNDS: usize = 5;
+
+// what beat_length_at used to be, kept around to compare against
+fn linear_beat_length_at(timing_points: &[TimingPoint], time: f64) -> f64 {
+    if timing_points.is_empty() {
+        return 0.0;
+    }
+
+    let mut point = 0;
+    let mut sample = 0;
+    for (i, x) in timing_points.iter().enumerate() {
+        if x.offset <= time {
+            if x.timing_change {
+                point = i;
+            } else {
+                sample = i;
+            }
+        }
+    }
+
+    if sample > point && timing_points[sample].beat_length < 0.0 {
+        timing_points[point].beat_length * timing_points[sample].bpm_multiplier() as f64
+    } else {
+        timing_points[point].beat_length
+    }
+}
+
+// same for bpm_multiplier_at, the last point that started no matter what kind it is
+fn linear_bpm_multiplier_at(timing_points: &[TimingPoint], time: f64) -> f32 {
+    timing_points
+        .iter()
+        .rev()
+        .find(|x| x.offset <= time)
+        .map(|x| x.bpm_multiplier())
+        .unwrap_or(1.0)
+}
+
+// fastest out of a few runs
+fn time_rounds(mut f: impl FnMut()) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let now = Instant::now();
+            f();
+            now.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn same(a: f64, b: f64) -> bool {
+    a == b || (a.is_nan() && b.is_nan())
+}
+
+fn main() {
+    let path = match std::env::args().skip(1).find(|x| !x.starts_with("--")) {
+        Some(x) => x,
+        None => {
+            eprintln!("usage: cargo bench --bench timing -- <songs folder>");
+            std::process::exit(1);
+        }
+    };
+
+    let mut beatmaps: Vec<Beatmap> = Vec::new();
+    let mut parse_time = Duration::ZERO;
+    for found in walk_songs(&path).filter_map(|x| x.ok()) {
+        let (set, difficulties) = match found.open() {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+        for x in difficulties {
+            let now = Instant::now();
+            let parsed = set.parse_difficulty_with_mode(&x, Mods::empty(), ParseMode::Lenient);
+            parse_time += now.elapsed();
+            if let Ok(beatmap) = parsed {
+                beatmaps.push(beatmap);
+            }
+        }
+    }
+
+    // every object's start and end, which is about what parsing and gameplay ask for
+    let queries: Vec<Vec<f64>> = beatmaps
+        .iter()
+        .map(|x| {
+            x.hit_objects
+                .iter()
+                .flat_map(|x| [x.start as f64, x.end as f64])
+                .collect()
+        })
+        .collect();
+    let query_count: usize = queries.iter().map(|x| x.len()).sum();
+    let point_count: usize = beatmaps.iter().map(|x| x.timing_points.len()).sum();
+
+    let mut mismatches = 0;
+    for (beatmap, times) in beatmaps.iter().zip(&queries) {
+        for &time in times {
+            let linear = linear_beat_length_at(&beatmap.timing_points, time);
+            if !same(linear, beatmap.timing.beat_length_at(time, true)) {
+                mismatches += 1;
+            }
+        }
+    }
+
+    let linear_time = time_rounds(|| {
+        for (beatmap, times) in beatmaps.iter().zip(&queries) {
+            for &time in times {
+                black_box(linear_beat_length_at(
+                    black_box(&beatmap.timing_points),
+                    black_box(time),
+                ));
+            }
+        }
+    });
+    let index_time = time_rounds(|| {
+        for (beatmap, times) in beatmaps.iter().zip(&queries) {
+            for &time in times {
+                black_box(black_box(&beatmap.timing).beat_length_at(black_box(time), true));
+            }
+        }
+    });
+
+    // what parsing pays for the index, once per map
+    let build_time = time_rounds(|| {
+        for beatmap in &beatmaps {
+            black_box(TimingIndex::new(black_box(&beatmap.timing_points)));
+        }
+    });
+
+    // the lookups parsing does, each slider asks for its beat length and bpm multiplier once
+    let slider_starts: Vec<Vec<f64>> = beatmaps
+        .iter()
+        .map(|x| {
+            x.hit_objects
+                .iter()
+                .filter(|x| x.object_type == HitObjectType::Slider)
+                .map(|x| x.start as f64)
+                .collect()
+        })
+        .collect();
+    let slider_count: usize = slider_starts.iter().map(|x| x.len()).sum();
+    let linear_slider_time = time_rounds(|| {
+        for (beatmap, times) in beatmaps.iter().zip(&slider_starts) {
+            for &time in times {
+                let points = black_box(&beatmap.timing_points);
+                black_box(linear_beat_length_at(points, black_box(time)));
+                black_box(linear_bpm_multiplier_at(points, black_box(time)));
+            }
+        }
+    });
+    let index_slider_time = time_rounds(|| {
+        for (beatmap, times) in beatmaps.iter().zip(&slider_starts) {
+            for &time in times {
+                let timing = black_box(&beatmap.timing);
+                black_box(timing.beat_length_at(black_box(time), true));
+                black_box(timing.bpm_multiplier_at(black_box(time)));
+            }
+        }
+    });
+
+    // the whole slider step, lookups and all, starting from scratch like the parser does
+    let recalculate_time = time_rounds(|| {
+        for beatmap in &mut beatmaps {
+            let (version, timing, difficulty) =
+                (beatmap.format_version, &beatmap.timing, &beatmap.difficulty);
+            for x in &mut beatmap.hit_objects {
+                if let Some(slider_info) = &mut x.slider_info {
+                    slider_info.ball_path.clear();
+                    slider_info.score_times.clear();
+                    slider_info.small_ticks.clear();
+                    slider_info.end_ticks.clear();
+                    x.recalculate_slider(version, timing, difficulty);
+                }
+            }
+        }
+    });
+
+    // nothing parses with linear scans anymore, so this is only an estimate:
+    // the measured parse minus the index and its slider lookups, plus the linear slider lookups
+    let estimated_linear_parse_time =
+        (parse_time.saturating_sub(build_time + index_slider_time)) + linear_slider_time;
+
+    println!(
+        "{} maps, {} timing points, {} lookups, {} sliders",
+        beatmaps.len(),
+        point_count,
+        query_count,
+        slider_count
+    );
+    println!("lookups on every object:");
+    println!("    linear scan: {:.2?}", linear_time);
+    println!("    index:       {:.2?}", index_time);
+    println!(
+        "    {:.1}x faster",
+        linear_time.as_secs_f64() / index_time.as_secs_f64().max(f64::EPSILON)
+    );
+    println!("parsing in total:");
+    println!("    with the index:                  {:.2?}", parse_time);
+    println!(
+        "    with linear scans (estimated): ~{:.2?}",
+        estimated_linear_parse_time
+    );
+    println!(
+        "    building the index took {:.2?}, slider lookups took {:.2?} instead of {:.2?}",
+        build_time, index_slider_time, linear_slider_time
+    );
+    println!(
+        "    recalculate_slider took {:.2?} ({:.2?} per slider)",
+        recalculate_time,
+        recalculate_time / slider_count.max(1) as u32
+    );
+    // only maps with timing points out of order should end up here
+    println!("{} lookups disagreed", mismatches);
+}
@@ -1,8 +1,14 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, rc::Rc, sync::Arc};
 
-use crate::framework::render::{TextureAtlas, TextureRegion};
+use log::warn;
 
-use super::skin::Skin;
+use crate::{
+    beatmap::BeatmapSet,
+    framework::render::{TextureAtlas, TextureRegion},
+    math::Vector2,
+};
+
+use super::skin::{AssetSource, Skin};
 
 pub struct AnimatedTexture {
     textures: Vec<Rc<TextureRegion>>,
@@ -40,8 +46,16 @@ impl AnimatedTexture {
     }
 }
 
+// can't collide with anything from a skin since those always come from a filename
+const WHITE_TEX_NAME: &str = "<white>";
+
+// what missing textures look like, big enough to not be invisible when scaled like a hitcircle
+const PLACEHOLDER_SIZE: usize = 128;
+const PLACEHOLDER_CHECKER_SIZE: usize = 16;
+
 pub struct AssetLoader {
     pub skin: Skin,
+    sources: Vec<AssetSource>, // first one that has something wins
     // TODO: proper layering system once ui and stuff get implemented
     // also, i would need to store textures in cpu memory to recreate the atlas between beatmap/skin changes
     atlas: TextureAtlas,
@@ -49,9 +63,20 @@ pub struct AssetLoader {
 }
 
 impl AssetLoader {
-    pub fn new(skin_path: &str) -> AssetLoader {
+    // beatmap folder -> user skin -> built-in skin
+    pub fn new(skin_path: Option<&str>, beatmap_set: Option<Arc<BeatmapSet>>) -> AssetLoader {
+        let mut sources = Vec::with_capacity(3);
+        if let Some(set) = beatmap_set {
+            sources.push(AssetSource::Beatmap(set));
+        }
+        if let Some(path) = skin_path {
+            sources.push(AssetSource::Skin(path.to_string()));
+        }
+        sources.push(AssetSource::Default);
+
         let mut loader = AssetLoader {
             skin: Skin::new(skin_path),
+            sources,
             atlas: TextureAtlas::new(4096, gl::RGBA),
             tex_map: Default::default(),
         };
@@ -64,6 +89,9 @@ impl AssetLoader {
         loader.lookup_tex("sliderscorepoint");
         loader.lookup_tex("reversearrow");
         loader.lookup_anim("sliderb", false);
+        for i in 0..10 {
+            loader.lookup_tex(&format!("{}-{}", loader.skin.hit_circle_prefix, i));
+        }
 
         loader
     }
@@ -75,23 +103,84 @@ impl AssetLoader {
         has_dash: bool,
     ) -> Rc<AnimatedTexture> {
         if let Some(tex) = self.tex_map.get(name) {
-            tex.clone()
-        } else if let Some(tex) = self
-            .skin
-            .try_load_tex(&mut self.atlas, name, animated, has_dash)
-        {
-            self.tex_map.insert(name.to_string(), tex.clone());
-            tex
-        } else {
-            // TODO: generate a placeholder
-            panic!("Failed to load texture {}", name);
+            return tex.clone();
         }
+
+        let tex = self
+            .sources
+            .iter()
+            .find_map(|source| {
+                self.skin
+                    .try_load_tex(source, &mut self.atlas, name, animated, has_dash)
+            })
+            .unwrap_or_else(|| {
+                // cached like anything else, so this only gets logged once
+                warn!("Missing texture {}, using a placeholder", name);
+                Rc::new(AnimatedTexture::new(&[self.placeholder_tex(name)], -1.0))
+            });
+        self.tex_map.insert(name.to_string(), tex.clone());
+        tex
+    }
+
+    // magenta and black checkerboard, so that it's obvious something is missing
+    fn placeholder_tex(&mut self, name: &str) -> Rc<TextureRegion> {
+        let mut pixels = Vec::with_capacity(PLACEHOLDER_SIZE * PLACEHOLDER_SIZE * 4);
+        for y in 0..PLACEHOLDER_SIZE {
+            for x in 0..PLACEHOLDER_SIZE {
+                let checker =
+                    ((x / PLACEHOLDER_CHECKER_SIZE) ^ (y / PLACEHOLDER_CHECKER_SIZE)) & 1 == 0;
+                pixels.extend_from_slice(if checker {
+                    &[0xFF, 0x00, 0xFF, 0xFF]
+                } else {
+                    &[0x00, 0x00, 0x00, 0xFF]
+                });
+            }
+        }
+        self.atlas.add(
+            name,
+            &pixels,
+            PLACEHOLDER_SIZE as u32,
+            PLACEHOLDER_SIZE as u32,
+            1.0,
+        )
+    }
+
+    // samples come from the skin or the built-in one, beatmap samples have their own rules
+    pub fn read_skin_sample(&self, filename: &str) -> Option<Vec<u8>> {
+        self.sources
+            .iter()
+            .filter(|x| !matches!(x, AssetSource::Beatmap(_)))
+            .find_map(|x| x.read_file(filename))
     }
 
     pub fn lookup_anim(&mut self, name: &str, has_dash: bool) -> Rc<AnimatedTexture> {
         self.lookup_internal(name, true, has_dash)
     }
 
+    // 1x1 white texture for drawing plain rectangles
+    pub fn white_tex(&mut self) -> Rc<TextureRegion> {
+        if let Some(tex) = self.tex_map.get(WHITE_TEX_NAME) {
+            return tex.textures[0].clone();
+        }
+
+        // pull the uvs in so that linear filtering doesn't bleed in the atlas padding
+        let tex = self
+            .atlas
+            .add(WHITE_TEX_NAME, &[0xFF; 4 * 4 * 4], 4, 4, 1.0);
+        let inset = Vector2::new(1.0, 1.0) / self.atlas.size as f32;
+        let tex = Rc::new(TextureRegion {
+            uvs: [tex.uvs[0] + inset, tex.uvs[1] - inset],
+            width: 1.0,
+            height: 1.0,
+            ..(*tex).clone()
+        });
+        self.tex_map.insert(
+            WHITE_TEX_NAME.to_string(),
+            Rc::new(AnimatedTexture::new(&[tex.clone()], -1.0)),
+        );
+        tex
+    }
+
     pub fn lookup_tex(&mut self, name: &str) -> Rc<TextureRegion> {
         let anim = self.lookup_internal(name, false, false);
         assert!(!anim.textures.is_empty());
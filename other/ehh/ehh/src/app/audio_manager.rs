@@ -1,101 +1,144 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc};
 
-use crate::framework::{
-    bass::{Bass, BassChannelCommon, BassMixer, BassSample, BassStream},
-    clock::{BassStreamClock, Clock, DecoupledClock, OffsetClock},
+use log::warn;
+
+use crate::{
+    beatmap::{Beatmap, BeatmapSet, ResolvedHitSound, ResolvedSample},
+    framework::{
+        audio::{AudioBackend, AudioSample, AudioTrack},
+        clock::{Clock, DecoupledClock, OffsetClock, TrackClock},
+    },
 };
 
-use super::asset_loader::AssetLoader;
+use super::{asset_loader::AssetLoader, config::Config};
+
+// how many copies of a sample can play over each other
+const SAMPLE_CHANNELS: u32 = 4;
+// osu tries these in order
+const SAMPLE_EXTENSIONS: [&str; 3] = ["wav", "ogg", "mp3"];
 
 pub struct AudioManager {
-    bass: Rc<Bass>,
+    backend: Rc<dyn AudioBackend>,
     asset_loader: Rc<RefCell<AssetLoader>>,
-    mixer: Rc<BassMixer>,
-    main_track: Rc<BassStream>,
+    main_track: Rc<dyn AudioTrack>,
     main_track_clock: OffsetClock,
-    test_sample: Rc<BassSample>,
+    effect_volume: f32,
+
+    // None means it doesn't exist, so it doesn't get looked up again
+    beatmap_set: Arc<BeatmapSet>,
+    beatmap_samples: HashMap<String, Option<Rc<dyn AudioSample>>>,
+    skin_samples: HashMap<String, Option<Rc<dyn AudioSample>>>,
 }
 
 impl AudioManager {
     pub fn new(
-        bass: Rc<Bass>,
+        backend: Rc<dyn AudioBackend>,
         asset_loader: Rc<RefCell<AssetLoader>>,
-        audio_path: &str,
-    ) -> AudioManager {
-        let mixer = Rc::new(
-            bass.create_mixer(
-                44100,
-                2,
-                bassmix_sys::BASS_MIXER_NONSTOP | bass_sys::BASS_SAMPLE_FLOAT,
-            )
-            .expect("Failed to create mixer"),
-        );
-        mixer.set_attrib(bass_sys::BASS_ATTRIB_BUFFER, 0.0);
-        mixer.set_attrib(bass_sys::BASS_ATTRIB_VOL, 0.5);
-        mixer
-            .set_device(bass.get_device().unwrap())
-            .expect("Failed to set mixer device");
-        mixer.play(false).expect("Failed to start the mixer");
-
-        // TODO: gracefully handle this failing
-        let main_track = bass
-            .create_stream_from_file(
-                audio_path,
-                bass_sys::BASS_STREAM_DECODE | bass_sys::BASS_STREAM_PRESCAN,
-            )
-            .expect("Failed to load audio track");
-        mixer
-            .add_channel(main_track.clone(), bassmix_sys::BASS_MIXER_CHAN_PAUSE)
-            .expect("Failed to add audio track to the mixer");
-        let main_track_clock = OffsetClock::new(
-            Box::new(DecoupledClock::new(Box::new(BassStreamClock::new(
-                mixer.clone(),
-                main_track.clone(),
-            )))),
-            -15.0, // offset used by osu for wasapi backend
+        beatmap: &Beatmap,
+        config: &Config,
+    ) -> Result<AudioManager, String> {
+        backend.set_volume(config.master_volume as f32 / 100.0);
+
+        let audio = beatmap
+            .set
+            .read_file(&beatmap.audio_filename)
+            .map_err(|e| format!("Failed to read audio track: {}", e))?;
+        let main_track = backend.create_track(audio)?;
+        main_track.set_volume(config.music_volume as f32 / 100.0);
+        let mut main_track_clock = OffsetClock::new(
+            Box::new(DecoupledClock::new(
+                Box::new(TrackClock::new(main_track.clone())),
+                backend.create_clock(),
+            )),
+            -backend.output_latency() - config.audio_offset(beatmap) as f64,
         );
+        main_track_clock.set_rate(beatmap.difficulty.mods.clock_rate());
 
-        let test_sample = bass
-            .create_sample_from_file(
-                Some(mixer.clone()),
-                &format!(
-                    "{}/normal-hitnormal.wav",
-                    asset_loader.borrow().skin.base_path
-                ),
-                1,
-                0,
-            )
-            .unwrap();
-
-        AudioManager {
-            bass,
+        Ok(AudioManager {
+            backend,
             asset_loader,
-            mixer,
             main_track,
             main_track_clock,
-            test_sample,
+            effect_volume: config.effect_volume as f32 / 100.0,
+            beatmap_set: beatmap.set.clone(),
+            beatmap_samples: HashMap::new(),
+            skin_samples: HashMap::new(),
+        })
+    }
+
+    pub fn play_hitsound(&mut self, hitsound: &ResolvedHitSound, pan: f32) {
+        let volume = hitsound.volume.clamp(0, 100) as f32 / 100.0 * self.effect_volume;
+        for x in &hitsound.samples {
+            // custom samples fall back to the skin if the beatmap doesn't have them
+            let sample = match x {
+                ResolvedSample::Skinnable(name) => name
+                    .beatmap_filename()
+                    .and_then(|x| self.beatmap_sample(&x, true))
+                    .or_else(|| self.skin_sample(&name.name)),
+                ResolvedSample::File(filename) => self.beatmap_sample(filename, false),
+            };
+            if let Some(sample) = sample {
+                sample.play(pan, volume);
+            }
         }
     }
 
-    pub fn play_test_sample(&mut self, pan: f32) {
-        /*
-        let channel = self
-            .test_sample
-            .get_channel(
-                bass_sys::BASS_SAMCHAN_STREAM | bass_sys::BASS_STREAM_DECODE,
-                true,
-            )
-            .unwrap();
-        channel.set_attrib(bass_sys::BASS_ATTRIB_PAN, pan);
-        channel.set_attrib(bass_sys::BASS_ATTRIB_VOL, 0.8);
-        self.mixer
-            .add_channel(
-                channel,
-                bassmix_sys::BASS_MIXER_CHAN_NORAMPIN | bass_sys::BASS_STREAM_AUTOFREE,
-            )
-            .unwrap();
-        */
-        self.test_sample.play_mixer(pan, 0.8);
+    fn beatmap_sample(&mut self, name: &str, add_extension: bool) -> Option<Rc<dyn AudioSample>> {
+        if let Some(x) = self.beatmap_samples.get(name) {
+            return x.clone();
+        }
+
+        let mut sample = None;
+        let filenames: Vec<String> = if add_extension {
+            SAMPLE_EXTENSIONS
+                .iter()
+                .map(|ext| format!("{}.{}", name, ext))
+                .collect()
+        } else {
+            vec![name.to_string()]
+        };
+        for filename in filenames {
+            if !self.beatmap_set.exists(&filename) {
+                continue;
+            }
+            let loaded = self
+                .beatmap_set
+                .read_file(&filename)
+                .ok()
+                .and_then(|data| self.backend.create_sample(&data, SAMPLE_CHANNELS).ok());
+            if loaded.is_none() {
+                warn!("Failed to load beatmap sample {}", filename);
+            }
+            sample = loaded;
+            break;
+        }
+
+        self.beatmap_samples
+            .insert(name.to_string(), sample.clone());
+        sample
+    }
+
+    fn skin_sample(&mut self, name: &str) -> Option<Rc<dyn AudioSample>> {
+        if let Some(x) = self.skin_samples.get(name) {
+            return x.clone();
+        }
+
+        let asset_loader = self.asset_loader.borrow();
+        let sample = SAMPLE_EXTENSIONS.iter().find_map(|ext| {
+            let filename = format!("{}.{}", name, ext);
+            let data = asset_loader.read_skin_sample(&filename)?;
+            let loaded = self.backend.create_sample(&data, SAMPLE_CHANNELS).ok();
+            if loaded.is_none() {
+                warn!("Failed to load skin sample {}", filename);
+            }
+            loaded
+        });
+        if sample.is_none() {
+            warn!("Skin is missing sample {}", name);
+        }
+
+        self.skin_samples.insert(name.to_string(), sample.clone());
+        sample
     }
 
     pub fn seek_music(&mut self, pos: f64) -> bool {
@@ -114,6 +157,10 @@ impl AudioManager {
         self.main_track_clock.get_time()
     }
 
+    pub fn music_rate(&self) -> f64 {
+        self.main_track_clock.get_rate()
+    }
+
     pub fn update(&mut self) {
         self.main_track_clock.update();
     }
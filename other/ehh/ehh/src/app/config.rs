@@ -0,0 +1,289 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+};
+
+use log::warn;
+
+use crate::beatmap::Beatmap;
+
+// looked for in the working directory, same as osu keeping its cfg next to the exe
+pub const DEFAULT_CONFIG_PATH: &str = "ehh.cfg";
+
+// everything the user can change that sticks around between runs
+// same "Key = Value" format as osu!.cfg
+pub struct Config {
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub frame_limit: u32, // 0 means unlimited, does nothing with vsync on
+
+    pub skin_path: Option<String>, // only the built-in skin if there's none
+    pub songs_path: Option<String>, // where relative beatmap paths get looked up
+
+    // 0 to 100
+    pub master_volume: u32,
+    pub music_volume: u32,
+    pub effect_volume: u32,
+
+    // how far ahead of the audio the game runs in ms, raise it if you're hitting early
+    pub universal_offset: i32,
+    // added on top of the universal one, see beatmap_key for what they're keyed by
+    pub beatmap_offsets: BTreeMap<String, i32>,
+
+    pub keys: [String; 2], // sdl key names
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            width: 1920,
+            height: 1080,
+            fullscreen: true,
+            vsync: false,
+            frame_limit: 0,
+            skin_path: None,
+            songs_path: None,
+            master_volume: 50,
+            music_volume: 100,
+            effect_volume: 100,
+            universal_offset: 0,
+            beatmap_offsets: BTreeMap::new(),
+            keys: ["Z".to_string(), "X".to_string()],
+        }
+    }
+}
+
+// also used for skin.ini, a bad value gets warned about and the default kept
+// source is the file name for the warning
+pub(super) fn parse_or_warn<T: std::str::FromStr>(
+    val: &str,
+    source: &str,
+    line_num: u32,
+    default: T,
+) -> T {
+    match val.trim().parse::<T>() {
+        Ok(x) => x,
+        Err(_) => {
+            warn!("Invalid value \"{}\" in {} line {}", val, source, line_num);
+            default
+        }
+    }
+}
+
+pub(super) fn parse_bool(val: &str, source: &str, line_num: u32, default: bool) -> bool {
+    match val.trim() {
+        "1" | "true" | "True" => true,
+        "0" | "false" | "False" => false,
+        _ => {
+            warn!("Invalid value \"{}\" in {} line {}", val, source, line_num);
+            default
+        }
+    }
+}
+
+// online id if it has one, otherwise what osu shows in song select
+pub fn beatmap_key(beatmap: &Beatmap) -> String {
+    if beatmap.beatmap_id > 0 {
+        beatmap.beatmap_id.to_string()
+    } else {
+        format!(
+            "{} - {} ({}) [{}]",
+            beatmap.romanized_artist, beatmap.romanized_title, beatmap.creator, beatmap.version
+        )
+    }
+}
+
+impl Config {
+    // a missing file just means defaults, a broken one gets as much read out of it as possible
+    // nothing gets written, that only happens through save()
+    pub fn load(path: &str) -> Config {
+        match File::open(path) {
+            Ok(file) => Config::parse(&mut BufReader::new(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+            Err(e) => {
+                warn!("Failed to open {}: {}", path, e);
+                Config::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()
+    }
+
+    pub fn parse(file: &mut impl BufRead) -> Config {
+        let mut config = Config::default();
+        for (line_num, line) in file.lines().enumerate() {
+            let line_num = line_num as u32 + 1;
+            let line = match line {
+                Ok(x) => x,
+                Err(e) => {
+                    warn!("Failed to read config: {}", e);
+                    break;
+                }
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, val) = match line.split_once('=') {
+                Some((key, val)) => (key.trim(), val.trim()),
+                None => {
+                    warn!("Invalid config line {}: {}", line_num, line);
+                    continue;
+                }
+            };
+            let path = || (!val.is_empty()).then(|| val.to_string());
+            match key {
+                "Width" => config.width = parse_or_warn(val, "config", line_num, config.width),
+                "Height" => config.height = parse_or_warn(val, "config", line_num, config.height),
+                "Fullscreen" => {
+                    config.fullscreen = parse_bool(val, "config", line_num, config.fullscreen)
+                }
+                "VSync" => config.vsync = parse_bool(val, "config", line_num, config.vsync),
+                "FrameLimit" => config.frame_limit = parse_or_warn(val, "config", line_num, 0),
+                "Skin" => config.skin_path = path(),
+                "SongsFolder" => config.songs_path = path(),
+                "VolumeUniversal" => {
+                    config.master_volume =
+                        parse_or_warn(val, "config", line_num, config.master_volume)
+                }
+                "VolumeMusic" => {
+                    config.music_volume =
+                        parse_or_warn(val, "config", line_num, config.music_volume)
+                }
+                "VolumeEffect" => {
+                    config.effect_volume =
+                        parse_or_warn(val, "config", line_num, config.effect_volume)
+                }
+                "Offset" => config.universal_offset = parse_or_warn(val, "config", line_num, 0),
+                // the offset goes first since beatmap keys can have anything in them
+                "BeatmapOffset" => match val.split_once(' ') {
+                    Some((offset, beatmap)) => {
+                        if let Ok(offset) = offset.parse::<i32>() {
+                            config
+                                .beatmap_offsets
+                                .insert(beatmap.trim().to_string(), offset);
+                        } else {
+                            warn!("Invalid beatmap offset in config line {}", line_num);
+                        }
+                    }
+                    None => warn!("Invalid beatmap offset in config line {}", line_num),
+                },
+                "KeyOsuLeft" => config.keys[0] = val.to_string(),
+                "KeyOsuRight" => config.keys[1] = val.to_string(),
+                _ => warn!("Unknown config key {}", key),
+            }
+        }
+
+        config.master_volume = config.master_volume.min(100);
+        config.music_volume = config.music_volume.min(100);
+        config.effect_volume = config.effect_volume.min(100);
+        config
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "# ehh configuration")?;
+        writeln!(out, "Width = {}", self.width)?;
+        writeln!(out, "Height = {}", self.height)?;
+        writeln!(out, "Fullscreen = {}", self.fullscreen as i32)?;
+        writeln!(out, "VSync = {}", self.vsync as i32)?;
+        writeln!(out, "FrameLimit = {}", self.frame_limit)?;
+        writeln!(out, "Skin = {}", self.skin_path.as_deref().unwrap_or(""))?;
+        writeln!(
+            out,
+            "SongsFolder = {}",
+            self.songs_path.as_deref().unwrap_or("")
+        )?;
+        writeln!(out, "VolumeUniversal = {}", self.master_volume)?;
+        writeln!(out, "VolumeMusic = {}", self.music_volume)?;
+        writeln!(out, "VolumeEffect = {}", self.effect_volume)?;
+        writeln!(out, "Offset = {}", self.universal_offset)?;
+        writeln!(out, "KeyOsuLeft = {}", self.keys[0])?;
+        writeln!(out, "KeyOsuRight = {}", self.keys[1])?;
+        for (beatmap, offset) in &self.beatmap_offsets {
+            writeln!(out, "BeatmapOffset = {} {}", offset, beatmap)?;
+        }
+        Ok(())
+    }
+
+    // universal + per-beatmap
+    pub fn audio_offset(&self, beatmap: &Beatmap) -> i32 {
+        self.universal_offset
+            + self
+                .beatmap_offsets
+                .get(&beatmap_key(beatmap))
+                .copied()
+                .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::Path};
+
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let source = "\
+# comments and junk get skipped
+Width = 1280
+Height=720
+Fullscreen = 0
+VSync = maybe
+FrameLimit = 240
+Skin = skins/Some Skin
+SongsFolder =
+VolumeMusic = 150
+Offset = -12
+BeatmapOffset = 5 123456
+BeatmapOffset = -3 Artist - Title (mapper) [Hard]
+KeyOsuLeft = Left Shift
+not a key value pair
+";
+        let config = Config::parse(&mut Cursor::new(source));
+        assert_eq!((config.width, config.height), (1280, 720));
+        assert!(!config.fullscreen);
+        assert!(!config.vsync); // invalid, so the default
+        assert_eq!(config.frame_limit, 240);
+        assert_eq!(config.skin_path.as_deref(), Some("skins/Some Skin"));
+        assert_eq!(config.songs_path, None);
+        assert_eq!(config.music_volume, 100);
+        assert_eq!(config.universal_offset, -12);
+        assert_eq!(config.beatmap_offsets["123456"], 5);
+        assert_eq!(config.beatmap_offsets["Artist - Title (mapper) [Hard]"], -3);
+        assert_eq!(config.keys, ["Left Shift", "X"]);
+
+        // writing it back out keeps everything
+        let mut written = Vec::new();
+        config.write(&mut written).unwrap();
+        let reparsed = Config::parse(&mut Cursor::new(written.clone()));
+        let mut rewritten = Vec::new();
+        reparsed.write(&mut rewritten).unwrap();
+        assert_eq!(written, rewritten);
+        assert_eq!(reparsed.beatmap_offsets, config.beatmap_offsets);
+    }
+
+    #[test]
+    fn test_load_missing() {
+        let path = std::env::temp_dir().join("ehh_test_load_missing.cfg");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        // loading alone never creates the file
+        let config = Config::load(path);
+        assert_eq!(config.width, Config::default().width);
+        assert!(!Path::new(path).exists());
+
+        config.save(path).unwrap();
+        assert_eq!(Config::load(path).keys, config.keys);
+        std::fs::remove_file(path).unwrap();
+    }
+}
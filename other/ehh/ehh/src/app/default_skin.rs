@@ -0,0 +1,75 @@
+// the skin that's always there underneath everything else, compiled in so that ehh works
+// without an osu install
+
+macro_rules! skin_file {
+    ($name:literal) => {
+        ($name, include_bytes!(concat!("../../assets/skin/", $name)))
+    };
+}
+
+const FILES: [(&str, &[u8]); 29] = [
+    skin_file!("approachcircle.png"),
+    skin_file!("cursor.png"),
+    skin_file!("default-0.png"),
+    skin_file!("default-1.png"),
+    skin_file!("default-2.png"),
+    skin_file!("default-3.png"),
+    skin_file!("default-4.png"),
+    skin_file!("default-5.png"),
+    skin_file!("default-6.png"),
+    skin_file!("default-7.png"),
+    skin_file!("default-8.png"),
+    skin_file!("default-9.png"),
+    skin_file!("drum-hitclap.wav"),
+    skin_file!("drum-hitfinish.wav"),
+    skin_file!("drum-hitnormal.wav"),
+    skin_file!("drum-hitwhistle.wav"),
+    skin_file!("hitcircle.png"),
+    skin_file!("hitcircleoverlay.png"),
+    skin_file!("normal-hitclap.wav"),
+    skin_file!("normal-hitfinish.wav"),
+    skin_file!("normal-hitnormal.wav"),
+    skin_file!("normal-hitwhistle.wav"),
+    skin_file!("reversearrow.png"),
+    skin_file!("sliderb.png"),
+    skin_file!("sliderscorepoint.png"),
+    skin_file!("soft-hitclap.wav"),
+    skin_file!("soft-hitfinish.wav"),
+    skin_file!("soft-hitnormal.wav"),
+    skin_file!("soft-hitwhistle.wav"),
+];
+
+// filenames are matched case insensitively, same as skins on windows
+pub fn default_skin_file(filename: &str) -> Option<&'static [u8]> {
+    FILES
+        .iter()
+        .find(|x| x.0.eq_ignore_ascii_case(filename))
+        .map(|x| x.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_skin() {
+        // everything gameplay looks up unconditionally
+        for name in [
+            "hitcircle",
+            "hitcircleoverlay",
+            "approachcircle",
+            "sliderscorepoint",
+            "reversearrow",
+            "sliderb",
+            "cursor",
+        ] {
+            let data = default_skin_file(&format!("{}.png", name)).unwrap();
+            assert!(lodepng::decode32(data).is_ok(), "{} doesn't decode", name);
+        }
+        for i in 0..10 {
+            assert!(default_skin_file(&format!("default-{}.png", i)).is_some());
+        }
+        assert!(default_skin_file("Normal-HitNormal.wav").is_some());
+        assert!(default_skin_file("nothing.png").is_none());
+    }
+}
@@ -1,17 +1,27 @@
-use std::{cell::RefCell, fs::File, io::BufReader, path::PathBuf, rc::Rc};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
 
 use log::info;
+use sdl2::{event::Event, keyboard::Keycode};
 
 use crate::{
     app::{audio_manager::AudioManager, hitobject_manager::HitObjectManager},
+    beatmap::{BeatmapSet, Gamemode},
     framework::{
-        bass::Bass,
-        render::{Alignment, DrawBatch, TextRenderer, TextSprite},
+        audio::AudioBackend,
+        render::{Alignment, DrawBatch, Origin, TextRenderer, TextSprite, TextureRegion},
     },
+    math::{interp_time, Easing, Vector2},
+    replay::{autoplay_frames, OsuKeys, ReplayFrame},
     Beatmap,
 };
 
-use super::asset_loader::AssetLoader;
+use super::{
+    asset_loader::AssetLoader,
+    config::Config,
+    input::{InputFrame, InputManager},
+    main::PlayOptions,
+    score_processor::ScoreProcessor,
+};
 
 // native osu ui resolution is 1024x768
 // playfield resolution is 512x384, exactly half
@@ -22,104 +32,390 @@ pub const OSU_NATIVE_TO_PLAYFIELD_RATIO: u32 = 2;
 pub const OSU_PLAYFIELD_WIDTH: u32 = OSU_NATIVE_WIDTH / OSU_NATIVE_TO_PLAYFIELD_RATIO;
 pub const OSU_PLAYFIELD_HEIGHT: u32 = OSU_NATIVE_HEIGHT / OSU_NATIVE_TO_PLAYFIELD_RATIO;
 
+// how far the arrow keys jump
+const SEEK_STEP: i32 = 5000;
+
 struct OsuHUD {
-    hitobject_manager: Rc<RefCell<HitObjectManager>>,
     text_renderer: Rc<RefCell<TextRenderer>>,
     batch: DrawBatch,
-    text: TextSprite,
+    score_text: TextSprite,
+    accuracy_text: TextSprite,
+    combo_text: TextSprite,
+    practice_text: TextSprite,
+    health_tex: Rc<TextureRegion>,
+    health_width: f32,
 }
 
 impl OsuHUD {
     pub fn new(
         width: f32,
         height: f32,
-        hitobject_manager: Rc<RefCell<HitObjectManager>>,
+        asset_loader: Rc<RefCell<AssetLoader>>,
         text_renderer: Rc<RefCell<TextRenderer>>,
     ) -> OsuHUD {
         let ortho = cgmath::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
-        let text = TextSprite::new(
+        let score_text = TextSprite::new(
             text_renderer.clone(),
-            "Hellooooo Wooorld",
-            8.0,
+            "00000000",
+            width - 8.0,
             0.0,
-            0.25,
+            0.5,
+            Alignment::Right,
+        );
+        let accuracy_text = TextSprite::new(
+            text_renderer.clone(),
+            "100.00%",
+            width - 8.0,
+            56.0,
+            0.3,
+            Alignment::Right,
+        );
+        let combo_text = TextSprite::new(
+            text_renderer.clone(),
+            "0x",
+            8.0,
+            height - 64.0,
+            0.5,
             Alignment::Left,
         );
+        let practice_text = TextSprite::new(
+            text_renderer.clone(),
+            "",
+            width / 2.0,
+            0.0,
+            0.3,
+            Alignment::Center,
+        );
+
+        // same width as the score, from the left edge
+        let health_width = width / 2.5;
+        let white = asset_loader.borrow_mut().white_tex();
+        let health_tex = Rc::new(TextureRegion {
+            width: health_width,
+            height: height / 54.0,
+            ..(*white).clone()
+        });
 
         OsuHUD {
-            hitobject_manager,
             text_renderer,
             batch: DrawBatch::new(ortho),
-            text,
+            score_text,
+            accuracy_text,
+            combo_text,
+            practice_text,
+            health_tex,
+            health_width,
         }
     }
 
-    pub fn update(&mut self) {}
+    pub fn set_practice_text(&mut self, text: &str) {
+        self.practice_text.set_text(text);
+    }
+
+    pub fn update(&mut self, score: &ScoreProcessor) {
+        self.score_text.set_text(&format!("{:08}", score.score));
+        self.accuracy_text
+            .set_text(&format!("{:.2}%", score.accuracy() * 100.0));
+        self.combo_text.set_text(&if score.failed {
+            "Failed".to_string()
+        } else {
+            format!("{}x", score.combo)
+        });
+
+        self.health_tex = Rc::new(TextureRegion {
+            width: self.health_width * score.health as f32,
+            ..(*self.health_tex).clone()
+        });
+    }
 
     pub fn draw(&mut self) {
-        self.text.set_text(&format!(
-            "Visible objects: {}",
-            self.hitobject_manager.borrow().visible_objs_count()
-        ));
-        self.text.add_to_batch(&mut self.batch);
+        let background = Rc::new(TextureRegion {
+            width: self.health_width,
+            ..(*self.health_tex).clone()
+        });
+        self.batch.add(
+            background,
+            Vector2::new(8.0, 8.0),
+            1.0,
+            Origin::TopLeft,
+            0x80000000,
+            0.0,
+        );
+        self.batch.add(
+            self.health_tex.clone(),
+            Vector2::new(8.0, 8.0),
+            1.0,
+            Origin::TopLeft,
+            0xFFFFFFFF,
+            0.0,
+        );
+
+        self.score_text.add_to_batch(&mut self.batch);
+        self.accuracy_text.add_to_batch(&mut self.batch);
+        self.combo_text.add_to_batch(&mut self.batch);
+        self.practice_text.add_to_batch(&mut self.batch);
+
+        self.batch.draw();
+    }
+}
+
+// black bars at the top and bottom of the screen during breaks
+struct BreakOverlay {
+    beatmap: Rc<Beatmap>,
+    batch: DrawBatch,
+    bar_tex: Rc<TextureRegion>,
+    height: f32,
+}
+
+impl BreakOverlay {
+    const FADE_TIME: i32 = 200;
+
+    pub fn new(
+        width: f32,
+        height: f32,
+        asset_loader: Rc<RefCell<AssetLoader>>,
+        beatmap: Rc<Beatmap>,
+    ) -> BreakOverlay {
+        let ortho = cgmath::ortho(0.0, width, height, 0.0, -1.0, 1.0);
+        let white = asset_loader.borrow_mut().white_tex();
+        let bar_tex = Rc::new(TextureRegion {
+            width,
+            height: height / 8.0,
+            ..(*white).clone()
+        });
+
+        BreakOverlay {
+            beatmap,
+            batch: DrawBatch::new(ortho),
+            bar_tex,
+            height,
+        }
+    }
+
+    pub fn draw(&mut self, time: i32) {
+        if !self.beatmap.letterbox_in_breaks {
+            return;
+        }
+
+        if let Some(break_period) = self.beatmap.events.break_at(time) {
+            let fade_in = interp_time(
+                0.0,
+                1.0,
+                break_period.start as f32,
+                (break_period.start + Self::FADE_TIME) as f32,
+                time as f32,
+                Easing::Linear,
+            );
+            let fade_out = interp_time(
+                1.0,
+                0.0,
+                (break_period.end - Self::FADE_TIME) as f32,
+                break_period.end as f32,
+                time as f32,
+                Easing::Linear,
+            );
+            let alpha = (fade_in.min(fade_out) * 255.0).clamp(0.0, 255.0) as u32;
+
+            self.batch.add(
+                self.bar_tex.clone(),
+                Vector2::new(0.0, 0.0),
+                1.0,
+                Origin::TopLeft,
+                alpha << 24,
+                0.0,
+            );
+            self.batch.add(
+                self.bar_tex.clone(),
+                Vector2::new(0.0, self.height - self.bar_tex.height),
+                1.0,
+                Origin::TopLeft,
+                alpha << 24,
+                0.0,
+            );
+            self.batch.draw();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PauseChoice {
+    Resume,
+    Retry,
+    Quit,
+}
+
+struct PauseMenu {
+    batch: DrawBatch,
+    dim_tex: Rc<TextureRegion>,
+    title_text: TextSprite,
+    choice_texts: Vec<TextSprite>,
+    selected: usize,
+}
+
+impl PauseMenu {
+    const CHOICES: [(PauseChoice, &'static str); 3] = [
+        (PauseChoice::Resume, "Resume"),
+        (PauseChoice::Retry, "Retry"),
+        (PauseChoice::Quit, "Quit"),
+    ];
+
+    pub fn new(
+        width: f32,
+        height: f32,
+        asset_loader: Rc<RefCell<AssetLoader>>,
+        text_renderer: Rc<RefCell<TextRenderer>>,
+    ) -> PauseMenu {
+        let ortho = cgmath::ortho(0.0, width, height, 0.0, -1.0, 1.0);
+        let white = asset_loader.borrow_mut().white_tex();
+        let dim_tex = Rc::new(TextureRegion {
+            width,
+            height,
+            ..(*white).clone()
+        });
+        let title_text = TextSprite::new(
+            text_renderer.clone(),
+            "Paused",
+            width / 2.0,
+            height / 4.0,
+            0.6,
+            Alignment::Center,
+        );
+        let choice_texts = (0..Self::CHOICES.len())
+            .map(|i| {
+                TextSprite::new(
+                    text_renderer.clone(),
+                    "",
+                    width / 2.0,
+                    height / 2.0 + i as f32 * 64.0,
+                    0.4,
+                    Alignment::Center,
+                )
+            })
+            .collect();
+
+        let mut menu = PauseMenu {
+            batch: DrawBatch::new(ortho),
+            dim_tex,
+            title_text,
+            choice_texts,
+            selected: 0,
+        };
+        menu.select(0);
+        menu
+    }
+
+    fn select(&mut self, selected: usize) {
+        self.selected = selected;
+        for (i, (text, (_, name))) in self.choice_texts.iter_mut().zip(Self::CHOICES).enumerate() {
+            text.set_text(&if i == selected {
+                format!("> {} <", name)
+            } else {
+                name.to_string()
+            });
+        }
+    }
+
+    // escape is the same as picking resume
+    pub fn handle_event(&mut self, event: &Event) -> Option<PauseChoice> {
+        let keycode = match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => *keycode,
+            _ => return None,
+        };
+        let count = Self::CHOICES.len();
+        match keycode {
+            Keycode::Up => self.select((self.selected + count - 1) % count),
+            Keycode::Down => self.select((self.selected + 1) % count),
+            Keycode::Return | Keycode::KpEnter => return Some(Self::CHOICES[self.selected].0),
+            Keycode::Escape => return Some(PauseChoice::Resume),
+            _ => {}
+        }
+        None
+    }
 
+    pub fn draw(&mut self) {
+        self.batch.add(
+            self.dim_tex.clone(),
+            Vector2::new(0.0, 0.0),
+            1.0,
+            Origin::TopLeft,
+            0xA0000000,
+            0.0,
+        );
+        self.title_text.add_to_batch(&mut self.batch);
+        for x in &self.choice_texts {
+            x.add_to_batch(&mut self.batch);
+        }
         self.batch.draw();
     }
 }
 
+// what the app should do after the game handles an event
+pub enum GameAction {
+    None,
+    Quit,
+}
+
 pub struct OsuGame {
-    bass: Rc<Bass>,
-    asset_loader: Rc<RefCell<AssetLoader>>,
     audio_manager: Rc<RefCell<AudioManager>>,
     hitobject_manager: Rc<RefCell<HitObjectManager>>,
-    text_renderer: Rc<RefCell<TextRenderer>>,
+    input_manager: InputManager,
+    autoplay: Option<VecDeque<ReplayFrame>>, // plays by itself when set, frames get used up as time goes on
+    score_processor: ScoreProcessor,
 
     hud: OsuHUD,
+    break_overlay: BreakOverlay,
+    pause_menu: PauseMenu,
 
-    width: f32,
-    height: f32,
+    paused: bool,
+    practice: Option<(i32, i32)>, // the music jumps back to the start once it gets to the end
+    practice_start: Option<i32>,  // picked, but still waiting for an end
 }
 
 impl OsuGame {
     pub fn new(
-        bass: Rc<Bass>,
+        audio_backend: Rc<dyn AudioBackend>,
         text_renderer: Rc<RefCell<TextRenderer>>,
         width: f32,
         height: f32,
-        beatmap_path: String,
+        options: &PlayOptions,
+        config: &Config,
     ) -> Result<OsuGame, String> {
-        info!("Opening {}...", beatmap_path);
-        let beatmap_file = match File::open(beatmap_path.clone()) {
-            Ok(x) => x,
-            Err(_) => {
-                return Err("Failed to open beatmap".to_string());
+        info!("Opening {} with {}...", options.beatmap_path, options.mods);
+        let beatmap = match BeatmapSet::open_difficulty(
+            &options.beatmap_path,
+            options.difficulty.as_deref(),
+            options.mods,
+        ) {
+            Ok(x) => Rc::new(x),
+            Err(e) => {
+                return Err(format!("Failed to load beatmap: {:?}", e));
             }
         };
-        let mut folder = PathBuf::from(beatmap_path);
-        folder.pop();
-        let beatmap =
-            match Beatmap::parse(&folder.to_string_lossy(), &mut BufReader::new(beatmap_file)) {
-                Ok(x) => x,
-                Err(_) => {
-                    return Err("Failed to parse beatmap".to_string());
-                }
-            };
+        // the other modes parse fine, but there's nothing to play them with yet
+        if beatmap.mode != Gamemode::Osu {
+            return Err(format!(
+                "Only osu!standard beatmaps can be played for now, this one is {:?}",
+                beatmap.mode
+            ));
+        }
 
-        // TODO: don't hardcode this path when i get config stuff implemented
         let asset_loader = Rc::new(RefCell::new(AssetLoader::new(
-            "C:\\Users\\Khang\\AppData\\Local\\osu!\\Skins\\Luminous",
-            //"F:\\osu!\\skins\\Awesome's Clear Skin v10",
+            config.skin_path.as_deref(),
+            Some(beatmap.set.clone()),
         )));
 
         let audio_manager = Rc::new(RefCell::new(AudioManager::new(
-            bass.clone(),
+            audio_backend,
             asset_loader.clone(),
-            &format!("{}/{}", beatmap.base_path, beatmap.audio_filename),
-        )));
-        // TODO: properly dynamically set the lead in based on storyboard events and stuff
+            &beatmap,
+            config,
+        )?));
         audio_manager
             .borrow_mut()
-            .seek_music(beatmap.hit_objects[0].start as f64 - 1800.0);
+            .seek_music(beatmap.gameplay_start_time() as f64);
         audio_manager.borrow_mut().resume_music();
 
         let hitobject_manager = Rc::new(RefCell::new(HitObjectManager::new(
@@ -127,26 +423,44 @@ impl OsuGame {
             height,
             asset_loader.clone(),
             audio_manager.clone(),
-            Rc::new(beatmap),
+            beatmap.clone(),
         )));
 
-        let hud = OsuHUD::new(
-            width,
-            height,
-            hitobject_manager.clone(),
-            text_renderer.clone(),
-        );
+        let input_manager = {
+            let hitobject_manager = hitobject_manager.borrow();
+            InputManager::new(
+                hitobject_manager.playfield_scale,
+                hitobject_manager.playfield_offset,
+                &config.keys,
+            )
+        };
+
+        let score_processor = ScoreProcessor::new(&beatmap);
+        let autoplay = options
+            .autoplay
+            .then(|| VecDeque::from(autoplay_frames(&beatmap)));
+
+        let hud = OsuHUD::new(width, height, asset_loader.clone(), text_renderer.clone());
+        let break_overlay = BreakOverlay::new(width, height, asset_loader.clone(), beatmap);
+        let pause_menu = PauseMenu::new(width, height, asset_loader, text_renderer);
 
-        Ok(OsuGame {
-            bass,
-            asset_loader,
+        let mut game = OsuGame {
             audio_manager,
             hitobject_manager,
-            text_renderer,
+            input_manager,
+            autoplay,
+            score_processor,
             hud,
-            width,
-            height,
-        })
+            break_overlay,
+            pause_menu,
+            paused: false,
+            practice: None,
+            practice_start: None,
+        };
+        if options.practice.is_some() {
+            game.set_practice(options.practice);
+        }
+        Ok(game)
     }
 
     pub fn get_title(&self) -> String {
@@ -157,19 +471,208 @@ impl OsuGame {
         )
     }
 
-    pub fn update(&mut self) {
+    pub fn music_pos(&self) -> f64 {
+        self.audio_manager.borrow().music_pos()
+    }
+
+    // every object got judged, there's nothing left to play
+    pub fn is_finished(&self) -> bool {
+        self.hitobject_manager.borrow().is_finished()
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> GameAction {
+        if self.paused {
+            match self.pause_menu.handle_event(event) {
+                Some(PauseChoice::Resume) => self.resume(),
+                Some(PauseChoice::Retry) => {
+                    self.restart();
+                    self.resume();
+                }
+                Some(PauseChoice::Quit) => return GameAction::Quit,
+                None => {}
+            }
+            return GameAction::None;
+        }
+
+        if let Event::KeyDown {
+            keycode: Some(keycode),
+            repeat: false,
+            ..
+        } = *event
+        {
+            let time = self.music_pos() as i32;
+            match keycode {
+                Keycode::Escape => self.pause(),
+                Keycode::Backquote => self.restart(),
+                Keycode::Left => self.seek(time - SEEK_STEP),
+                Keycode::Right => self.seek(time + SEEK_STEP),
+                // [ and ] pick where the practice section starts and ends, \ stops practicing
+                Keycode::LeftBracket => {
+                    self.practice_start = Some(time);
+                    self.update_practice_text();
+                }
+                Keycode::RightBracket => match self.practice_start {
+                    Some(start) if time > start => self.set_practice(Some((start, time))),
+                    _ => {}
+                },
+                Keycode::Backslash => self.set_practice(None),
+                _ => {
+                    self.input_manager.handle_event(event);
+                }
+            }
+            return GameAction::None;
+        }
+
+        self.input_manager.handle_event(event);
+        GameAction::None
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.audio_manager.borrow_mut().pause_music();
+        self.pause_menu.select(0);
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.audio_manager.borrow_mut().resume_music();
+        // anything that happened while paused doesn't count
+        self.input_manager.reset(self.music_pos());
+    }
+
+    // back to the very start with a fresh score
+    pub fn restart(&mut self) {
+        let start = self
+            .hitobject_manager
+            .borrow()
+            .beatmap
+            .gameplay_start_time();
+        self.seek_to(start, i32::MIN);
+    }
+
+    // plays from some time in the middle, objects before it get skipped
+    // the music starts a bit earlier so that there's time to get ready
+    // seeking past the end stops at the last object instead of running off the song
+    pub fn seek(&mut self, time: i32) {
+        let beatmap = self.hitobject_manager.borrow().beatmap.clone();
+        let start = beatmap.gameplay_start_time();
+        let end = beatmap.last_object_end().unwrap_or(start).max(start);
+        let lead_in = Beatmap::MIN_LEAD_IN.max(beatmap.difficulty.preempt);
+        let time = time.clamp(start, end);
+        self.seek_to((time - lead_in).max(start), time);
+    }
+
+    fn seek_to(&mut self, music_time: i32, skip_before: i32) {
+        let beatmap = self.hitobject_manager.borrow().beatmap.clone();
+        info!("Seeking to {}ms", music_time);
+
+        self.audio_manager
+            .borrow_mut()
+            .seek_music(music_time as f64);
+        self.hitobject_manager.borrow_mut().reset(skip_before);
+        // a score from partway through doesn't mean anything, so it starts over
+        self.score_processor = ScoreProcessor::new(&beatmap);
+        self.input_manager.reset(music_time as f64);
+        if self.autoplay.is_some() {
+            // clicks meant for skipped objects would hit the next one early
+            let frames = autoplay_frames(&beatmap)
+                .into_iter()
+                .filter(|x| x.time >= music_time)
+                .map(|x| ReplayFrame {
+                    keys: if x.time < skip_before {
+                        OsuKeys::empty()
+                    } else {
+                        x.keys
+                    },
+                    ..x
+                });
+            self.autoplay = Some(frames.collect());
+        }
+        self.hud.update(&self.score_processor);
+    }
+
+    pub fn set_practice(&mut self, practice: Option<(i32, i32)>) {
+        self.practice = practice;
+        self.practice_start = practice.map(|x| x.0);
+        self.update_practice_text();
+        if let Some((start, _)) = practice {
+            self.seek(start);
+        }
+    }
+
+    fn update_practice_text(&mut self) {
+        let text = match (self.practice, self.practice_start) {
+            (Some((start, end)), _) => format!(
+                "Practicing {:.1}s to {:.1}s",
+                start as f64 / 1000.0,
+                end as f64 / 1000.0
+            ),
+            (None, Some(start)) => format!("Practice from {:.1}s", start as f64 / 1000.0),
+            (None, None) => String::new(),
+        };
+        self.hud.set_practice_text(&text);
+    }
+
+    // ticks is sdl's timer, which is what event timestamps use
+    pub fn update(&mut self, ticks: u32) {
         self.audio_manager.borrow_mut().update();
+        if self.paused {
+            return;
+        }
 
-        let audio_time = self.audio_manager.borrow().music_pos() as i32;
-        self.hitobject_manager.borrow_mut().update(audio_time);
+        let audio_time = self.audio_manager.borrow().music_pos();
+        if let Some((start, end)) = self.practice {
+            if audio_time >= end as f64 {
+                self.seek(start);
+                return;
+            }
+        }
+        let was_failed = self.score_processor.failed;
+        {
+            let mut hitobject_manager = self.hitobject_manager.borrow_mut();
+            let frames = self.input_manager.take_frames();
+            match self.autoplay.as_mut() {
+                Some(autoplay) => {
+                    while let Some(frame) = autoplay.front() {
+                        if frame.time as f64 > audio_time {
+                            break;
+                        }
+                        hitobject_manager.handle_input(&InputFrame::from(frame));
+                        autoplay.pop_front();
+                    }
+                }
+                None => {
+                    for frame in frames {
+                        hitobject_manager.handle_input(&frame);
+                    }
+                }
+            }
+            hitobject_manager.update(audio_time as i32);
 
-        self.hud.update();
+            for x in hitobject_manager.take_judgements() {
+                self.score_processor.apply(&x);
+            }
+        }
+        self.score_processor.update(audio_time as i32);
+        if self.score_processor.failed && !was_failed {
+            // TODO: fail animation
+            info!("Failed at {}ms", audio_time as i32);
+        }
+        self.input_manager
+            .sync(ticks, audio_time, self.audio_manager.borrow().music_rate());
+
+        self.hud.update(&self.score_processor);
     }
 
     pub fn draw(&mut self) {
         let audio_time = self.audio_manager.borrow().music_pos() as i32;
         self.hitobject_manager.borrow_mut().draw(audio_time);
+        // the bars go over the playfield, like stable
+        self.break_overlay.draw(audio_time);
 
         self.hud.draw();
+        if self.paused {
+            self.pause_menu.draw();
+        }
     }
 }
@@ -1,18 +1,20 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::VecDeque, f32::consts::PI, rc::Rc};
 
 use intervaltree::IntervalTree;
 
 use crate::{
-    beatmap::{HitObject, HitObjectType},
-    framework::render::{DrawBatch, Origin},
+    beatmap::{Difficulty, HitObject, HitObjectType, Mods},
+    framework::render::{DrawBatch, Origin, SliderRenderer},
     math::{interp_time, Easing, Vector2},
+    replay::OsuKeys,
     Beatmap,
 };
 
 use super::{
     asset_loader::AssetLoader,
     audio_manager::AudioManager,
-    game::{OSU_PLAYFIELD_HEIGHT, OSU_PLAYFIELD_WIDTH},
+    game::{OSU_NATIVE_TO_PLAYFIELD_RATIO, OSU_PLAYFIELD_HEIGHT, OSU_PLAYFIELD_WIDTH},
+    input::InputFrame,
 };
 
 // only what's needed for standard
@@ -37,46 +39,151 @@ bitflags::bitflags! {
     }
 }
 
+// the follow circle is bigger than the ball once it's being held
+const FOLLOW_CIRCLE_SCALE: f32 = 2.4;
+
+// stable's spinner speed cap, in radians per ms
+const MAX_SPIN_SPEED: f32 = 477.0 / 60000.0 * 2.0 * PI;
+// how far back the rpm counter looks
+const RPM_WINDOW: f64 = 500.0;
+
+// something that should affect the score, in the order it happened
+#[derive(Debug, Clone, Copy)]
+pub struct Judgement {
+    pub time: i32,
+    pub obj_idx: usize,
+    pub pos: Vector2,
+    // slider parts get missed with their own type added on, so they can be told apart from object misses
+    pub result: IncreaseScoreType,
+}
+
 pub struct GameplaySliderInfo {
     pub is_sliding: bool,
     pub slide_update: i32,
+    pub next_edge: usize, // the head's sound plays on hit, so this starts at the first repeat
+
+    pub head_judged: bool,
+    pub nested: Vec<(i32, IncreaseScoreType, Vector2)>, // ticks, repeats and the end in order
+    pub next_nested: usize,
+    pub nested_hit: usize, // includes the head
+}
+
+pub struct GameplaySpinnerInfo {
+    pub rotation: f32, // radians, either direction counts
+    pub rpm: f32,
+    pub required_spins: i32,
+    spins_awarded: i32,
+    last_input: Option<(f64, f32)>, // time, angle
+    history: VecDeque<(f64, f32)>,
 }
 
 pub struct GameplayHitObject {
-    audio_manager: Rc<RefCell<AudioManager>>,
+    audio_manager: Option<Rc<RefCell<AudioManager>>>, // none when there's nothing to play sounds with
     beatmap: Rc<Beatmap>,
     inner_obj_idx: usize,
     combo_color: u32,
     pub hit_time: Option<i32>,
+    pub judgement: Option<IncreaseScoreType>,
     pub slider_info: Option<GameplaySliderInfo>,
+    pub spinner_info: Option<GameplaySpinnerInfo>,
 }
 
 impl GameplayHitObject {
     pub fn new(
-        audio_manager: Rc<RefCell<AudioManager>>,
+        audio_manager: Option<Rc<RefCell<AudioManager>>>,
         beatmap: Rc<Beatmap>,
         inner_obj_idx: usize,
+        combo_colours: &[u32],
     ) -> GameplayHitObject {
-        let slider_info = if beatmap.hit_objects[inner_obj_idx].object_type == HitObjectType::Slider
-        {
-            Some(GameplaySliderInfo {
-                is_sliding: false,
-                slide_update: beatmap.hit_objects[inner_obj_idx].start - 1000, // random number
-            })
-        } else {
-            None
-        };
+        let (slider_info, spinner_info) = Self::initial_state(&beatmap, inner_obj_idx);
+
+        let combo_index = beatmap.hit_objects[inner_obj_idx].combo_index as usize;
+        let combo_color = combo_colours[combo_index % combo_colours.len()];
 
         GameplayHitObject {
             audio_manager,
             beatmap,
-            combo_color: 0x0000FF, // BGR, just a placeholder for now...
+            combo_color, // BGR
             inner_obj_idx,
             hit_time: None,
+            judgement: None,
             slider_info,
+            spinner_info,
         }
     }
 
+    // what the slider and spinner state look like before anything happens
+    fn initial_state(
+        beatmap: &Beatmap,
+        inner_obj_idx: usize,
+    ) -> (Option<GameplaySliderInfo>, Option<GameplaySpinnerInfo>) {
+        let obj = &beatmap.hit_objects[inner_obj_idx];
+        let slider_info = obj.slider_info.as_ref().map(|x| {
+            let mut nested: Vec<(i32, IncreaseScoreType, Vector2)> = x
+                .small_ticks
+                .iter()
+                .map(|tick| (tick.time, IncreaseScoreType::SLIDER_TICK, tick.pos))
+                .chain(
+                    x.end_ticks
+                        .iter()
+                        .filter(|tick| tick.is_repeat)
+                        .map(|tick| (tick.time, IncreaseScoreType::SLIDER_REPEAT, tick.pos)),
+                )
+                .collect();
+            // the end gets checked a bit early, same as stable
+            if let Some(&end) = x.score_times.last() {
+                nested.push((end, IncreaseScoreType::SLIDER_END, obj.unstacked_end_pos));
+            }
+            nested.sort_by_key(|x| x.0);
+            // tick positions are all unstacked
+            for x in nested.iter_mut() {
+                x.2 = x.2 - obj.stack_offset;
+            }
+
+            GameplaySliderInfo {
+                is_sliding: false,
+                slide_update: obj.start - 1000, // random number
+                next_edge: 1,
+                head_judged: false,
+                nested,
+                next_nested: 0,
+                nested_hit: 0,
+            }
+        });
+        let spinner_info = if obj.object_type == HitObjectType::Spinner {
+            let length = (obj.end - obj.start) as f32 / 1000.0;
+            Some(GameplaySpinnerInfo {
+                rotation: 0.0,
+                rpm: 0.0,
+                required_spins: (length * beatmap.difficulty.spins_per_second) as i32,
+                spins_awarded: 0,
+                last_input: None,
+                history: VecDeque::new(),
+            })
+        } else {
+            None
+        };
+
+        (slider_info, spinner_info)
+    }
+
+    // back to how it was before the object got played, for seeking backwards
+    pub fn reset(&mut self) {
+        let (slider_info, spinner_info) = Self::initial_state(&self.beatmap, self.inner_obj_idx);
+        self.hit_time = None;
+        self.judgement = None;
+        self.slider_info = slider_info;
+        self.spinner_info = spinner_info;
+    }
+
+    // done without counting for anything, for objects before wherever playing started
+    pub fn skip(&mut self) {
+        if let Some(slider_info) = self.slider_info.as_mut() {
+            slider_info.head_judged = true;
+        }
+        self.judgement = Some(IncreaseScoreType::IGNORE);
+    }
+
     pub fn inner_obj(&self) -> &HitObject {
         &self.beatmap.hit_objects[self.inner_obj_idx]
     }
@@ -97,6 +204,10 @@ impl GameplayHitObject {
         self.inner_obj().end
     }
 
+    pub fn combo_number(&self) -> i32 {
+        self.inner_obj().combo_number
+    }
+
     pub fn is_hitcircle(&self) -> bool {
         self.inner_obj().object_type == HitObjectType::Circle
     }
@@ -109,6 +220,18 @@ impl GameplayHitObject {
         self.inner_obj().object_type == HitObjectType::Spinner
     }
 
+    pub fn is_judged(&self) -> bool {
+        self.judgement.is_some()
+    }
+
+    // whether the object can still be clicked
+    pub fn head_judged(&self) -> bool {
+        match &self.slider_info {
+            Some(x) => x.head_judged,
+            None => self.is_spinner() || self.is_judged(),
+        }
+    }
+
     pub fn start_slide(&mut self, time: i32) {
         if let Some(slider_info) = self.slider_info.as_mut() {
             if !slider_info.is_sliding {
@@ -127,10 +250,39 @@ impl GameplayHitObject {
         }
     }
 
-    pub fn play_hitsound(&mut self) {
-        // TODO: placeholder. needs to properly get all the data required like timing points and shit
-        let pan = (self.start_pos().x / 512.0 - 0.5) * 0.8;
-        self.audio_manager.borrow_mut().play_test_sample(pan);
+    pub fn play_hitsound(&mut self, edge: usize, pos: Vector2) {
+        let audio_manager = match &self.audio_manager {
+            Some(x) => x,
+            None => return,
+        };
+        let pan = (pos.x / 512.0 - 0.5) * 0.8;
+        let hitsound =
+            self.inner_obj()
+                .edge_hitsound(edge, &self.beatmap.timing, self.beatmap.sample_set);
+        audio_manager.borrow_mut().play_hitsound(&hitsound, pan);
+    }
+
+    // plays the repeat and tail sounds once the ball gets to them
+    pub fn update_slider_edges(&mut self, time: i32) {
+        let beatmap = self.beatmap.clone();
+        let end_ticks = match &beatmap.hit_objects[self.inner_obj_idx].slider_info {
+            Some(x) => &x.end_ticks,
+            None => return,
+        };
+
+        loop {
+            let slider_info = match self.slider_info.as_mut() {
+                Some(x) if x.is_sliding => x,
+                _ => return,
+            };
+            let tick = match end_ticks.get(slider_info.next_edge - 1) {
+                Some(x) if x.time <= time => x,
+                _ => return,
+            };
+            let edge = slider_info.next_edge;
+            slider_info.next_edge += 1;
+            self.play_hitsound(edge, tick.pos);
+        }
     }
 
     pub fn hit(&mut self, hit_time: i32) -> IncreaseScoreType {
@@ -145,14 +297,402 @@ impl GameplayHitObject {
             _ => IncreaseScoreType::MISS,
         };
 
-        self.play_hitsound();
+        self.play_hitsound(0, self.start_pos());
 
-        if self.is_slider() {
+        // slider heads only count towards the slider's judgement at the end
+        // stable scores them the same as repeats
+        if let Some(slider_info) = self.slider_info.as_mut() {
+            slider_info.head_judged = true;
+            if hit_value == IncreaseScoreType::MISS {
+                return IncreaseScoreType::MISS | IncreaseScoreType::SLIDER_REPEAT;
+            }
+            slider_info.nested_hit += 1;
             self.start_slide(hit_time);
+            return IncreaseScoreType::SLIDER_REPEAT;
         }
 
+        self.judgement = Some(hit_value);
         hit_value
     }
+
+    // the object went past its hit window without getting clicked
+    pub fn miss_head(&mut self) -> IncreaseScoreType {
+        match self.slider_info.as_mut() {
+            Some(slider_info) => {
+                slider_info.head_judged = true;
+                IncreaseScoreType::MISS | IncreaseScoreType::SLIDER_REPEAT
+            }
+            None => {
+                self.judgement = Some(IncreaseScoreType::MISS);
+                IncreaseScoreType::MISS
+            }
+        }
+    }
+
+    // holding a key inside the ball starts tracking, letting go or leaving the follow circle stops it
+    pub fn update_tracking(&mut self, time: i32, pos: Vector2, holding: bool) {
+        let (is_sliding, head_judged) = match &self.slider_info {
+            Some(x) => (x.is_sliding, x.head_judged),
+            None => return,
+        };
+        if self.is_judged() || (time < self.start_time() && !head_judged) {
+            return;
+        }
+
+        let (ball_pos, _) = self.inner_obj().ball_pos_at_time(time);
+        let radius =
+            self.beatmap.difficulty.obj_radius * if is_sliding { FOLLOW_CIRCLE_SCALE } else { 1.0 };
+        if holding && pos.distance(ball_pos) <= radius {
+            self.start_slide(time);
+        } else {
+            self.stop_slide(time);
+        }
+    }
+
+    // ticks, repeats and the end, plus the slider itself once it's done
+    pub fn judge_nested(&mut self, time: i32, out: &mut Vec<(i32, Vector2, IncreaseScoreType)>) {
+        let (end, end_pos) = (self.end_time(), self.end_pos());
+        let slider_info = match self.slider_info.as_mut() {
+            Some(x) => x,
+            None => return,
+        };
+        if self.judgement.is_some() {
+            return;
+        }
+
+        while let Some(&(nested_time, kind, pos)) = slider_info.nested.get(slider_info.next_nested)
+        {
+            if nested_time > time {
+                return;
+            }
+            slider_info.next_nested += 1;
+            if slider_info.is_sliding {
+                slider_info.nested_hit += 1;
+                out.push((nested_time, pos, kind));
+            } else {
+                out.push((nested_time, pos, IncreaseScoreType::MISS | kind));
+            }
+        }
+        if slider_info.nested.is_empty() && time < end {
+            return;
+        }
+
+        let total = slider_info.nested.len() + 1;
+        let hit = slider_info.nested_hit;
+        let result = if hit == total {
+            IncreaseScoreType::HIT_300
+        } else if hit * 2 >= total {
+            IncreaseScoreType::HIT_100
+        } else if hit > 0 {
+            IncreaseScoreType::HIT_50
+        } else {
+            IncreaseScoreType::MISS
+        };
+        slider_info.is_sliding = false;
+        slider_info.slide_update = end;
+        self.judgement = Some(result);
+        out.push((end, end_pos, result));
+    }
+
+    // every full spin is worth something, and going past the requirement gives bonus points
+    pub fn spin(
+        &mut self,
+        time: f64,
+        pos: Vector2,
+        holding: bool,
+        out: &mut Vec<(i32, Vector2, IncreaseScoreType)>,
+    ) {
+        let (start, end) = (self.start_time() as f64, self.end_time() as f64);
+        // the cap is in real time, so dt lets more rotation happen per beatmap ms
+        let max_speed = MAX_SPIN_SPEED * self.beatmap.difficulty.mods.clock_rate() as f32;
+        let spinner_info = match self.spinner_info.as_mut() {
+            Some(x) => x,
+            None => return,
+        };
+        if self.judgement.is_some() || time < start {
+            return;
+        }
+        let time = time.min(end);
+
+        let centre = Vector2::new(OSU_PLAYFIELD_WIDTH as f32, OSU_PLAYFIELD_HEIGHT as f32) / 2.0;
+        let diff = pos - centre;
+        let angle = diff.y.atan2(diff.x);
+
+        match spinner_info.last_input {
+            // updates without any movement would make the speed cap too strict on the next real one
+            Some((_, last_angle)) if last_angle == angle => {}
+            Some((last_time, last_angle)) => {
+                if holding {
+                    let mut delta = angle - last_angle;
+                    if delta > PI {
+                        delta -= 2.0 * PI;
+                    } else if delta < -PI {
+                        delta += 2.0 * PI;
+                    }
+                    let delta = delta.abs().min(max_speed * (time - last_time) as f32);
+                    spinner_info.rotation += delta;
+                    spinner_info.history.push_back((time, delta));
+                }
+                spinner_info.last_input = Some((time, angle));
+            }
+            None => spinner_info.last_input = Some((time, angle)),
+        }
+
+        while let Some(&(x, _)) = spinner_info.history.front() {
+            if x >= time - RPM_WINDOW {
+                break;
+            }
+            spinner_info.history.pop_front();
+        }
+        let recent: f32 = spinner_info.history.iter().map(|x| x.1).sum();
+        spinner_info.rpm = recent / (2.0 * PI) * (60000.0 / RPM_WINDOW as f32);
+
+        let spins = (spinner_info.rotation / (2.0 * PI)) as i32;
+        while spinner_info.spins_awarded < spins {
+            spinner_info.spins_awarded += 1;
+            let kind = if spinner_info.spins_awarded > spinner_info.required_spins {
+                IncreaseScoreType::SPINNER_BONUS
+            } else {
+                IncreaseScoreType::SPINNER_SPIN
+            };
+            out.push((time as i32, centre, kind));
+        }
+    }
+
+    // spinners get judged on how much of the requirement got done
+    pub fn finish_spinner(&mut self) -> Option<IncreaseScoreType> {
+        let spinner_info = self.spinner_info.as_ref()?;
+        if self.judgement.is_some() {
+            return None;
+        }
+
+        let progress = if spinner_info.required_spins > 0 {
+            spinner_info.rotation / (2.0 * PI) / spinner_info.required_spins as f32
+        } else {
+            1.0
+        };
+        let result = if progress >= 1.0 {
+            IncreaseScoreType::HIT_300
+        } else if progress > 0.9 {
+            IncreaseScoreType::HIT_100
+        } else if progress > 0.75 {
+            IncreaseScoreType::HIT_50
+        } else {
+            IncreaseScoreType::MISS
+        };
+        self.judgement = Some(result);
+        Some(result)
+    }
+}
+
+// everything that decides what got hit, without anything that draws or plays sounds
+pub struct HitObjectJudge {
+    pub beatmap: Rc<Beatmap>,
+
+    // in time order, everything before first_active is fully judged
+    objs: Vec<Rc<RefCell<GameplayHitObject>>>,
+    first_active: usize,
+    input: InputFrame,
+    judgements: Vec<Judgement>,
+}
+
+impl HitObjectJudge {
+    pub fn new(beatmap: Rc<Beatmap>, objs: Vec<Rc<RefCell<GameplayHitObject>>>) -> HitObjectJudge {
+        HitObjectJudge {
+            beatmap,
+            objs,
+            first_active: 0,
+            input: Default::default(),
+            judgements: Vec::new(),
+        }
+    }
+
+    // for replays and tests, nothing gets drawn so the colours don't matter
+    pub fn headless(beatmap: Rc<Beatmap>) -> HitObjectJudge {
+        let objs = (0..beatmap.hit_objects.len())
+            .map(|i| {
+                Rc::new(RefCell::new(GameplayHitObject::new(
+                    None,
+                    beatmap.clone(),
+                    i,
+                    &[0xFFFFFF],
+                )))
+            })
+            .collect();
+        HitObjectJudge::new(beatmap, objs)
+    }
+
+    // objects that have started but haven't been fully judged yet
+    fn active_objs(
+        &self,
+        time: i32,
+    ) -> impl Iterator<Item = (usize, &Rc<RefCell<GameplayHitObject>>)> {
+        self.objs
+            .iter()
+            .enumerate()
+            .skip(self.first_active)
+            .take_while(move |x| x.1.borrow().start_time() <= time)
+    }
+
+    // misses anything that can't be hit anymore and judges slider ticks and spinners up to the given time
+    fn advance(&mut self, time: i32) {
+        let hit_50 = self.beatmap.difficulty.hit_50;
+        let mut judgements = Vec::new();
+        for (i, x) in self.active_objs(time) {
+            let mut x = x.borrow_mut();
+            let mut results = Vec::new();
+
+            let miss_time = x.start_time() + hit_50;
+            if !x.head_judged() && time > miss_time {
+                results.push((miss_time, x.start_pos(), x.miss_head()));
+            }
+            x.judge_nested(time, &mut results);
+            if x.is_spinner() && time >= x.end_time() {
+                if let Some(result) = x.finish_spinner() {
+                    results.push((x.end_time(), x.start_pos(), result));
+                }
+            }
+
+            judgements.extend(results.into_iter().map(|(time, pos, result)| Judgement {
+                time,
+                obj_idx: i,
+                pos,
+                result,
+            }));
+        }
+        self.judgements.extend(judgements);
+        self.skip_judged();
+    }
+
+    fn skip_judged(&mut self) {
+        while self
+            .objs
+            .get(self.first_active)
+            .map(|x| x.borrow().is_judged())
+            .unwrap_or(false)
+        {
+            self.first_active += 1;
+        }
+    }
+
+    // slider tracking and spinners care about the input even when nothing's being pressed
+    fn apply_input(&mut self, time: f64) {
+        let holding = !self.input.keys.gameplay().is_empty();
+        let mut judgements = Vec::new();
+        for (i, x) in self.active_objs(time as i32) {
+            let mut x = x.borrow_mut();
+            let mut results = Vec::new();
+            x.update_tracking(time as i32, self.input.pos, holding);
+            x.spin(time, self.input.pos, holding, &mut results);
+            judgements.extend(results.into_iter().map(|(time, pos, result)| Judgement {
+                time,
+                obj_idx: i,
+                pos,
+                result,
+            }));
+        }
+        self.judgements.extend(judgements);
+    }
+
+    // only the earliest object that still needs to be clicked can be hit, clicking anything else does nothing
+    fn handle_press(&mut self, time: i32) {
+        let preempt = self.beatmap.difficulty.preempt;
+        let hit_50 = self.beatmap.difficulty.hit_50;
+        let radius = self.beatmap.difficulty.obj_radius;
+
+        let target = self
+            .objs
+            .iter()
+            .enumerate()
+            .skip(self.first_active)
+            .take_while(|x| x.1.borrow().start_time() - preempt <= time)
+            .find(|x| !x.1.borrow().head_judged());
+        if let Some((i, x)) = target {
+            let mut x = x.borrow_mut();
+            if self.input.pos.distance(x.start_pos()) <= radius
+                && (time - x.start_time()).abs() < hit_50
+            {
+                let result = x.hit(time);
+                self.judgements.push(Judgement {
+                    time,
+                    obj_idx: i,
+                    pos: x.start_pos(),
+                    result,
+                });
+            }
+        }
+    }
+
+    pub fn handle_input(&mut self, frame: &InputFrame) {
+        let time = frame.time as i32;
+        self.advance(time);
+
+        let pressed = frame.keys.gameplay() - self.input.keys.gameplay();
+        self.input = *frame;
+        if !pressed.is_empty() {
+            self.handle_press(time);
+        }
+        self.apply_input(frame.time);
+    }
+
+    pub fn update(&mut self, time: i32) {
+        self.advance(time);
+        // the ball keeps moving even if the cursor doesn't
+        self.apply_input(time as f64);
+    }
+
+    // everything that happened since the last call, roughly in order
+    pub fn take_judgements(&mut self) -> Vec<Judgement> {
+        std::mem::take(&mut self.judgements)
+    }
+
+    // puts every object back to how it started, anything starting before the given time gets skipped
+    pub fn reset(&mut self, skip_before: i32) {
+        for x in &self.objs {
+            let mut x = x.borrow_mut();
+            x.reset();
+            if x.start_time() < skip_before {
+                x.skip();
+            }
+        }
+        self.judgements.clear();
+        self.input.keys = OsuKeys::empty();
+        self.first_active = 0;
+        self.skip_judged();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.first_active >= self.objs.len()
+    }
+
+    // where the last input that got judged was
+    pub fn cursor_pos(&self) -> Vector2 {
+        self.input.pos
+    }
+}
+
+// how much of the path is showing, as fractions of its length
+// it snakes in while fading in, and snakes out behind the ball on the last slide
+fn snaked_range(difficulty: &Difficulty, obj: &HitObject, slides: i32, time: i32) -> (f32, f32) {
+    let preempt = difficulty.preempt;
+    let snake_in_time = preempt - difficulty.preempt_slider_complete;
+    let to = ((time - (obj.start - preempt)) as f32 / snake_in_time.max(1) as f32).clamp(0.0, 1.0);
+
+    if slides < 1 || time <= obj.start || obj.end <= obj.start {
+        return (0.0, to);
+    }
+    let span_length = (obj.end - obj.start) as f32 / slides as f32;
+    let span = (time - obj.start) as f32 / span_length - (slides - 1) as f32;
+    if span <= 0.0 {
+        return (0.0, to);
+    }
+    let progress = span.min(1.0);
+    // odd slide counts finish at the tail, even ones go back to the head
+    if slides % 2 == 1 {
+        (progress, to)
+    } else {
+        (0.0, to.min(1.0 - progress))
+    }
 }
 
 pub struct HitObjectManager {
@@ -161,8 +701,14 @@ pub struct HitObjectManager {
     pub beatmap: Rc<Beatmap>,
     gameplay_objs: IntervalTree<i32, Rc<RefCell<GameplayHitObject>>>,
     visible_objs: Vec<Rc<RefCell<GameplayHitObject>>>,
+    judge: HitObjectJudge,
+
+    // playfield = window * scale + offset
+    pub playfield_scale: f32,
+    pub playfield_offset: Vector2,
 
     batch: DrawBatch,
+    slider_renderer: SliderRenderer,
 }
 
 impl HitObjectManager {
@@ -187,28 +733,41 @@ impl HitObjectManager {
             1.0f32,
         );
 
+        let combo_colours = if !beatmap.combo_colours.is_empty() {
+            beatmap.combo_colours.clone()
+        } else {
+            asset_loader.borrow().skin.combo_colours.clone()
+        };
+
         // TODO: i can totally just do this in one iteration, but it would probably really suck to read...
         let mut gameplay_objs = Vec::with_capacity(beatmap.hit_objects.len());
         for (i, _x) in beatmap.hit_objects.iter().enumerate() {
             gameplay_objs.push(Rc::new(RefCell::new(GameplayHitObject::new(
-                audio_manager.clone(),
+                Some(audio_manager.clone()),
                 beatmap.clone(),
                 i,
+                &combo_colours,
             ))));
         }
+        let judge = HitObjectJudge::new(beatmap.clone(), gameplay_objs.clone());
         let gameplay_objs = IntervalTree::from_iter(gameplay_objs.into_iter().map(|x| {
             let start = x.borrow().start_time();
             let end = x.borrow().end_time() + 1;
             (start..end.max(start + 1), x)
         }));
 
+        let slider_renderer = SliderRenderer::new(ortho, beatmap.difficulty.obj_radius);
         HitObjectManager {
             asset_loader,
             audio_manager,
             beatmap,
             gameplay_objs,
             visible_objs: Default::default(),
+            judge,
+            playfield_scale: scale,
+            playfield_offset: Vector2::new(-extra_x, extra_y),
             batch: DrawBatch::new(ortho),
+            slider_renderer,
         }
     }
 
@@ -229,23 +788,32 @@ impl HitObjectManager {
         }
     }
 
-    // just for debug purposes...
-    fn update_force_hit(&mut self, time: i32) {
-        for x in &self.visible_objs {
-            let mut x = x.borrow_mut();
-            let start = x.start_time();
-            if start <= time && x.hit_time.is_none() {
-                x.hit(start);
-            }
-        }
+    pub fn handle_input(&mut self, frame: &InputFrame) {
+        self.judge.handle_input(frame);
     }
 
     pub fn update(&mut self, time: i32) {
         self.update_visible_objs(time);
-        self.update_force_hit(time);
+        self.judge.update(time);
+        for x in &self.visible_objs {
+            x.borrow_mut().update_slider_edges(time);
+        }
+    }
+
+    pub fn take_judgements(&mut self) -> Vec<Judgement> {
+        self.judge.take_judgements()
     }
 
-    fn draw_hitcircles(&mut self, time: i32) {
+    pub fn reset(&mut self, skip_before: i32) {
+        self.judge.reset(skip_before);
+        self.visible_objs.clear();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.judge.is_finished()
+    }
+
+    fn draw_hitcircles(&mut self, time: i32, objs: &[Rc<RefCell<GameplayHitObject>>]) {
         let hitcircle = self.asset_loader.borrow_mut().lookup_tex("hitcircle");
         let hitcircle_scale = self.beatmap.difficulty.obj_radius * 2.0
             / hitcircle.width.max(hitcircle.height)
@@ -264,10 +832,29 @@ impl HitObjectManager {
             / approachcircle.width.max(approachcircle.height)
             * approachcircle.dpi_scale;
 
+        let (prefix, overlap, overlay_above_number) = {
+            let skin = &self.asset_loader.borrow().skin;
+            (
+                skin.hit_circle_prefix.clone(),
+                skin.hit_circle_overlap,
+                skin.hit_circle_overlay_above_number,
+            )
+        };
+        let mut number_texs = Vec::with_capacity(10);
+        for i in 0..10 {
+            number_texs.push(
+                self.asset_loader
+                    .borrow_mut()
+                    .lookup_tex(&format!("{}-{}", prefix, i)),
+            );
+        }
+        let number_scale = self.beatmap.difficulty.obj_radius * 2.0 / 128.0;
+
         let preempt = self.beatmap.difficulty.preempt;
         let hit_50 = self.beatmap.difficulty.hit_50;
+        let hidden = self.beatmap.difficulty.mods.contains(Mods::HIDDEN);
 
-        for x in &self.visible_objs {
+        for x in objs {
             let x = x.borrow();
             let start_time = x.start_time();
             let hit_time = x.hit_time;
@@ -298,6 +885,22 @@ impl HitObjectManager {
             } else {
                 0x0
             };
+            // hidden fades everything out long before it has to be hit
+            let circle_alpha = if hidden {
+                let fade_out_start = start_time - preempt + (preempt as f32 * 0.4) as i32;
+                let fade = interp_time(
+                    1.0,
+                    0.0,
+                    fade_out_start as f32,
+                    fade_out_start as f32 + preempt as f32 * 0.3,
+                    time as f32,
+                    Easing::Linear,
+                )
+                .clamp(0.0, 1.0);
+                (circle_alpha as f32 * fade) as u32
+            } else {
+                circle_alpha
+            };
             let approach_alpha = (interp_time(
                 0.0,
                 0.9,
@@ -332,17 +935,59 @@ impl HitObjectManager {
                 0.0,
             );
 
-            self.batch.add(
-                hitcircleoverlay.clone(),
-                x.start_pos(),
-                hitcircleoverlay_scale * circle_scale,
-                Origin::Center,
-                0xFFFFFF | (circle_alpha << 24),
-                0.0,
-            );
+            if !overlay_above_number {
+                self.batch.add(
+                    hitcircleoverlay.clone(),
+                    x.start_pos(),
+                    hitcircleoverlay_scale * circle_scale,
+                    Origin::Center,
+                    0xFFFFFF | (circle_alpha << 24),
+                    0.0,
+                );
+            }
+
+            // numbers disappear instantly once the circle is hit
+            if hit_time.is_none() {
+                let digits: Vec<_> = x
+                    .combo_number()
+                    .to_string()
+                    .bytes()
+                    .map(|d| number_texs[(d - b'0') as usize].clone())
+                    .collect();
+                let widths: Vec<f32> = digits
+                    .iter()
+                    .map(|d| d.width / d.dpi_scale * number_scale)
+                    .collect();
+                let spacing = -overlap * number_scale;
+                let total_width = widths.iter().sum::<f32>() + spacing * (digits.len() - 1) as f32;
+
+                let mut digit_x = x.start_pos().x - total_width / 2.0;
+                for (digit, width) in digits.into_iter().zip(widths) {
+                    self.batch.add(
+                        digit,
+                        Vector2::new(digit_x + width / 2.0, x.start_pos().y),
+                        number_scale,
+                        Origin::Center,
+                        0xFFFFFF | (circle_alpha << 24),
+                        0.0,
+                    );
+                    digit_x += width + spacing;
+                }
+            }
+
+            if overlay_above_number {
+                self.batch.add(
+                    hitcircleoverlay.clone(),
+                    x.start_pos(),
+                    hitcircleoverlay_scale * circle_scale,
+                    Origin::Center,
+                    0xFFFFFF | (circle_alpha << 24),
+                    0.0,
+                );
+            }
 
             // approach circle
-            if time <= start_time && x.hit_time.is_none() {
+            if time <= start_time && x.hit_time.is_none() && !hidden {
                 let scale = interp_time(
                     4.0,
                     1.0,
@@ -363,8 +1008,79 @@ impl HitObjectManager {
         }
     }
 
-    fn draw_slider_objs(&mut self, time: i32) {
-        // slider stuff (tracking circle, ticks, etc) are drawn under all hitcircles
+    // bodies don't go through the batch, so this draws right away
+    fn draw_slider_body(&mut self, time: i32, x: &GameplayHitObject) {
+        let preempt = self.beatmap.difficulty.preempt;
+        let hidden = self.beatmap.difficulty.mods.contains(Mods::HIDDEN);
+        let (track_override, border) = {
+            let skin = &self.asset_loader.borrow().skin;
+            (
+                self.beatmap
+                    .slider_track_override
+                    .or(skin.slider_track_override),
+                self.beatmap.slider_border.unwrap_or(skin.slider_border),
+            )
+        };
+
+        let obj = x.inner_obj();
+        let slider_info = match &obj.slider_info {
+            Some(x) => x,
+            None => return,
+        };
+
+        let fade_in = interp_time(
+            0.0,
+            1.0,
+            (obj.start - preempt) as f32,
+            (obj.start - preempt + 400) as f32,
+            time as f32,
+            Easing::Linear,
+        );
+        let fade_out = interp_time(
+            1.0,
+            0.0,
+            obj.end as f32,
+            (obj.end + 240) as f32,
+            time as f32,
+            Easing::Linear,
+        );
+        let mut alpha = fade_in.min(fade_out).clamp(0.0, 1.0);
+        // hidden fades the body out over the whole slider instead
+        if hidden {
+            let fade_out_start = obj.start - preempt + (preempt as f32 * 0.4) as i32;
+            alpha *= interp_time(
+                1.0,
+                0.0,
+                fade_out_start as f32,
+                obj.end.max(fade_out_start + 1) as f32,
+                time as f32,
+                Easing::Linear,
+            )
+            .clamp(0.0, 1.0);
+        }
+
+        let (from, to) = snaked_range(&self.beatmap.difficulty, obj, slider_info.slides, time);
+        self.slider_renderer.add(
+            x.inner_obj_idx,
+            (from, to),
+            track_override.unwrap_or(x.combo_color),
+            border,
+            alpha,
+            || {
+                slider_info
+                    .curve
+                    .points_between(from, to)
+                    .into_iter()
+                    .map(|x| x - obj.stack_offset)
+                    .collect()
+            },
+        );
+
+        self.slider_renderer.draw();
+    }
+
+    fn draw_slider_objs(&mut self, time: i32, objs: &[Rc<RefCell<GameplayHitObject>>]) {
+        // slider stuff (tracking circle, ticks, etc) goes over the body, but under the hitcircles
         let tick_tex = self
             .asset_loader
             .borrow_mut()
@@ -372,8 +1088,15 @@ impl HitObjectManager {
         let repeat_tex = self.asset_loader.borrow_mut().lookup_tex("reversearrow");
         let ball_tex = self.asset_loader.borrow_mut().lookup_anim("sliderb", false);
         let tex_scale = self.beatmap.difficulty.obj_radius * 2.0 / 128.0;
+        let (ball_tint, ball_colour) = {
+            let skin = &self.asset_loader.borrow().skin;
+            (
+                skin.allow_slider_ball_tint,
+                skin.slider_ball.unwrap_or(0xFFFFFF),
+            )
+        };
 
-        for x in &self.visible_objs {
+        for x in objs {
             let x = x.borrow_mut();
             if let Some(slider_info) = &x.inner_obj().slider_info {
                 // TODO: make these rely on the slider state instead!!!
@@ -384,7 +1107,11 @@ impl HitObjectManager {
                         pos,
                         tex_scale,
                         Origin::Center,
-                        0xFFFFFFFF,
+                        if ball_tint {
+                            x.combo_color
+                        } else {
+                            ball_colour
+                        } | 0xFF000000,
                         ang,
                     );
                 }
@@ -474,9 +1201,225 @@ impl HitObjectManager {
         }
     }
 
+    fn draw_cursor(&mut self) {
+        let cursor = self.asset_loader.borrow_mut().lookup_tex("cursor");
+        let origin = if self.asset_loader.borrow().skin.cursor_centre {
+            Origin::Center
+        } else {
+            Origin::TopLeft
+        };
+        self.batch.add(
+            cursor,
+            self.judge.cursor_pos(),
+            1.0 / OSU_NATIVE_TO_PLAYFIELD_RATIO as f32,
+            origin,
+            0xFFFFFFFF,
+            0.0,
+        );
+    }
+
     pub fn draw(&mut self, time: i32) {
-        self.draw_slider_objs(time);
-        self.draw_hitcircles(time);
+        // like stable, earlier objects go on top of later ones, and each slider's body goes right under its head
+        // so each slider starts a new run, with everything before it in the batch drawn first
+        let mut objs = self.visible_objs.clone();
+        objs.sort_by_key(|x| std::cmp::Reverse(x.borrow().start_time()));
+        for run in objs.chunk_by(|_, x| !x.borrow().is_slider()) {
+            if run[0].borrow().is_slider() {
+                self.batch.draw();
+                self.draw_slider_body(time, &run[0].borrow());
+            }
+            self.draw_slider_objs(time, run);
+            self.draw_hitcircles(time, run);
+        }
+        self.slider_renderer.finish_frame();
+
+        self.draw_cursor();
         self.batch.draw();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn judge() -> HitObjectJudge {
+        let beatmap = Beatmap::fixture("test/judge.osu");
+        HitObjectJudge::headless(Rc::new(beatmap))
+    }
+
+    fn frame(time: f64, pos: Vector2, keys: OsuKeys) -> InputFrame {
+        InputFrame { time, pos, keys }
+    }
+
+    fn results(judge: &mut HitObjectJudge, obj_idx: usize) -> Vec<IncreaseScoreType> {
+        judge
+            .take_judgements()
+            .into_iter()
+            .filter(|x| x.obj_idx == obj_idx)
+            .map(|x| x.result)
+            .collect()
+    }
+
+    #[test]
+    fn test_snaked_range() {
+        let beatmap = Beatmap::fixture("test/simple_slider_with_repeats.osu");
+        let difficulty = &beatmap.difficulty;
+        let obj = &beatmap.hit_objects[0];
+        let slides = obj.slider_info.as_ref().unwrap().slides;
+        assert_eq!(slides, 2);
+        let (start, preempt) = (obj.start, difficulty.preempt);
+        let snake_in_time = preempt - difficulty.preempt_slider_complete;
+        let span = (obj.end - obj.start) as f32 / slides as f32;
+        let range = |slides: i32, time: f32| snaked_range(difficulty, obj, slides, time as i32);
+        let close = |a: (f32, f32), b: (f32, f32)| {
+            assert!(
+                (a.0 - b.0).abs() < 0.01 && (a.1 - b.1).abs() < 0.01,
+                "{:?} != {:?}",
+                a,
+                b
+            )
+        };
+
+        // snakes in from the head while fading in, then stays whole
+        close(range(2, (start - preempt - 100) as f32), (0.0, 0.0));
+        close(
+            range(2, (start - preempt + snake_in_time / 2) as f32),
+            (0.0, 0.5),
+        );
+        close(
+            range(2, (start - preempt + snake_in_time) as f32),
+            (0.0, 1.0),
+        );
+        close(range(2, start as f32 + span * 0.5), (0.0, 1.0));
+
+        // an even slide count ends at the head, so the tail end goes first
+        close(range(2, start as f32 + span * 1.25), (0.0, 0.75));
+        close(range(2, obj.end as f32), (0.0, 0.0));
+        close(range(2, (obj.end + 100) as f32), (0.0, 0.0));
+
+        // an odd one ends at the tail, so the head goes first
+        let span = (obj.end - obj.start) as f32 / 3.0;
+        close(range(3, start as f32 + span * 2.5), (0.5, 1.0));
+        close(
+            range(1, start as f32 + (obj.end - start) as f32 * 0.25),
+            (0.25, 1.0),
+        );
+    }
+
+    #[test]
+    fn test_stacked_click() {
+        let mut judge = judge();
+        let radius = judge.beatmap.difficulty.obj_radius;
+        let first = &judge.beatmap.hit_objects[0];
+        assert_eq!(first.stack_count, 1);
+        let (stacked, unstacked) = (first.start_pos, first.unstacked_start_pos);
+
+        // close enough to where the circle is in the file, but not to where it gets drawn
+        let outside = stacked + Vector2::new(radius + 1.0, 0.0);
+        assert!(outside.distance(unstacked) < radius);
+        judge.handle_input(&frame(1000.0, outside, OsuKeys::K1));
+        assert!(results(&mut judge, 0).is_empty());
+
+        judge.handle_input(&frame(1010.0, stacked, OsuKeys::empty()));
+        judge.handle_input(&frame(1020.0, stacked, OsuKeys::K1));
+        assert_eq!(results(&mut judge, 0), &[IncreaseScoreType::HIT_300]);
+    }
+
+    #[test]
+    fn test_notelock() {
+        let mut judge = judge();
+        judge.reset(2000);
+        let (first, second) = (Vector2::new(300.0, 100.0), Vector2::new(400.0, 100.0));
+
+        // the first circle can still be hit, so clicking the second one does nothing
+        judge.handle_input(&frame(2080.0, second, OsuKeys::K1));
+        assert!(judge.take_judgements().is_empty());
+        assert!(!judge.objs[3].borrow().is_judged());
+
+        judge.handle_input(&frame(2090.0, first, OsuKeys::K2));
+        assert_eq!(results(&mut judge, 2), &[IncreaseScoreType::HIT_100]);
+        judge.handle_input(&frame(2100.0, second, OsuKeys::K1));
+        assert_eq!(results(&mut judge, 3), &[IncreaseScoreType::HIT_300]);
+    }
+
+    #[test]
+    fn test_slider_release() {
+        let mut judge = judge();
+        judge.reset(3000);
+        let beatmap = judge.beatmap.clone();
+        let slider = &beatmap.hit_objects[4];
+        let ball = |time: i32| slider.ball_pos_at_time(time).0;
+
+        // held through the tick, then let go before the end
+        let mut judgements = Vec::new();
+        for time in (3000..=4100).step_by(20) {
+            let keys = if time < 3600 {
+                OsuKeys::K1
+            } else {
+                OsuKeys::empty()
+            };
+            judge.handle_input(&frame(time as f64, ball(time), keys));
+            if time == 3580 {
+                assert!(
+                    judge.objs[4]
+                        .borrow()
+                        .slider_info
+                        .as_ref()
+                        .unwrap()
+                        .is_sliding
+                );
+            }
+            judgements.extend(results(&mut judge, 4));
+        }
+        let slider_info = judge.objs[4].borrow();
+        let slider_info = slider_info.slider_info.as_ref().unwrap();
+        assert!(!slider_info.is_sliding);
+        assert_eq!(slider_info.nested_hit, 2);
+        assert_eq!(
+            judgements,
+            &[
+                IncreaseScoreType::SLIDER_REPEAT,
+                IncreaseScoreType::SLIDER_TICK,
+                IncreaseScoreType::MISS | IncreaseScoreType::SLIDER_END,
+                IncreaseScoreType::HIT_100,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_slow_spinner() {
+        let mut judge = judge();
+        judge.reset(5000);
+        let required = judge.objs[5]
+            .borrow()
+            .spinner_info
+            .as_ref()
+            .unwrap()
+            .required_spins;
+        assert_eq!(required, 10);
+
+        // 200rpm for two seconds is only two thirds of what's needed
+        let centre = Vector2::new(256.0, 192.0);
+        let speed = 200.0 / 60000.0 * 2.0 * PI;
+        let mut judgements = Vec::new();
+        for time in (5000..=7100).step_by(16) {
+            let angle = (time - 5000) as f32 * speed;
+            let pos = centre + Vector2::new(angle.cos(), angle.sin()) * 50.0;
+            judge.handle_input(&frame(time as f64, pos, OsuKeys::K1));
+            if time == 6008 {
+                let rpm = judge.objs[5].borrow().spinner_info.as_ref().unwrap().rpm;
+                assert!((rpm - 200.0).abs() < 5.0, "{}", rpm);
+            }
+            judgements.extend(results(&mut judge, 5));
+        }
+
+        let spins = judgements
+            .iter()
+            .filter(|x| **x == IncreaseScoreType::SPINNER_SPIN)
+            .count();
+        assert_eq!(spins, 6);
+        assert!(!judgements.contains(&IncreaseScoreType::SPINNER_BONUS));
+        assert_eq!(judgements.last(), Some(&IncreaseScoreType::MISS));
+        assert!(judge.is_finished());
+    }
+}
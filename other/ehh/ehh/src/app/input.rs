@@ -0,0 +1,183 @@
+use log::warn;
+use sdl2::{event::Event, keyboard::Keycode, mouse::MouseButton};
+
+use crate::{
+    math::Vector2,
+    replay::{OsuKeys, ReplayFrame},
+};
+
+// the full input state at some point in beatmap time
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputFrame {
+    pub time: f64,
+    pub pos: Vector2, // in playfield coordinates
+    pub keys: OsuKeys,
+}
+
+impl From<&ReplayFrame> for InputFrame {
+    fn from(x: &ReplayFrame) -> InputFrame {
+        InputFrame {
+            time: x.time as f64,
+            pos: x.pos,
+            keys: x.keys,
+        }
+    }
+}
+
+impl From<&InputFrame> for ReplayFrame {
+    fn from(x: &InputFrame) -> ReplayFrame {
+        ReplayFrame {
+            time: x.time.round() as i32,
+            pos: x.pos,
+            keys: x.keys,
+        }
+    }
+}
+
+// turns sdl events into timestamped frames
+pub struct InputManager {
+    // playfield = window * scale + offset
+    scale: f32,
+    offset: Vector2,
+    key_bindings: [(Keycode, OsuKeys); 2],
+
+    current: InputFrame,
+    frames: Vec<InputFrame>,
+
+    // what the audio time was at some sdl timestamp, used to place events in beatmap time
+    // none right after a reset, until the next sync
+    sync_ticks: Option<u32>,
+    sync_time: f64,
+    rate: f64,
+}
+
+impl InputManager {
+    // keys are sdl key names, like "Z" or "Left Shift"
+    pub fn new(scale: f32, offset: Vector2, keys: &[String; 2]) -> InputManager {
+        let key = |name: &str, default| {
+            Keycode::from_name(name).unwrap_or_else(|| {
+                warn!("Unknown key {}, using {}", name, default);
+                default
+            })
+        };
+        InputManager {
+            scale,
+            offset,
+            key_bindings: [
+                (key(&keys[0], Keycode::Z), OsuKeys::K1),
+                (key(&keys[1], Keycode::X), OsuKeys::K2),
+            ],
+            current: Default::default(),
+            frames: Vec::new(),
+            sync_ticks: None,
+            sync_time: 0.0,
+            rate: 1.0,
+        }
+    }
+
+    // should be called every frame after the audio clock is updated
+    pub fn sync(&mut self, ticks: u32, time: f64, rate: f64) {
+        self.sync_ticks = Some(ticks);
+        self.sync_time = time;
+        self.rate = rate;
+    }
+
+    fn event_time(&self, timestamp: u32) -> f64 {
+        match self.sync_ticks {
+            Some(ticks) => self.sync_time + (timestamp as f64 - ticks as f64) * self.rate,
+            // the old ticks went with the old time, so anything before the next sync happens right at the reset
+            None => self.sync_time,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        let (timestamp, pos, keys) = match *event {
+            Event::MouseMotion {
+                timestamp, x, y, ..
+            } => (
+                timestamp,
+                Vector2::new(x as f32, y as f32) * self.scale + self.offset,
+                self.current.keys,
+            ),
+            Event::MouseButtonDown {
+                timestamp,
+                mouse_btn,
+                ..
+            } => (
+                timestamp,
+                self.current.pos,
+                self.current.keys | mouse_button(mouse_btn),
+            ),
+            Event::MouseButtonUp {
+                timestamp,
+                mouse_btn,
+                ..
+            } => (
+                timestamp,
+                self.current.pos,
+                self.current.keys - mouse_button(mouse_btn),
+            ),
+            Event::KeyDown {
+                timestamp,
+                keycode: Some(keycode),
+                repeat: false,
+                ..
+            } => (
+                timestamp,
+                self.current.pos,
+                self.current.keys | self.key(keycode),
+            ),
+            Event::KeyUp {
+                timestamp,
+                keycode: Some(keycode),
+                ..
+            } => (
+                timestamp,
+                self.current.pos,
+                self.current.keys - self.key(keycode),
+            ),
+            _ => return,
+        };
+
+        // events can't go back in time, even if the clock got resynced
+        let time = self.event_time(timestamp).max(self.current.time);
+        if pos != self.current.pos || keys != self.current.keys {
+            self.current = InputFrame { time, pos, keys };
+            self.frames.push(self.current);
+        }
+    }
+
+    fn key(&self, keycode: Keycode) -> OsuKeys {
+        self.key_bindings
+            .iter()
+            .find(|x| x.0 == keycode)
+            .map(|x| x.1)
+            .unwrap_or_default()
+    }
+
+    // forgets any held keys and starts again from the given time, for after seeking or unpausing
+    pub fn reset(&mut self, time: f64) {
+        self.current.time = time;
+        self.current.keys = OsuKeys::empty();
+        self.frames.clear();
+        self.sync_ticks = None;
+        self.sync_time = time;
+    }
+
+    // everything that happened since the last call, oldest first
+    pub fn take_frames(&mut self) -> Vec<InputFrame> {
+        std::mem::take(&mut self.frames)
+    }
+
+    pub fn current(&self) -> InputFrame {
+        self.current
+    }
+}
+
+fn mouse_button(button: MouseButton) -> OsuKeys {
+    match button {
+        MouseButton::Left => OsuKeys::M1,
+        MouseButton::Right => OsuKeys::M2,
+        _ => OsuKeys::empty(),
+    }
+}
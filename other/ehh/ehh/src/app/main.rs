@@ -1,18 +1,27 @@
 use gl::types::*;
-use log::{error, info};
+#[cfg(feature = "bass")]
+use log::info;
+use log::{error, warn};
 use std::{
     cell::RefCell,
     ffi::{c_void, CStr},
     rc::Rc,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
+#[cfg(feature = "bass")]
+use crate::framework::{audio::BassBackend, bass::Bass};
 use crate::framework::{
-    bass::Bass,
+    audio::{AudioBackend, AudioSink, SoftwareBackend},
     render::{Alignment, DrawBatch, TextRenderer, TextSprite},
 };
 
-use super::game::OsuGame;
+use super::{
+    config::Config,
+    game::{GameAction, OsuGame},
+    song_select::{SongSelect, SongSelectAction},
+};
+use crate::{beatmap::Mods, library::Library};
 
 extern "system" fn gl_msg_callback(
     source: GLenum,
@@ -90,18 +99,48 @@ impl FPSCounter {
     }
 }
 
+pub(super) fn create_text_renderer() -> Rc<RefCell<TextRenderer>> {
+    Rc::new(RefCell::new(TextRenderer::new(&[
+        include_bytes!("../../assets/fonts/NotoSans-Bold.ttf").to_vec(),
+        include_bytes!("../../assets/fonts/NotoSansJP-Bold.otf").to_vec(),
+    ])))
+}
+
+// what to play and how
+pub struct PlayOptions {
+    pub beatmap_path: String,
+    pub difficulty: Option<String>, // which one out of a folder or .osz
+    pub mods: Mods,
+    pub autoplay: bool,
+    pub practice: Option<(i32, i32)>, // loops between these times in ms
+}
+
+// what shows up first when the window opens
+pub enum StartScene {
+    Play(PlayOptions),
+    // mods and autoplay apply to everything picked from it
+    SongSelect {
+        library: Library,
+        mods: Mods,
+        autoplay: bool,
+    },
+}
+
 pub struct EhhApp {
-    bass: Rc<Bass>,
+    audio_backend: Rc<dyn AudioBackend>,
+    // only set when falling back to it, it has to be fed time to keep the song moving
+    software_audio: Option<Rc<SoftwareBackend>>,
     text_renderer: Rc<RefCell<TextRenderer>>,
     batch: DrawBatch,
     fps_counter: FPSCounter,
-    game: OsuGame,
+    song_select: Option<SongSelect>,
+    game: Option<OsuGame>,
 }
 
 impl EhhApp {
-    pub fn run(beatmap_path: String) {
-        let width = 1920;
-        let height = 1080;
+    pub fn run(start: StartScene, config: Config) {
+        let width = config.width;
+        let height = config.height;
 
         let sdl = sdl2::init().expect("Failed to initialize SDL2");
         let sdl_video = sdl
@@ -110,19 +149,25 @@ impl EhhApp {
         let gl_attr = sdl_video.gl_attr();
         gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
         gl_attr.set_context_version(4, 6);
-        let mut window = sdl_video
-            .window("ehh", width, height)
-            .opengl()
-            .fullscreen()
-            .position_centered()
-            .build()
-            .expect("Failed to create a window");
+        let mut window = {
+            let mut builder = sdl_video.window("ehh", width, height);
+            builder.opengl().position_centered();
+            if config.fullscreen {
+                builder.fullscreen();
+            }
+            builder.build().expect("Failed to create a window")
+        };
         let _gl_context = window
             .gl_create_context()
             .expect("Failed to initialize a GL context");
         gl::load_with(|s| sdl_video.gl_get_proc_address(s) as *const c_void);
 
-        sdl_video.gl_set_swap_interval(0).unwrap();
+        if sdl_video.gl_set_swap_interval(config.vsync as i32).is_err() {
+            warn!("Failed to set vsync to {}", config.vsync);
+        }
+        // vsync already limits the framerate
+        let min_frame_time = (!config.vsync && config.frame_limit > 0)
+            .then(|| Duration::from_secs_f64(1.0 / config.frame_limit as f64));
 
         #[cfg(debug_assertions)]
         unsafe {
@@ -131,60 +176,44 @@ impl EhhApp {
             gl::DebugMessageCallback(Some(gl_msg_callback), std::ptr::null());
         }
 
-        let bass = Rc::new(Bass::new(-1, 44100, 0).expect("Failed to initialize BASS"));
-        let bass_version = bass.get_version();
-        let bassmix_version = bass.get_bassmix_version();
-        info!(
-            "BASS version:    {}.{}.{}.{}",
-            bass_version.0, bass_version.1, bass_version.2, bass_version.3
-        );
-        info!(
-            "BASSmix version: {}.{}.{}.{}",
-            bassmix_version.0, bassmix_version.1, bassmix_version.2, bassmix_version.3
-        );
-        info!(
-            "Using device:    {}",
-            bass.get_device_info(bass.get_device().unwrap())
-                .unwrap()
-                .name
-        );
+        let (audio_backend, software_audio) = create_audio_backend();
 
-        // osu beatmap sync stuff
-        bass.set_config(68, 1).unwrap(); // BASS_CONFIG_MP3_OLDGAPS (undocumented in bass.chm!!!)
-        bass.set_config(bass_sys::BASS_CONFIG_DEV_NONSTOP, 1)
-            .unwrap();
-        bass.set_config(bass_sys::BASS_CONFIG_UPDATEPERIOD, 5);
-        bass.set_config(bass_sys::BASS_CONFIG_UPDATETHREADS, 1);
-
-        let text_renderer = Rc::new(RefCell::new(TextRenderer::new(&[
-            include_bytes!("../../assets/fonts/NotoSans-Bold.ttf").to_vec(),
-            include_bytes!("../../assets/fonts/NotoSansJP-Bold.otf").to_vec(),
-        ])));
-
-        let game = match OsuGame::new(
-            bass.clone(),
-            text_renderer.clone(),
-            width as f32,
-            height as f32,
-            beatmap_path,
-        ) {
-            Ok(x) => x,
-            Err(x) => {
-                error!("Failed to create the game scene: {x}");
-                return;
-            }
-        };
+        let text_renderer = create_text_renderer();
 
         let ortho = cgmath::ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
 
         let mut app = EhhApp {
-            bass,
+            audio_backend,
+            software_audio,
             fps_counter: FPSCounter::new(text_renderer.clone(), width as f32, height as f32),
             batch: DrawBatch::new(ortho),
             text_renderer,
-            game,
+            song_select: None,
+            game: None,
         };
-        window.set_title(&app.game.get_title()).unwrap();
+        match start {
+            StartScene::Play(options) => {
+                if let Err(x) = app.start_game(&options, &config) {
+                    error!("Failed to create the game scene: {x}");
+                    return;
+                }
+            }
+            StartScene::SongSelect {
+                library,
+                mods,
+                autoplay,
+            } => {
+                app.song_select = Some(SongSelect::new(
+                    library,
+                    mods,
+                    autoplay,
+                    app.text_renderer.clone(),
+                    width as f32,
+                    height as f32,
+                ));
+            }
+        }
+        window.set_title(&app.get_title()).unwrap();
 
         unsafe {
             gl::Viewport(0, 0, width as i32, height as i32);
@@ -194,12 +223,47 @@ impl EhhApp {
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
         }
 
+        let timer = sdl
+            .timer()
+            .expect("Failed to initialize SDL2's timer subsystem");
         let mut event_pump = sdl.event_pump().unwrap();
+        let mut last_frame = Instant::now();
         'main: loop {
+            let frame_start = Instant::now();
+            if let Some(audio) = &app.software_audio {
+                let elapsed = frame_start.duration_since(last_frame).as_secs_f64() * 1000.0;
+                if let Err(e) = audio.mix(elapsed) {
+                    error!("{e}");
+                }
+            }
+            last_frame = frame_start;
             for event in event_pump.poll_iter() {
                 match event {
                     sdl2::event::Event::Quit { .. } => break 'main,
-                    _ => {}
+                    _ => {
+                        if let Some(game) = app.game.as_mut() {
+                            match game.handle_event(&event) {
+                                GameAction::None => {}
+                                // goes back to song select, if that's where it came from
+                                GameAction::Quit if app.song_select.is_some() => {
+                                    app.game = None;
+                                    window.set_title(&app.get_title()).unwrap();
+                                }
+                                GameAction::Quit => break 'main,
+                            }
+                        } else if let Some(song_select) = app.song_select.as_mut() {
+                            match song_select.handle_event(&event) {
+                                SongSelectAction::None => {}
+                                SongSelectAction::Play(options) => {
+                                    match app.start_game(&options, &config) {
+                                        Ok(()) => window.set_title(&app.get_title()).unwrap(),
+                                        Err(x) => error!("Failed to create the game scene: {x}"),
+                                    }
+                                }
+                                SongSelectAction::Quit => break 'main,
+                            }
+                        }
+                    }
                 }
             }
 
@@ -207,15 +271,94 @@ impl EhhApp {
                 gl::Clear(gl::COLOR_BUFFER_BIT);
             }
 
-            app.game.update();
-            app.game.draw();
+            if let Some(game) = app.game.as_mut() {
+                game.update(timer.ticks());
+                game.draw();
+            } else if let Some(song_select) = app.song_select.as_mut() {
+                song_select.draw();
+            }
 
             app.fps_counter.draw(&mut app.batch);
             app.batch.draw();
 
+            window.gl_swap_window();
+
+            if let Some(min_frame_time) = min_frame_time {
+                let elapsed = frame_start.elapsed();
+                if elapsed < min_frame_time {
+                    std::thread::sleep(min_frame_time - elapsed);
+                }
+            }
             app.fps_counter.end_frame();
+        }
+    }
 
-            window.gl_swap_window();
+    fn start_game(&mut self, options: &PlayOptions, config: &Config) -> Result<(), String> {
+        // the old one has to go first so that its music stops
+        self.game = None;
+        self.game = Some(OsuGame::new(
+            self.audio_backend.clone(),
+            self.text_renderer.clone(),
+            config.width as f32,
+            config.height as f32,
+            options,
+            config,
+        )?);
+        Ok(())
+    }
+
+    fn get_title(&self) -> String {
+        match (&self.game, &self.song_select) {
+            (Some(game), _) => game.get_title(),
+            (None, Some(song_select)) => song_select.get_title(),
+            (None, None) => "ehh".to_string(),
         }
     }
 }
+
+// bass if it's there, otherwise a silent software backend so that everything else still works
+fn create_audio_backend() -> (Rc<dyn AudioBackend>, Option<Rc<SoftwareBackend>>) {
+    #[cfg(feature = "bass")]
+    match init_bass() {
+        Ok(x) => return (Rc::new(x), None),
+        Err(e) => warn!("{e}, continuing without sound"),
+    }
+    #[cfg(not(feature = "bass"))]
+    warn!("Built without BASS, continuing without sound");
+
+    let software = Rc::new(SoftwareBackend::new(AudioSink::Null));
+    (software.clone(), Some(software))
+}
+
+#[cfg(feature = "bass")]
+fn init_bass() -> Result<BassBackend, String> {
+    let bass = Rc::new(Bass::new(-1, 44100, 0)?);
+    let bass_version = bass.get_version();
+    let bassmix_version = bass.get_bassmix_version();
+    info!(
+        "BASS version:    {}.{}.{}.{}",
+        bass_version.0, bass_version.1, bass_version.2, bass_version.3
+    );
+    info!(
+        "BASSmix version: {}.{}.{}.{}",
+        bassmix_version.0, bassmix_version.1, bassmix_version.2, bassmix_version.3
+    );
+    match bass.get_device().and_then(|x| bass.get_device_info(x)) {
+        Ok(device) => info!("Using device:    {}", device.name),
+        Err(e) => warn!("Failed to get the current device: {}", e),
+    }
+
+    // osu beatmap sync stuff
+    for (option, value) in [
+        (68, 1), // BASS_CONFIG_MP3_OLDGAPS (undocumented in bass.chm!!!)
+        (bass_sys::BASS_CONFIG_DEV_NONSTOP, 1),
+        (bass_sys::BASS_CONFIG_UPDATEPERIOD, 5),
+        (bass_sys::BASS_CONFIG_UPDATETHREADS, 1),
+    ] {
+        if bass.set_config(option, value).is_none() {
+            warn!("Failed to set BASS config option {} to {}", option, value);
+        }
+    }
+
+    BassBackend::new(bass)
+}
@@ -1,8 +1,19 @@
 mod asset_loader;
 mod audio_manager;
+mod config;
+mod default_skin;
 mod game;
 mod hitobject_manager;
+mod input;
 mod main;
+mod render;
+mod replay_sim;
+mod score_processor;
 mod skin;
+mod song_select;
 
+pub use config::*;
 pub use main::*;
+pub use render::*;
+pub use replay_sim::*;
+pub use score_processor::ScoreProcessor;
@@ -0,0 +1,106 @@
+use std::{ffi::c_void, path::Path, rc::Rc};
+
+use log::info;
+use rgb::FromSlice;
+
+use crate::framework::{
+    audio::{AudioSink, SoftwareBackend},
+    render::Framebuffer,
+};
+
+use super::{config::Config, create_text_renderer, game::OsuGame, PlayOptions};
+
+// keep going for a bit after the last object so that everything gets to fade out
+const RENDER_TAIL: f64 = 1000.0;
+
+pub struct RenderOptions {
+    pub output_dir: String, // frames end up as 000000.png, 000001.png, etc
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+    pub audio_path: Option<String>, // also mixes the song and hitsounds into a wav
+}
+
+// plays through the whole map one fixed step at a time, so the same map always renders the same frames
+// there's still a gl context behind this, it just never gets shown
+pub fn render_play(
+    options: &PlayOptions,
+    render: &RenderOptions,
+    config: &Config,
+) -> Result<(), String> {
+    std::fs::create_dir_all(&render.output_dir)
+        .map_err(|e| format!("Failed to create {}: {}", render.output_dir, e))?;
+
+    let sdl = sdl2::init()?;
+    let sdl_video = sdl.video()?;
+    let gl_attr = sdl_video.gl_attr();
+    gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+    gl_attr.set_context_version(4, 6);
+    let window = sdl_video
+        .window("ehh", render.width, render.height)
+        .opengl()
+        .hidden()
+        .build()
+        .map_err(|e| format!("Failed to create a window: {}", e))?;
+    let _gl_context = window.gl_create_context()?;
+    gl::load_with(|s| sdl_video.gl_get_proc_address(s) as *const c_void);
+
+    let audio_backend = Rc::new(match &render.audio_path {
+        Some(path) => SoftwareBackend::new_wav(path)?,
+        None => SoftwareBackend::new(AudioSink::Null),
+    });
+    let mut game = OsuGame::new(
+        audio_backend.clone(),
+        create_text_renderer(),
+        render.width as f32,
+        render.height as f32,
+        options,
+        config,
+    )?;
+
+    let framebuffer = Framebuffer::new(render.width, render.height)?;
+    framebuffer.bind();
+    unsafe {
+        gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    }
+
+    let frame_time = 1000.0 / render.fps as f64;
+    let mut finish_time = None;
+    for frame in 0u64.. {
+        game.update((frame as f64 * frame_time) as u32);
+        if game.is_finished() {
+            let time = game.music_pos();
+            if time > *finish_time.get_or_insert(time) + RENDER_TAIL {
+                break;
+            }
+        }
+
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        game.draw();
+
+        let pixels = framebuffer.read_pixels();
+        let path = Path::new(&render.output_dir).join(format!("{:06}.png", frame));
+        lodepng::encode32_file(
+            &path,
+            pixels.as_rgba(),
+            render.width as usize,
+            render.height as usize,
+        )
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        // hitsounds from this frame get mixed in at the start of the next one
+        audio_backend.mix(frame_time)?;
+
+        if frame % (render.fps as u64 * 10) == 0 {
+            info!("Rendered up to {:.0}ms", game.music_pos());
+        }
+    }
+    audio_backend.finish()?;
+    framebuffer.unbind();
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use std::rc::Rc;
+
+use crate::{
+    framework::clock::{Clock, ManualClock},
+    replay::Replay,
+    Beatmap,
+};
+
+use super::{
+    hitobject_manager::HitObjectJudge, input::InputFrame, score_processor::ScoreProcessor,
+};
+
+// how often the game would update between replay frames, stable's replays are recorded at about this rate
+const UPDATE_INTERVAL: f64 = 1000.0 / 60.0;
+
+// plays a replay back without a window or any audio, the beatmap should already have the replay's mods applied
+pub fn simulate_replay(beatmap: Rc<Beatmap>, replay: &Replay) -> ScoreProcessor {
+    let mut judge = HitObjectJudge::headless(beatmap.clone());
+    let mut score = ScoreProcessor::new(&beatmap);
+
+    let first_frame = replay.frames.first().map(|x| x.time).unwrap_or(0);
+    let first_obj = beatmap
+        .hit_objects
+        .first()
+        .map(|x| x.start - x.time_preempt)
+        .unwrap_or(0);
+    let mut clock = ManualClock::new(first_frame.min(first_obj) as f64);
+    clock.set_rate(beatmap.difficulty.mods.clock_rate());
+    clock.start();
+
+    let mut frames = replay.frames.iter().peekable();
+    while !judge.is_finished() || frames.peek().is_some() {
+        clock.advance(UPDATE_INTERVAL);
+        clock.update();
+        let time = clock.get_time();
+
+        while let Some(frame) = frames.next_if(|x| x.time as f64 <= time) {
+            judge.handle_input(&InputFrame::from(frame));
+        }
+        judge.update(time as i32);
+        for x in judge.take_judgements() {
+            score.apply(&x);
+        }
+        score.update(time as i32);
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader};
+
+    use super::*;
+    use crate::{
+        beatmap::{HitObjectType, Mods},
+        replay::OsuKeys,
+    };
+
+    #[test]
+    fn test_simulate() {
+        let replay =
+            Replay::parse(&mut BufReader::new(File::open("test/jumps.osr").unwrap())).unwrap();
+        let beatmap = Rc::new(Beatmap::fixture_with_mods("test/jumps.osu", replay.mods));
+        let count = |kind: HitObjectType| {
+            beatmap
+                .hit_objects
+                .iter()
+                .filter(|x| x.object_type == kind)
+                .count() as u32
+        };
+
+        // every object gets clicked right on time, but nothing gets held or spun
+        let score = simulate_replay(beatmap.clone(), &replay);
+        assert_eq!(score.count_300, count(HitObjectType::Circle));
+        assert_eq!(
+            score.count_100 + score.count_50,
+            count(HitObjectType::Slider)
+        );
+        assert_eq!(score.count_miss, count(HitObjectType::Spinner));
+
+        // the same replay always gives the same result
+        let again = simulate_replay(
+            Rc::new(Beatmap::fixture_with_mods("test/jumps.osu", replay.mods)),
+            &replay,
+        );
+        assert_eq!(again.score, score.score);
+        assert_eq!(again.max_combo, score.max_combo);
+    }
+
+    #[test]
+    fn test_autoplay() {
+        for mods in [Mods::empty(), Mods::HARD_ROCK | Mods::DOUBLE_TIME] {
+            let beatmap = Rc::new(Beatmap::fixture_with_mods("test/jumps.osu", mods));
+            let replay = Replay::autoplay(&beatmap);
+
+            // should survive being saved too
+            let mut out = Vec::new();
+            replay.write(&mut out).unwrap();
+            let replay = Replay::parse(&mut out.as_slice()).unwrap();
+            assert_eq!(replay.mods, mods);
+            assert_eq!(replay.beatmap_hash, "09395d16555437873774e4b674e218c8");
+
+            let score = simulate_replay(beatmap.clone(), &replay);
+            assert_eq!(score.count_300 as usize, beatmap.hit_objects.len());
+            assert_eq!(score.max_combo as usize, beatmap.max_combo());
+            assert_eq!(score.accuracy(), 1.0);
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let beatmap = Rc::new(Beatmap::fixture_with_mods("test/jumps.osu", Mods::empty()));
+        let replay = Replay::autoplay(&beatmap);
+        let last_end = beatmap.hit_objects.iter().map(|x| x.end).max().unwrap();
+        let play = |judge: &mut HitObjectJudge, from: i32, skip_before: i32| {
+            let mut score = ScoreProcessor::new(&beatmap);
+            let mut judgements = Vec::new();
+            for frame in replay.frames.iter().filter(|x| x.time >= from) {
+                // clicks meant for skipped objects would hit the next one early
+                let mut frame = InputFrame::from(frame);
+                if (frame.time as i32) < skip_before {
+                    frame.keys = OsuKeys::empty();
+                }
+                judge.handle_input(&frame);
+                judge.update(frame.time as i32);
+                judgements.extend(judge.take_judgements());
+            }
+            judge.update(last_end + 1000);
+            judgements.extend(judge.take_judgements());
+            for x in &judgements {
+                score.apply(x);
+            }
+            (score, judgements)
+        };
+
+        let mut judge = HitObjectJudge::headless(beatmap.clone());
+        let (score, _) = play(&mut judge, i32::MIN, i32::MIN);
+        assert!(judge.is_finished());
+        assert_eq!(score.count_300 as usize, beatmap.hit_objects.len());
+
+        // going back to the middle makes everything after it playable again, without the earlier objects counting
+        let middle = beatmap.hit_objects.len() / 2;
+        let skip_before = beatmap.hit_objects[middle].start;
+        judge.reset(skip_before);
+        assert!(!judge.is_finished());
+        let (score, judgements) = play(&mut judge, skip_before - 2000, skip_before);
+        assert!(judge.is_finished());
+        assert!(judgements.iter().all(|x| x.obj_idx >= middle));
+        assert_eq!(score.count_300 as usize, beatmap.hit_objects.len() - middle);
+        assert_eq!(score.count_miss, 0);
+    }
+}
@@ -0,0 +1,362 @@
+use crate::beatmap::{map_diff_range, Beatmap, HitObjectType, Mods};
+
+use super::hitobject_manager::{IncreaseScoreType, Judgement};
+
+// stable keeps hp out of 200, so the numbers below are too
+const HP_BAR_MAXIMUM: f64 = 200.0;
+const HP_HIT_300: f64 = 6.0;
+const HP_HIT_100: f64 = 2.2;
+const HP_HIT_50: f64 = 0.4;
+const HP_GEKI: f64 = 14.0;
+const HP_KATU: f64 = 10.0;
+const HP_SLIDER_TICK: f64 = 3.0;
+const HP_SLIDER_REPEAT: f64 = 4.0;
+const HP_SPINNER_SPIN: f64 = 1.7;
+const HP_SPINNER_BONUS: f64 = 2.0;
+
+// scorev1, same as stable
+pub struct ScoreProcessor {
+    pub score: u64,
+    pub combo: u32,
+    pub max_combo: u32,
+
+    pub count_300: u32,
+    pub count_100: u32,
+    pub count_50: u32,
+    pub count_miss: u32,
+    pub count_geki: u32,
+    pub count_katu: u32,
+
+    pub health: f64, // 0 to 1
+    pub failed: bool,
+
+    // the last object in each combo decides geki/katu
+    combo_ends: Vec<bool>,
+    sliders: Vec<bool>,
+    // anything other than a 300 in the current combo, including slider parts
+    combo_imperfect: bool,
+    combo_has_miss: bool,
+    combo_has_50: bool,
+
+    difficulty_multiplier: f64,
+    mod_multiplier: f64,
+    hp_drain: f32,
+
+    // passive drain only happens while there's something to play
+    drain_start: i32,
+    drain_end: i32,
+    breaks: Vec<(i32, i32)>,
+    last_update: Option<i32>,
+}
+
+impl ScoreProcessor {
+    pub fn new(beatmap: &Beatmap) -> ScoreProcessor {
+        let objs = &beatmap.hit_objects;
+        let combo_ends = (0..objs.len())
+            .map(|i| objs.get(i + 1).map(|x| x.is_new_combo()).unwrap_or(true))
+            .collect();
+        let sliders = objs
+            .iter()
+            .map(|x| x.object_type == HitObjectType::Slider)
+            .collect();
+
+        let drain_start = objs.first().map(|x| x.start).unwrap_or(0);
+        let drain_end = objs.iter().map(|x| x.end).max().unwrap_or(0);
+        let breaks: Vec<(i32, i32)> = beatmap
+            .events
+            .breaks
+            .iter()
+            .map(|x| (x.start, x.end))
+            .collect();
+
+        // stable uses the stats from the file here, mods get their own multiplier
+        let drain_seconds =
+            (drain_end - drain_start - breaks.iter().map(|x| x.1 - x.0).sum::<i32>()) as f64
+                / 1000.0;
+        let density = if drain_seconds > 0.0 {
+            (objs.len() as f64 / drain_seconds * 8.0).clamp(0.0, 16.0)
+        } else {
+            0.0
+        };
+        let diff = &beatmap.difficulty;
+        let difficulty_multiplier =
+            ((diff.hp_drain + diff.circle_size + diff.overall_difficulty) as f64 + density) / 38.0
+                * 5.0;
+
+        ScoreProcessor {
+            score: 0,
+            combo: 0,
+            max_combo: 0,
+            count_300: 0,
+            count_100: 0,
+            count_50: 0,
+            count_miss: 0,
+            count_geki: 0,
+            count_katu: 0,
+            health: 1.0,
+            failed: false,
+            combo_ends,
+            sliders,
+            combo_imperfect: false,
+            combo_has_miss: false,
+            combo_has_50: false,
+            difficulty_multiplier: difficulty_multiplier.round(),
+            mod_multiplier: mod_multiplier(diff.mods),
+            hp_drain: diff.hp_drain_adjusted(),
+            drain_start,
+            drain_end,
+            breaks,
+            last_update: None,
+        }
+    }
+
+    // returns the result with geki/katu added on if it ended a combo
+    pub fn apply(&mut self, judgement: &Judgement) -> IncreaseScoreType {
+        if self.failed {
+            return judgement.result;
+        }
+
+        let mut result = judgement.result;
+        if result.contains(IncreaseScoreType::MISS) {
+            let part = result - IncreaseScoreType::MISS;
+            self.combo_imperfect = true;
+            self.combo_has_miss = true;
+            if part.is_empty() {
+                self.count_miss += 1;
+                self.end_object(judgement.obj_idx, result);
+            }
+
+            // stable doesn't break combo for missing a slider's end
+            if part != IncreaseScoreType::SLIDER_END {
+                self.combo = 0;
+            }
+            let hp = map_diff_range(self.hp_drain, 6.0, 25.0, 40.0) as f64;
+            self.change_health(if part.is_empty() { -hp } else { -hp / 4.0 });
+            return result;
+        }
+
+        let (score, hp, combo) = match result {
+            IncreaseScoreType::SLIDER_TICK => (10, HP_SLIDER_TICK, true),
+            IncreaseScoreType::SLIDER_REPEAT | IncreaseScoreType::SLIDER_END => {
+                (30, HP_SLIDER_REPEAT, true)
+            }
+            IncreaseScoreType::SPINNER_SPIN => (100, HP_SPINNER_SPIN, false),
+            // the spin itself still counts on top of the bonus
+            IncreaseScoreType::SPINNER_BONUS => (1100, HP_SPINNER_BONUS, false),
+            IncreaseScoreType::HIT_300 | IncreaseScoreType::HIT_100 | IncreaseScoreType::HIT_50 => {
+                let (value, hp) = match result {
+                    IncreaseScoreType::HIT_300 => {
+                        self.count_300 += 1;
+                        (300, HP_HIT_300)
+                    }
+                    IncreaseScoreType::HIT_100 => {
+                        self.count_100 += 1;
+                        self.combo_imperfect = true;
+                        (100, HP_HIT_100)
+                    }
+                    _ => {
+                        self.count_50 += 1;
+                        self.combo_imperfect = true;
+                        self.combo_has_50 = true;
+                        (50, HP_HIT_50)
+                    }
+                };
+                let multiplier = self.combo.saturating_sub(1) as f64
+                    * self.difficulty_multiplier
+                    * self.mod_multiplier
+                    / 25.0;
+                let score = value + (value as f64 * multiplier) as u64;
+
+                result = self.end_object(judgement.obj_idx, result);
+                let hp = if result.contains(IncreaseScoreType::GEKI_ADDITION) {
+                    HP_GEKI
+                } else if result.contains(IncreaseScoreType::KATU_ADDITION) {
+                    HP_KATU
+                } else {
+                    hp
+                };
+
+                // a slider's combo comes from its head, ticks and end instead
+                let is_slider = self
+                    .sliders
+                    .get(judgement.obj_idx)
+                    .copied()
+                    .unwrap_or(false);
+                (score, hp, !is_slider)
+            }
+            _ => (0, 0.0, false),
+        };
+
+        self.score += score;
+        if combo {
+            self.combo += 1;
+            self.max_combo = self.max_combo.max(self.combo);
+        }
+        // higher hp drain makes everything heal less
+        let multiplier = map_diff_range(self.hp_drain, 1.2, 1.0, 0.8) as f64;
+        self.change_health(hp * multiplier);
+
+        result
+    }
+
+    fn end_object(&mut self, obj_idx: usize, result: IncreaseScoreType) -> IncreaseScoreType {
+        if !self.combo_ends.get(obj_idx).copied().unwrap_or(true) {
+            return result;
+        }
+
+        let mut result = result;
+        if !self.combo_has_miss {
+            if !self.combo_imperfect {
+                self.count_geki += 1;
+                result |= IncreaseScoreType::GEKI_ADDITION;
+            } else if !self.combo_has_50 {
+                self.count_katu += 1;
+                result |= IncreaseScoreType::KATU_ADDITION;
+            }
+        }
+
+        self.combo_imperfect = false;
+        self.combo_has_miss = false;
+        self.combo_has_50 = false;
+        result
+    }
+
+    fn change_health(&mut self, amount: f64) {
+        self.health = (self.health + amount / HP_BAR_MAXIMUM).clamp(0.0, 1.0);
+        if self.health <= 0.0 {
+            self.failed = true;
+        }
+    }
+
+    // drains hp up to the given time
+    pub fn update(&mut self, time: i32) {
+        let last = self.last_update.replace(time).unwrap_or(time);
+        if self.failed || time <= last {
+            return;
+        }
+
+        // TODO: stable works out the drain rate by simulating a play of the map, this is just a guess
+        let drain_per_ms = map_diff_range(self.hp_drain, 2.0, 6.0, 12.0) as f64 / 1000.0;
+        let start = last.max(self.drain_start);
+        let end = time.min(self.drain_end);
+        if end <= start {
+            return;
+        }
+        let in_breaks: i32 = self
+            .breaks
+            .iter()
+            .map(|x| (end.min(x.1) - start.max(x.0)).max(0))
+            .sum();
+        self.change_health(-((end - start - in_breaks) as f64 * drain_per_ms));
+    }
+
+    // 0 to 1
+    pub fn accuracy(&self) -> f64 {
+        let total = self.count_300 + self.count_100 + self.count_50 + self.count_miss;
+        if total == 0 {
+            return 1.0;
+        }
+        (self.count_300 * 300 + self.count_100 * 100 + self.count_50 * 50) as f64
+            / (total * 300) as f64
+    }
+}
+
+fn mod_multiplier(mods: Mods) -> f64 {
+    let mut out = 1.0;
+    if mods.contains(Mods::EASY) {
+        out *= 0.5;
+    }
+    if mods.contains(Mods::HALF_TIME) {
+        out *= 0.3;
+    }
+    if mods.contains(Mods::HARD_ROCK) {
+        out *= 1.06;
+    }
+    if mods.contains(Mods::DOUBLE_TIME) {
+        out *= 1.12;
+    }
+    if mods.contains(Mods::HIDDEN) {
+        out *= 1.06;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vector2;
+
+    fn judgement(obj_idx: usize, result: IncreaseScoreType) -> Judgement {
+        Judgement {
+            time: 0,
+            obj_idx,
+            pos: Vector2::new(0.0, 0.0),
+            result,
+        }
+    }
+
+    #[test]
+    fn test_perfect_play() {
+        let beatmap = Beatmap::fixture("test/jumps.osu");
+        let mut score = ScoreProcessor::new(&beatmap);
+        for (i, x) in beatmap.hit_objects.iter().enumerate() {
+            if let Some(slider_info) = &x.slider_info {
+                score.apply(&judgement(i, IncreaseScoreType::SLIDER_REPEAT));
+                for _ in 1..slider_info.score_times.len() {
+                    score.apply(&judgement(i, IncreaseScoreType::SLIDER_TICK));
+                }
+                score.apply(&judgement(i, IncreaseScoreType::SLIDER_END));
+            }
+            score.apply(&judgement(i, IncreaseScoreType::HIT_300));
+        }
+
+        let combos = beatmap
+            .hit_objects
+            .iter()
+            .filter(|x| x.is_new_combo())
+            .count();
+        assert_eq!(score.max_combo as usize, beatmap.max_combo());
+        assert_eq!(score.count_300 as usize, beatmap.hit_objects.len());
+        assert_eq!(score.count_geki as usize, combos);
+        assert_eq!(score.count_katu, 0);
+        assert_eq!(score.accuracy(), 1.0);
+        assert_eq!(score.health, 1.0);
+    }
+
+    #[test]
+    fn test_misses() {
+        let beatmap = Beatmap::fixture("test/jumps.osu");
+        let mut score = ScoreProcessor::new(&beatmap);
+        score.apply(&judgement(0, IncreaseScoreType::HIT_300));
+        score.apply(&judgement(1, IncreaseScoreType::HIT_300));
+        assert_eq!(score.combo, 2);
+
+        // a slider end miss keeps the combo, anything else breaks it
+        score.apply(&judgement(
+            2,
+            IncreaseScoreType::MISS | IncreaseScoreType::SLIDER_END,
+        ));
+        assert_eq!(score.combo, 2);
+        score.apply(&judgement(
+            2,
+            IncreaseScoreType::MISS | IncreaseScoreType::SLIDER_TICK,
+        ));
+        assert_eq!(score.combo, 0);
+        assert_eq!(score.count_miss, 0);
+
+        score.apply(&judgement(2, IncreaseScoreType::MISS));
+        assert_eq!(score.count_miss, 1);
+        assert!((score.accuracy() - 2.0 / 3.0).abs() < 1e-9);
+        assert!(score.health < 1.0);
+
+        while !score.failed {
+            score.apply(&judgement(3, IncreaseScoreType::MISS));
+        }
+        assert_eq!(score.health, 0.0);
+
+        // nothing counts after failing
+        let total = score.score;
+        score.apply(&judgement(4, IncreaseScoreType::HIT_300));
+        assert_eq!(score.score, total);
+    }
+}
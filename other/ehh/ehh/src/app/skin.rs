@@ -1,48 +1,317 @@
-use std::rc::Rc;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    rc::Rc,
+    sync::Arc,
+};
 
+use log::warn;
 use rgb::ComponentBytes;
 
-use crate::framework::render::{texture::TextureRegion, TextureAtlas};
+use crate::{
+    beatmap::{parse_colour, BeatmapSet},
+    framework::render::{texture::TextureRegion, TextureAtlas},
+};
 
-use super::asset_loader::AnimatedTexture;
+use super::{
+    asset_loader::AnimatedTexture,
+    config::{parse_bool, parse_or_warn},
+    default_skin::default_skin_file,
+};
 
-// TODO: start parsing skin.ini once i have to
+// default skin colours, BGR
+const DEFAULT_COMBO_COLOURS: [u32; 4] = [0x00C0FF, 0x00CA00, 0xFF7C12, 0x3918F2];
+
+// where skin elements can come from, asset lookups try each of these in order
+pub enum AssetSource {
+    Beatmap(Arc<BeatmapSet>),
+    Skin(String),
+    Default,
+}
+
+impl AssetSource {
+    pub fn read_file(&self, filename: &str) -> Option<Vec<u8>> {
+        match self {
+            AssetSource::Beatmap(set) => {
+                if set.exists(filename) {
+                    set.read_file(filename).ok()
+                } else {
+                    None
+                }
+            }
+            AssetSource::Skin(base_path) => {
+                let direct = Path::new(base_path).join(filename);
+                if direct.is_file() {
+                    return std::fs::read(direct).ok();
+                }
+                // skins usually come from windows, where HitCircle.png and hitcircle.png are the same file
+                let found = std::fs::read_dir(base_path).ok()?.flatten().find(|x| {
+                    x.file_name()
+                        .to_string_lossy()
+                        .eq_ignore_ascii_case(filename)
+                })?;
+                std::fs::read(found.path()).ok()
+            }
+            AssetSource::Default => default_skin_file(filename).map(|x| x.to_vec()),
+        }
+    }
+}
+
+// what "Version: latest" means right now
+pub const SKIN_LATEST_VERSION: f32 = 2.7;
+
+enum Section {
+    None,
+    General,
+    Colours,
+    Fonts,
+    Other, // [CatchTheBeat], [Mania], etc
+}
+
+// everything from skin.ini, with stable's defaults for anything that's missing
 pub struct Skin {
-    pub base_path: String,
+    pub base_path: Option<String>,
+
+    // General
+    pub name: String,
+    pub author: String,
+    pub version: f32,             // 1.0 if there's no skin.ini at all
+    pub animation_framerate: f32, // -1 means every animation takes a second
+    pub allow_slider_ball_tint: bool,
+    pub cursor_centre: bool,
+    pub cursor_expand: bool,
+    pub cursor_rotate: bool,
+    pub hit_circle_overlay_above_number: bool,
+    pub slider_ball_flip: bool,
+
+    // Colours, BGR
+    pub combo_colours: Vec<u32>,
+    pub slider_ball: Option<u32>,
+    pub slider_border: u32,
+    pub slider_track_override: Option<u32>,
+    pub spinner_background: u32,
+
+    // Fonts
+    pub hit_circle_prefix: String,
+    pub hit_circle_overlap: f32,
+    pub score_prefix: String,
+    pub score_overlap: f32,
+    pub combo_prefix: String,
+    pub combo_overlap: f32,
 }
 
-impl Skin {
-    pub fn new(base_path: &str) -> Skin {
+impl Default for Skin {
+    fn default() -> Self {
         Skin {
-            base_path: base_path.to_string(),
+            base_path: None,
+            name: String::new(),
+            author: String::new(),
+            version: 1.0,
+            animation_framerate: -1.0,
+            allow_slider_ball_tint: false,
+            cursor_centre: true,
+            cursor_expand: true,
+            cursor_rotate: true,
+            hit_circle_overlay_above_number: true,
+            slider_ball_flip: true,
+            combo_colours: DEFAULT_COMBO_COLOURS.to_vec(),
+            slider_ball: None,
+            slider_border: 0xFFFFFF,
+            slider_track_override: None,
+            spinner_background: 0x640000,
+            hit_circle_prefix: "default".to_string(),
+            hit_circle_overlap: -2.0,
+            score_prefix: "score".to_string(),
+            score_overlap: 0.0,
+            combo_prefix: "score".to_string(),
+            combo_overlap: 0.0,
+        }
+    }
+}
+
+impl Skin {
+    // no path means only the built-in skin, which is always the latest version
+    pub fn new(base_path: Option<&str>) -> Skin {
+        let base_path = match base_path {
+            Some(x) => x,
+            None => {
+                return Skin {
+                    version: SKIN_LATEST_VERSION,
+                    ..Default::default()
+                }
+            }
+        };
+        let mut skin = Skin {
+            base_path: Some(base_path.to_string()),
+            ..Default::default()
+        };
+
+        // the filename's case doesn't matter on windows
+        let ini_path = std::fs::read_dir(base_path).ok().and_then(|dir| {
+            dir.flatten()
+                .find(|x| {
+                    x.file_name()
+                        .to_string_lossy()
+                        .eq_ignore_ascii_case("skin.ini")
+                })
+                .map(|x| x.path())
+        });
+        match ini_path.map(File::open) {
+            Some(Ok(file)) => skin.parse_ini(&mut BufReader::new(file)),
+            Some(Err(e)) => warn!("Failed to open skin.ini: {}", e),
+            None => {}
+        }
+
+        skin
+    }
+
+    // skins are a lot messier than beatmaps, so bad lines get skipped instead of failing everything
+    pub fn parse_ini(&mut self, file: &mut impl BufRead) {
+        // a skin.ini without a version is treated as the latest one, unlike a missing skin.ini
+        self.version = SKIN_LATEST_VERSION;
+        let mut combo_colours = Vec::new();
+
+        let mut section = Section::None;
+        for (line_num, line) in file.lines().enumerate() {
+            let line_num = line_num as u32 + 1;
+            let line = match line {
+                Ok(x) => x,
+                Err(e) => {
+                    warn!("Failed to read skin.ini: {}", e);
+                    break;
+                }
+            };
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = match &line[1..line.len() - 1] {
+                    "General" => Section::General,
+                    "Colours" | "Colors" => Section::Colours,
+                    "Fonts" => Section::Fonts,
+                    _ => Section::Other,
+                };
+                continue;
+            }
+
+            let (key, val) = match line.split_once(':') {
+                Some((key, val)) => (key.trim(), val.trim()),
+                None => continue,
+            };
+            match section {
+                Section::General => self.parse_general(key, val, line_num),
+                Section::Colours => {
+                    let colour = match parse_colour(val, line_num) {
+                        Ok(x) => x,
+                        Err(_) => {
+                            warn!("Invalid colour \"{}\" in skin.ini line {}", val, line_num);
+                            continue;
+                        }
+                    };
+                    match key {
+                        "SliderBall" => self.slider_ball = Some(colour),
+                        "SliderBorder" => self.slider_border = colour,
+                        "SliderTrackOverride" => self.slider_track_override = Some(colour),
+                        "SpinnerBackground" => self.spinner_background = colour,
+                        // same as beatmaps, only Combo1 through Combo8 in file order
+                        x if x.starts_with("Combo") => {
+                            if let Ok(idx) = x[5..].parse::<usize>() {
+                                if (1..=8).contains(&idx) {
+                                    combo_colours.push(colour);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Section::Fonts => match key {
+                    "HitCirclePrefix" => self.hit_circle_prefix = val.replace('\\', "/"),
+                    "HitCircleOverlap" => {
+                        self.hit_circle_overlap = parse_or_warn(val, "skin.ini", line_num, -2.0)
+                    }
+                    "ScorePrefix" => self.score_prefix = val.replace('\\', "/"),
+                    "ScoreOverlap" => {
+                        self.score_overlap = parse_or_warn(val, "skin.ini", line_num, 0.0)
+                    }
+                    "ComboPrefix" => self.combo_prefix = val.replace('\\', "/"),
+                    "ComboOverlap" => {
+                        self.combo_overlap = parse_or_warn(val, "skin.ini", line_num, 0.0)
+                    }
+                    _ => {}
+                },
+                Section::None | Section::Other => {}
+            }
+        }
+
+        if !combo_colours.is_empty() {
+            self.combo_colours = combo_colours;
+        }
+    }
+
+    fn parse_general(&mut self, key: &str, val: &str, line_num: u32) {
+        let flag = |default: bool| parse_bool(val, "skin.ini", line_num, default);
+        match key {
+            "Name" => self.name = val.to_string(),
+            "Author" => self.author = val.to_string(),
+            "Version" => {
+                self.version = if val.eq_ignore_ascii_case("latest") {
+                    SKIN_LATEST_VERSION
+                } else {
+                    parse_or_warn(val, "skin.ini", line_num, SKIN_LATEST_VERSION)
+                }
+            }
+            "AnimationFramerate" => {
+                self.animation_framerate = parse_or_warn(val, "skin.ini", line_num, -1.0)
+            }
+            "AllowSliderBallTint" => self.allow_slider_ball_tint = flag(false),
+            "CursorCentre" => self.cursor_centre = flag(true),
+            "CursorExpand" => self.cursor_expand = flag(true),
+            "CursorRotate" => self.cursor_rotate = flag(true),
+            "HitCircleOverlayAboveNumber" | "HitCircleOverlayAboveNumer" => {
+                self.hit_circle_overlay_above_number = flag(true)
+            }
+            "SliderBallFlip" => self.slider_ball_flip = flag(true),
+            _ => {}
         }
     }
 
-    fn tex_load_internal(&self, atlas: &mut TextureAtlas, name: &str) -> Option<Rc<TextureRegion>> {
+    fn tex_load_internal(
+        &self,
+        source: &AssetSource,
+        atlas: &mut TextureAtlas,
+        name: &str,
+    ) -> Option<Rc<TextureRegion>> {
         // try to load @2x sprite first
-        if let Ok(img) = lodepng::decode32_file(format!("{}/{}@2x.png", self.base_path, name)) {
-            Some(atlas.add(
-                name,
-                img.buffer.as_bytes(),
-                img.width as u32,
-                img.height as u32,
-                2.0,
-            ))
-        } else if let Ok(img) = lodepng::decode32_file(format!("{}/{}.png", self.base_path, name)) {
-            Some(atlas.add(
-                name,
-                img.buffer.as_bytes(),
-                img.width as u32,
-                img.height as u32,
-                1.0,
-            ))
-        } else {
-            None
+        for (filename, dpi_scale) in [
+            (format!("{}@2x.png", name), 2.0),
+            (format!("{}.png", name), 1.0),
+        ] {
+            let data = match source.read_file(&filename) {
+                Some(x) => x,
+                None => continue,
+            };
+            match lodepng::decode32(&data) {
+                Ok(img) => {
+                    return Some(atlas.add(
+                        name,
+                        img.buffer.as_bytes(),
+                        img.width as u32,
+                        img.height as u32,
+                        dpi_scale,
+                    ))
+                }
+                Err(e) => warn!("Failed to decode {}: {}", filename, e),
+            }
         }
+        None
     }
 
     pub fn try_load_tex(
         &self,
+        source: &AssetSource,
         atlas: &mut TextureAtlas,
         name: &str,
         animated: bool,
@@ -51,25 +320,67 @@ impl Skin {
         // TODO: clean this up once let chains work properly
         if animated {
             let dash = if has_dash { "-" } else { "" };
-            if let Some(first_tex) = self.tex_load_internal(atlas, &format!("{}{}0", dash, name)) {
+            if let Some(first_tex) =
+                self.tex_load_internal(source, atlas, &format!("{}{}0", dash, name))
+            {
                 let mut textures = vec![first_tex];
                 let mut idx = 1;
                 while let Some(tex) =
-                    self.tex_load_internal(atlas, &format!("{}{}{}", name, dash, idx))
+                    self.tex_load_internal(source, atlas, &format!("{}{}{}", name, dash, idx))
                 {
                     textures.push(tex.clone());
                     idx += 1;
                 }
 
-                // TODO: fix framerate once i start parsing skin.ini
-                Some(Rc::new(AnimatedTexture::new(&textures, -1.0)))
+                Some(Rc::new(AnimatedTexture::new(
+                    &textures,
+                    self.animation_framerate,
+                )))
             } else {
-                self.tex_load_internal(atlas, name)
+                self.tex_load_internal(source, atlas, name)
                     .map(|tex| Rc::new(AnimatedTexture::new(&[tex], -1.0)))
             }
         } else {
-            self.tex_load_internal(atlas, name)
+            self.tex_load_internal(source, atlas, name)
                 .map(|tex| Rc::new(AnimatedTexture::new(&[tex], -1.0)))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_ini() {
+        let mut skin = Skin::default();
+        skin.parse_ini(&mut BufReader::new(File::open("test/skin.ini").unwrap()));
+
+        assert_eq!(skin.name, "test skin");
+        assert_eq!(skin.version, 2.5);
+        assert_eq!(skin.animation_framerate, 30.0);
+        assert!(!skin.cursor_centre);
+        assert!(!skin.hit_circle_overlay_above_number);
+        assert!(skin.slider_ball_flip); // invalid values keep the default
+        assert!(skin.allow_slider_ball_tint);
+
+        // BGR, Combo9 doesn't count
+        assert_eq!(skin.combo_colours, vec![0x0000FF, 0x00FF00, 0xFF0000]);
+        assert_eq!(skin.slider_border, 0x00FFFF);
+        assert_eq!(skin.slider_track_override, Some(0x141414));
+        assert_eq!(skin.slider_ball, None);
+
+        assert_eq!(skin.hit_circle_prefix, "fonts/circle");
+        assert_eq!(skin.hit_circle_overlap, 5.0);
+        assert_eq!(skin.score_prefix, "fonts/score");
+        assert_eq!(skin.score_overlap, 0.0);
+        // only [Fonts] counts, not the mania section
+        assert_eq!(skin.combo_prefix, "score");
+
+        // no skin.ini at all is the oldest version
+        assert_eq!(Skin::new(Some("test/nonexistent")).version, 1.0);
+        assert_eq!(Skin::new(None).version, SKIN_LATEST_VERSION);
+    }
+}
@@ -0,0 +1,315 @@
+use std::{cell::RefCell, rc::Rc};
+
+use sdl2::{event::Event, keyboard::Keycode};
+
+use crate::{
+    beatmap::Mods,
+    framework::render::{Alignment, DrawBatch, Origin, TextRenderer, TextSprite, TextureRegion},
+    library::{Library, LibraryEntry, SortMode},
+    math::Vector2,
+};
+
+use super::main::PlayOptions;
+
+const HEADER_HEIGHT: f32 = 80.0;
+const FOOTER_HEIGHT: f32 = 96.0;
+const ROW_HEIGHT: f32 = 40.0;
+const ROW_SCALE: f32 = 0.25;
+const PAGE_SIZE: usize = 10;
+
+pub enum SongSelectAction {
+    None,
+    Play(PlayOptions),
+    Quit,
+}
+
+// a plain list of every difficulty in the library, typing searches it
+pub struct SongSelect {
+    library: Library,
+    mods: Mods,
+    autoplay: bool,
+
+    query: String,
+    sort: SortMode,
+    results: Vec<usize>, // indices into the library
+    selected: usize,     // index into results
+
+    batch: DrawBatch,
+    highlight_tex: Rc<TextureRegion>,
+    search_text: TextSprite,
+    sort_text: TextSprite,
+    rows: Vec<(TextSprite, TextSprite)>, // name on the left, stars on the right
+    details_text: TextSprite,
+    help_text: TextSprite,
+}
+
+fn format_length(ms: i32) -> String {
+    let secs = ms.max(0) / 1000;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn format_bpm(entry: &LibraryEntry) -> String {
+    if (entry.max_bpm - entry.min_bpm).abs() < 0.5 {
+        format!("{:.0}", entry.max_bpm)
+    } else {
+        format!("{:.0}-{:.0}", entry.min_bpm, entry.max_bpm)
+    }
+}
+
+impl SongSelect {
+    pub fn new(
+        library: Library,
+        mods: Mods,
+        autoplay: bool,
+        text_renderer: Rc<RefCell<TextRenderer>>,
+        width: f32,
+        height: f32,
+    ) -> SongSelect {
+        let ortho = cgmath::ortho(0.0, width, height, 0.0, -1.0, 1.0);
+        let white = TextureRegion::white();
+        let highlight_tex = Rc::new(TextureRegion {
+            width,
+            height: ROW_HEIGHT,
+            ..(*white).clone()
+        });
+
+        let row_count = ((height - HEADER_HEIGHT - FOOTER_HEIGHT) / ROW_HEIGHT).max(1.0) as usize;
+        let rows = (0..row_count)
+            .map(|i| {
+                let y = HEADER_HEIGHT + i as f32 * ROW_HEIGHT;
+                (
+                    TextSprite::new(
+                        text_renderer.clone(),
+                        "",
+                        16.0,
+                        y,
+                        ROW_SCALE,
+                        Alignment::Left,
+                    ),
+                    TextSprite::new(
+                        text_renderer.clone(),
+                        "",
+                        width - 16.0,
+                        y,
+                        ROW_SCALE,
+                        Alignment::Right,
+                    ),
+                )
+            })
+            .collect();
+
+        let mut song_select = SongSelect {
+            library,
+            mods,
+            autoplay,
+            query: String::new(),
+            sort: SortMode::Artist,
+            results: Vec::new(),
+            selected: 0,
+            batch: DrawBatch::new(ortho),
+            highlight_tex,
+            search_text: TextSprite::new(
+                text_renderer.clone(),
+                "",
+                16.0,
+                8.0,
+                0.35,
+                Alignment::Left,
+            ),
+            sort_text: TextSprite::new(
+                text_renderer.clone(),
+                "",
+                width - 16.0,
+                8.0,
+                0.35,
+                Alignment::Right,
+            ),
+            rows,
+            details_text: TextSprite::new(
+                text_renderer.clone(),
+                "",
+                16.0,
+                height - FOOTER_HEIGHT + 8.0,
+                0.25,
+                Alignment::Left,
+            ),
+            help_text: TextSprite::new(
+                text_renderer,
+                "",
+                16.0,
+                height - FOOTER_HEIGHT / 2.0,
+                0.2,
+                Alignment::Left,
+            ),
+        };
+        song_select.search();
+        song_select
+    }
+
+    pub fn get_title(&self) -> String {
+        "ehh | song select".to_string()
+    }
+
+    pub fn selected_entry(&self) -> Option<&LibraryEntry> {
+        self.results
+            .get(self.selected)
+            .map(|&i| &self.library.entries[i])
+    }
+
+    // keeps the same map selected if it's still in the results
+    fn search(&mut self) {
+        let previous = self.results.get(self.selected).copied();
+        self.results = self.library.search(&self.query, self.sort);
+        self.selected = previous
+            .and_then(|x| self.results.iter().position(|&y| y == x))
+            .unwrap_or(0);
+        self.refresh();
+    }
+
+    fn select(&mut self, selected: isize) {
+        let last = self.results.len().saturating_sub(1) as isize;
+        self.selected = selected.clamp(0, last) as usize;
+        self.refresh();
+    }
+
+    // the selection stays in the middle of the list unless it's near either end
+    fn first_visible(&self) -> usize {
+        let rows = self.rows.len();
+        self.selected
+            .saturating_sub(rows / 2)
+            .min(self.results.len().saturating_sub(rows))
+    }
+
+    fn refresh(&mut self) {
+        self.search_text
+            .set_text(&format!("Search: {}_", self.query));
+        self.sort_text.set_text(&format!(
+            "{} maps, sorted by {}",
+            self.results.len(),
+            self.sort.name()
+        ));
+
+        let first = self.first_visible();
+        for (i, (name, stars)) in self.rows.iter_mut().enumerate() {
+            match self.results.get(first + i) {
+                Some(&index) => {
+                    let entry = &self.library.entries[index];
+                    name.set_text(&format!(
+                        "{} - {} [{}]",
+                        entry.romanized_artist, entry.romanized_title, entry.version
+                    ));
+                    stars.set_text(&match entry.stars {
+                        Some(x) => format!("{:.2}*", x),
+                        None => format!("{:?}", entry.mode),
+                    });
+                }
+                None => {
+                    name.set_text("");
+                    stars.set_text("");
+                }
+            }
+        }
+
+        let details = match self.selected_entry() {
+            Some(entry) => format!(
+                "Mapped by {} | {} | {} BPM | {}x | CS {} AR {} OD {} HP {}",
+                entry.creator,
+                format_length(entry.length),
+                format_bpm(entry),
+                entry.max_combo,
+                entry.circle_size,
+                entry.approach_rate,
+                entry.overall_difficulty,
+                entry.hp_drain
+            ),
+            None if self.library.entries.is_empty() => {
+                "The library is empty, set SongsFolder in the config".to_string()
+            }
+            None => "Nothing matches the search".to_string(),
+        };
+        self.details_text.set_text(&details);
+        self.help_text.set_text(&format!(
+            "Enter: {} with {} | Tab: change sort | Esc: clear search, then quit",
+            if self.autoplay { "watch" } else { "play" },
+            self.mods
+        ));
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> SongSelectAction {
+        match event {
+            Event::TextInput { text, .. } => {
+                self.query.push_str(text);
+                self.search();
+            }
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => {
+                let selected = self.selected as isize;
+                match *keycode {
+                    Keycode::Backspace => {
+                        self.query.pop();
+                        self.search();
+                    }
+                    Keycode::Escape => {
+                        if self.query.is_empty() {
+                            return SongSelectAction::Quit;
+                        }
+                        self.query.clear();
+                        self.search();
+                    }
+                    Keycode::Tab => {
+                        self.sort = self.sort.next();
+                        self.search();
+                    }
+                    Keycode::Up => self.select(selected - 1),
+                    Keycode::Down => self.select(selected + 1),
+                    Keycode::PageUp => self.select(selected - PAGE_SIZE as isize),
+                    Keycode::PageDown => self.select(selected + PAGE_SIZE as isize),
+                    Keycode::Home => self.select(0),
+                    Keycode::End => self.select(isize::MAX),
+                    Keycode::Return | Keycode::KpEnter => {
+                        if let Some(entry) = self.selected_entry() {
+                            return SongSelectAction::Play(PlayOptions {
+                                beatmap_path: entry.path.clone(),
+                                difficulty: Some(entry.filename.clone()),
+                                mods: self.mods,
+                                autoplay: self.autoplay,
+                                practice: None,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::MouseWheel { y, .. } => self.select(self.selected as isize - *y as isize),
+            _ => {}
+        }
+        SongSelectAction::None
+    }
+
+    pub fn draw(&mut self) {
+        if !self.results.is_empty() {
+            let row = self.selected - self.first_visible();
+            self.batch.add(
+                self.highlight_tex.clone(),
+                Vector2::new(0.0, HEADER_HEIGHT + row as f32 * ROW_HEIGHT),
+                1.0,
+                Origin::TopLeft,
+                0x40FFFFFF,
+                0.0,
+            );
+        }
+
+        self.search_text.add_to_batch(&mut self.batch);
+        self.sort_text.add_to_batch(&mut self.batch);
+        for (name, stars) in &self.rows {
+            name.add_to_batch(&mut self.batch);
+            stars.add_to_batch(&mut self.batch);
+        }
+        self.details_text.add_to_batch(&mut self.batch);
+        self.help_text.add_to_batch(&mut self.batch);
+
+        self.batch.draw();
+    }
+}
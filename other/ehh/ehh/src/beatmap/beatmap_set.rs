@@ -0,0 +1,305 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use zip::ZipArchive;
+
+use super::*;
+
+enum SetSource {
+    Folder(PathBuf),
+    Archive(Mutex<ZipArchive<BufReader<File>>>),
+}
+
+// every file that a beatmap can reference, either from an extracted folder or straight out of an .osz
+// filenames always use forward slashes and are looked up case insensitively like they would be on windows
+pub struct BeatmapSet {
+    source: SetSource,
+    names: OnceLock<HashMap<String, String>>, // lowercase name to the real one, built the first time a lookup misses
+}
+
+impl Default for BeatmapSet {
+    fn default() -> Self {
+        BeatmapSet::from_folder(".")
+    }
+}
+
+impl BeatmapSet {
+    // picks the right source based on the path
+    // a loose .osu file opens the folder it's in
+    pub fn open(path: impl AsRef<Path>) -> io::Result<BeatmapSet> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            return Ok(Self::from_folder(path));
+        }
+
+        match path.extension().and_then(|x| x.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("osz") => Self::from_osz(path),
+            _ => {
+                let mut folder = path.to_path_buf();
+                folder.pop();
+                Ok(Self::from_folder(folder))
+            }
+        }
+    }
+
+    pub fn from_folder(path: impl AsRef<Path>) -> BeatmapSet {
+        BeatmapSet {
+            source: SetSource::Folder(path.as_ref().to_path_buf()),
+            names: OnceLock::new(),
+        }
+    }
+
+    pub fn from_osz(path: impl AsRef<Path>) -> io::Result<BeatmapSet> {
+        let archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+        Ok(BeatmapSet {
+            source: SetSource::Archive(Mutex::new(archive)),
+            names: OnceLock::new(),
+        })
+    }
+
+    pub fn is_archive(&self) -> bool {
+        matches!(self.source, SetSource::Archive(_))
+    }
+
+    pub fn files(&self) -> Vec<String> {
+        let mut out: Vec<String> = match &self.source {
+            SetSource::Folder(path) => walkdir::WalkDir::new(path)
+                .into_iter()
+                .filter_map(|x| x.ok())
+                .filter(|x| x.file_type().is_file())
+                .filter_map(|x| {
+                    x.path()
+                        .strip_prefix(path)
+                        .ok()
+                        .map(|x| x.to_string_lossy().replace('\\', "/"))
+                })
+                .collect(),
+            SetSource::Archive(archive) => archive
+                .lock()
+                .unwrap()
+                .file_names()
+                .filter(|x| !x.ends_with('/'))
+                .map(|x| x.replace('\\', "/"))
+                .collect(),
+        };
+        out.sort();
+        out
+    }
+
+    // every .osu in the set, sorted by filename
+    pub fn difficulties(&self) -> Vec<String> {
+        self.files()
+            .into_iter()
+            .filter(|x| x.to_lowercase().ends_with(".osu"))
+            .collect()
+    }
+
+    pub fn read_file(&self, name: &str) -> io::Result<Vec<u8>> {
+        let name = name.trim().replace('\\', "/");
+        let mut out = Vec::new();
+        match &self.source {
+            SetSource::Folder(path) => {
+                let direct = path.join(&name);
+                let path = if direct.is_file() {
+                    direct
+                } else {
+                    path.join(self.find_file(&name)?)
+                };
+                File::open(path)?.read_to_end(&mut out)?;
+            }
+            SetSource::Archive(archive) => {
+                let exact = match archive.lock().unwrap().by_name(&name) {
+                    Ok(mut x) => Some(x.read_to_end(&mut out)),
+                    Err(_) => None,
+                };
+                match exact {
+                    Some(x) => {
+                        x?;
+                    }
+                    None => {
+                        let found = self.find_file(&name)?;
+                        archive
+                            .lock()
+                            .unwrap()
+                            .by_name(&found)?
+                            .read_to_end(&mut out)?;
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        let name = name.trim().replace('\\', "/");
+        if let SetSource::Folder(path) = &self.source {
+            if path.join(&name).is_file() {
+                return true;
+            }
+        }
+        self.find_file(&name).is_ok()
+    }
+
+    pub fn parse_difficulty(self: &Arc<Self>, name: &str) -> Result<Beatmap, BeatmapParseErr> {
+        self.parse_difficulty_with_mods(name, Mods::empty())
+    }
+
+    pub fn parse_difficulty_with_mods(
+        self: &Arc<Self>,
+        name: &str,
+        mods: Mods,
+    ) -> Result<Beatmap, BeatmapParseErr> {
+        self.parse_difficulty_with_mode(name, mods, ParseMode::Strict)
+    }
+
+    pub fn parse_difficulty_with_mode(
+        self: &Arc<Self>,
+        name: &str,
+        mods: Mods,
+        mode: ParseMode,
+    ) -> Result<Beatmap, BeatmapParseErr> {
+        let data = self.read_file(name)?;
+        let mut beatmap = Beatmap::parse_with_mode(self.clone(), &mut data.as_slice(), mods, mode)?;
+        beatmap.hash = format!("{:x}", md5::compute(&data));
+        Ok(beatmap)
+    }
+
+    // loads a loose .osu, or one difficulty out of a folder or .osz
+    // difficulties can be picked by filename or by the name in the brackets, otherwise the first one gets used
+    pub fn open_difficulty(
+        path: impl AsRef<Path>,
+        difficulty: Option<&str>,
+        mods: Mods,
+    ) -> Result<Beatmap, BeatmapParseErr> {
+        let path = path.as_ref();
+        let set = Arc::new(Self::open(path)?);
+        if !path.is_dir() && !set.is_archive() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            return set.parse_difficulty_with_mods(&name, mods);
+        }
+
+        let difficulties = set.difficulties();
+        let name = match difficulty {
+            Some(x) => {
+                let lower = x.to_lowercase();
+                let bracketed = format!("[{}].osu", lower);
+                difficulties.iter().find(|y| {
+                    let y = y.to_lowercase();
+                    y == lower || y.ends_with(&bracketed)
+                })
+            }
+            None => difficulties.first(),
+        };
+        match name {
+            Some(name) => set.parse_difficulty_with_mods(name, mods),
+            None => Err(BeatmapParseErr::IoError(not_found(
+                difficulty.unwrap_or(".osu"),
+            ))),
+        }
+    }
+
+    // case insensitive fallback for when the exact name doesn't exist
+    // gives back the name as it is in the folder or archive
+    fn find_file(&self, name: &str) -> io::Result<String> {
+        let names = self.names.get_or_init(|| {
+            let real: Vec<String> = match &self.source {
+                SetSource::Folder(_) => self.files(),
+                SetSource::Archive(archive) => archive
+                    .lock()
+                    .unwrap()
+                    .file_names()
+                    .filter(|x| !x.ends_with('/'))
+                    .map(|x| x.to_string())
+                    .collect(),
+            };
+            let mut names = HashMap::with_capacity(real.len());
+            for x in real {
+                names
+                    .entry(x.replace('\\', "/").to_lowercase())
+                    .or_insert(x);
+            }
+            names
+        });
+        names
+            .get(&name.to_lowercase())
+            .cloned()
+            .ok_or_else(|| not_found(name))
+    }
+}
+
+fn not_found(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} doesn't exist in the beatmap set", name),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::beatmap::*;
+
+    #[test]
+    fn test_osz() {
+        let set = Arc::new(BeatmapSet::open("test/test_set.osz").unwrap());
+        assert!(set.is_archive());
+        assert_eq!(
+            set.difficulties(),
+            &[
+                "Khangaroo - ehh test maps (khangaroood) [combo colours].osu",
+                "Khangaroo - ehh test maps (khangaroood) [simple slider].osu",
+            ]
+        );
+
+        // osu doesn't care about case or which slashes are used
+        assert_eq!(set.read_file("sb\\STAR.png").unwrap(), b"not really a png");
+        assert!(set.exists("AUDIO.MP3"));
+        assert!(!set.exists("video.avi"));
+
+        for x in set.difficulties() {
+            let beatmap = set.parse_difficulty(&x).unwrap();
+            assert!(x.contains(&format!("[{}]", beatmap.version)));
+            assert_eq!(
+                beatmap.set.read_file(&beatmap.audio_filename).unwrap(),
+                b"not really an mp3"
+            );
+        }
+    }
+
+    #[test]
+    fn test_folder() {
+        let set = Arc::new(BeatmapSet::open("test/simple_slider.osu").unwrap());
+        assert!(!set.is_archive());
+        assert!(set
+            .difficulties()
+            .contains(&"storyboard_events.osu".to_string()));
+
+        let beatmap = set.parse_difficulty("SIMPLE_SLIDER.osu").unwrap();
+        assert_eq!(beatmap.version, "simple slider");
+    }
+
+    #[test]
+    fn test_open_difficulty() {
+        let beatmap =
+            BeatmapSet::open_difficulty("test/test_set.osz", None, Mods::empty()).unwrap();
+        assert_eq!(beatmap.version, "combo colours");
+        let beatmap =
+            BeatmapSet::open_difficulty("test/test_set.osz", Some("Simple Slider"), Mods::empty())
+                .unwrap();
+        assert_eq!(beatmap.version, "simple slider");
+        assert!(
+            BeatmapSet::open_difficulty("test/test_set.osz", Some("insane"), Mods::empty())
+                .is_err()
+        );
+
+        let beatmap =
+            BeatmapSet::open_difficulty("test/storyboard_events.osu", None, Mods::empty()).unwrap();
+        assert!(beatmap.events.background.is_some());
+    }
+}
@@ -0,0 +1,457 @@
+// modding checks that the parser doesn't care about, but ranking would
+// none of these stop a map from loading, they're just things that are probably mistakes
+
+use crate::{json::json_string, math::Vector2};
+
+use super::*;
+
+const PLAYFIELD_WIDTH: f32 = 512.0;
+const PLAYFIELD_HEIGHT: f32 = 384.0;
+
+// anything further than this from every snap is unsnapped, times are rounded so 1ms is always allowed
+const SNAP_LENIENCY: f64 = 1.0;
+const SNAP_DIVISORS: [f64; 8] = [1.0, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckRule {
+    OffScreen,
+    Unsnapped,
+    OverlappingSpinner,
+    BrokenSlider,
+    MissingFile,
+    InconsistentMetadata,
+}
+
+impl CheckRule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CheckRule::OffScreen => "off-screen",
+            CheckRule::Unsnapped => "unsnapped",
+            CheckRule::OverlappingSpinner => "overlapping-spinner",
+            CheckRule::BrokenSlider => "broken-slider",
+            CheckRule::MissingFile => "missing-file",
+            CheckRule::InconsistentMetadata => "inconsistent-metadata",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckWarning {
+    pub rule: CheckRule,
+    pub line: Option<u32>, // in the .osu
+    pub time: Option<i32>,
+    pub message: String,
+}
+
+impl CheckWarning {
+    fn new(rule: CheckRule, line: u32, time: Option<i32>, message: String) -> CheckWarning {
+        CheckWarning {
+            rule,
+            line: if line > 0 { Some(line) } else { None },
+            time,
+            message,
+        }
+    }
+
+    fn at_object(rule: CheckRule, obj: &HitObject, message: String) -> CheckWarning {
+        CheckWarning::new(rule, obj.line, Some(obj.start), message)
+    }
+
+    // one json object, no trailing newline
+    pub fn to_json(&self) -> String {
+        let number = |x: Option<String>| x.unwrap_or_else(|| "null".to_string());
+        format!(
+            "{{\"rule\":\"{}\",\"line\":{},\"time\":{},\"message\":{}}}",
+            self.rule.name(),
+            number(self.line.map(|x| x.to_string())),
+            number(self.time.map(|x| x.to_string())),
+            json_string(&self.message)
+        )
+    }
+}
+
+impl std::fmt::Display for CheckWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if let Some(time) = self.time {
+            write!(f, "{} ", format_timestamp(time))?;
+        }
+        write!(f, "[{}] {}", self.rule.name(), self.message)
+    }
+}
+
+// the same mm:ss:mmm that the editor uses, so it can be pasted straight into it
+pub fn format_timestamp(time: i32) -> String {
+    let sign = if time < 0 { "-" } else { "" };
+    let time = time.unsigned_abs();
+    format!(
+        "{}{:02}:{:02}:{:03}",
+        sign,
+        time / 60000,
+        time / 1000 % 60,
+        time % 1000
+    )
+}
+
+fn on_screen(pos: Vector2) -> bool {
+    (0.0..=PLAYFIELD_WIDTH).contains(&pos.x) && (0.0..=PLAYFIELD_HEIGHT).contains(&pos.y)
+}
+
+// how far the time is from the closest snap on any of the usual divisors
+fn snap_distance(point: &TimingPoint, time: f64) -> f64 {
+    SNAP_DIVISORS
+        .iter()
+        .map(|divisor| {
+            let step = point.beat_length / divisor;
+            let snapped = point.offset + ((time - point.offset) / step).round() * step;
+            (time - snapped).abs()
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+impl Beatmap {
+    // everything that can be checked with just this difficulty
+    pub fn check(&self) -> Vec<CheckWarning> {
+        let mut out = Vec::new();
+        self.check_files(&mut out);
+        self.check_off_screen(&mut out);
+        self.check_snapping(&mut out);
+        self.check_spinners(&mut out);
+        self.check_sliders(&mut out);
+        out.sort_by_key(|x| (x.line.unwrap_or(0), x.time.unwrap_or(i32::MIN)));
+        out
+    }
+
+    fn check_files(&self, out: &mut Vec<CheckWarning>) {
+        if self.audio_filename.is_empty() {
+            out.push(CheckWarning::new(
+                CheckRule::MissingFile,
+                0,
+                None,
+                "there's no audio file".to_string(),
+            ));
+        }
+        let files = [
+            Some(("audio", &self.audio_filename)).filter(|(_, x)| !x.is_empty()),
+            self.events
+                .background
+                .as_ref()
+                .map(|x| ("background", &x.filename)),
+            self.events.video.as_ref().map(|x| ("video", &x.filename)),
+        ];
+        for (kind, filename) in files.into_iter().flatten() {
+            if !self.set.exists(filename) {
+                out.push(CheckWarning::new(
+                    CheckRule::MissingFile,
+                    0,
+                    None,
+                    format!("the {} \"{}\" isn't in the set", kind, filename),
+                ));
+            }
+        }
+    }
+
+    // stacking can push objects near the top left off of it, so this has to use the stacked positions
+    fn check_off_screen(&self, out: &mut Vec<CheckWarning>) {
+        if self.mode != Gamemode::Osu {
+            return;
+        }
+        for x in &self.hit_objects {
+            if x.object_type == HitObjectType::Spinner {
+                continue;
+            }
+            let message = if !on_screen(x.start_pos) {
+                format!(
+                    "starts off-screen at ({}, {})",
+                    x.start_pos.x, x.start_pos.y
+                )
+            } else if let Some(slider_info) = &x.slider_info {
+                let off_screen = slider_info
+                    .curve
+                    .lines
+                    .iter()
+                    .flat_map(|line| [line.p1, line.p2])
+                    .map(|pos| pos - x.stack_offset)
+                    .find(|&pos| !on_screen(pos));
+                match off_screen {
+                    Some(pos) => format!(
+                        "slider body goes off-screen at ({:.0}, {:.0})",
+                        pos.x, pos.y
+                    ),
+                    None => continue,
+                }
+            } else {
+                continue;
+            };
+            out.push(CheckWarning::at_object(CheckRule::OffScreen, x, message));
+        }
+    }
+
+    fn check_snapping(&self, out: &mut Vec<CheckWarning>) {
+        for x in &self.hit_objects {
+            let mut times = vec![("start", x.start)];
+            if x.end != x.start {
+                times.push(("end", x.end));
+            }
+            for (which, time) in times {
+                let point = match self.timing.uninherited_at(time as f64) {
+                    Some(x) if x.beat_length.is_finite() && x.beat_length > 0.0 => x,
+                    _ => continue,
+                };
+                let distance = snap_distance(point, time as f64);
+                if distance > SNAP_LENIENCY {
+                    out.push(CheckWarning::new(
+                        CheckRule::Unsnapped,
+                        x.line,
+                        Some(time),
+                        format!(
+                            "{} is unsnapped by {:.0}ms from the timing point on line {}",
+                            which, distance, point.line
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn check_spinners(&self, out: &mut Vec<CheckWarning>) {
+        // objects are sorted by start time, so anything that overlaps the current one is still in here
+        let mut active: Vec<&HitObject> = Vec::new();
+        for x in &self.hit_objects {
+            // something starting right as the spinner ends doesn't overlap it
+            active.retain(|y| y.end > x.start);
+            // each pair only gets reported once, on the spinner or the later one if they both are
+            for &earlier in &active {
+                let (spinner, other) = match (&earlier.object_type, &x.object_type) {
+                    (_, HitObjectType::Spinner) => (x, earlier),
+                    (HitObjectType::Spinner, _) => (earlier, x),
+                    _ => continue,
+                };
+                out.push(CheckWarning::at_object(
+                    CheckRule::OverlappingSpinner,
+                    spinner,
+                    format!(
+                        "overlaps the object at {} on line {}",
+                        format_timestamp(other.start),
+                        other.line
+                    ),
+                ));
+            }
+            active.push(x);
+        }
+    }
+
+    fn check_sliders(&self, out: &mut Vec<CheckWarning>) {
+        for x in &self.hit_objects {
+            let slider_info = match &x.slider_info {
+                Some(x) => x,
+                None => continue,
+            };
+
+            if slider_info.spatial_length <= 0.0 || slider_info.curve.length <= 0.0 {
+                out.push(CheckWarning::at_object(
+                    CheckRule::BrokenSlider,
+                    x,
+                    "slider has no length".to_string(),
+                ));
+            }
+
+            // the parser quietly falls back to the uninherited point, but stable doesn't
+            let broken_point = self
+                .timing
+                .inherited_at(x.start as f64)
+                .filter(|point| !point.beat_length.is_finite());
+            if let Some(point) = broken_point {
+                out.push(CheckWarning::at_object(
+                    CheckRule::BrokenSlider,
+                    x,
+                    format!(
+                        "slider velocity comes from a broken timing point on line {}",
+                        point.line
+                    ),
+                ));
+            } else if !slider_info.velocity.is_finite() || slider_info.velocity <= 0.0 {
+                out.push(CheckWarning::at_object(
+                    CheckRule::BrokenSlider,
+                    x,
+                    format!("slider has an invalid velocity of {}", slider_info.velocity),
+                ));
+            }
+        }
+    }
+}
+
+// checks every difficulty on its own, then makes sure they all agree with the first one
+// one list per difficulty, in the same order
+pub fn check_set(beatmaps: &[Beatmap]) -> Vec<Vec<CheckWarning>> {
+    let mut out: Vec<Vec<CheckWarning>> = beatmaps.iter().map(|x| x.check()).collect();
+    let first = match beatmaps.first() {
+        Some(x) => x,
+        None => return out,
+    };
+
+    let fields = |x: &Beatmap| {
+        [
+            ("Artist", x.romanized_artist.clone()),
+            ("ArtistUnicode", x.artist.clone()),
+            ("Title", x.romanized_title.clone()),
+            ("TitleUnicode", x.title.clone()),
+            ("Creator", x.creator.clone()),
+            ("Source", x.source.clone()),
+            ("Tags", x.tags.clone()),
+            ("BeatmapSetID", x.beatmap_set_id.to_string()),
+            ("AudioFilename", x.audio_filename.clone()),
+        ]
+    };
+    let expected = fields(first);
+    for (i, beatmap) in beatmaps.iter().enumerate().skip(1) {
+        for ((key, expected), (_, actual)) in expected.iter().zip(fields(beatmap)) {
+            if *expected != actual {
+                out[i].push(CheckWarning::new(
+                    CheckRule::InconsistentMetadata,
+                    0,
+                    None,
+                    format!(
+                        "{} is \"{}\", but it's \"{}\" in [{}]",
+                        key, actual, expected, first.version
+                    ),
+                ));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn rules(warnings: &[CheckWarning]) -> Vec<CheckRule> {
+        warnings.iter().map(|x| x.rule).collect()
+    }
+
+    #[test]
+    fn test_check() {
+        let beatmap = Beatmap::fixture("test/check.osu");
+        let warnings = beatmap.check();
+
+        let missing: Vec<&CheckWarning> = warnings
+            .iter()
+            .filter(|x| x.rule == CheckRule::MissingFile)
+            .collect();
+        assert_eq!(missing.len(), 2); // audio and background
+        assert!(missing.iter().all(|x| x.line.is_none()));
+
+        let at_line = |line: u32| {
+            warnings
+                .iter()
+                .filter(|x| x.line == Some(line))
+                .map(|x| x.rule)
+                .collect::<Vec<CheckRule>>()
+        };
+        // the first four objects are fine
+        for line in 56..=59 {
+            assert!(at_line(line).is_empty(), "line {}", line);
+        }
+        assert_eq!(at_line(60), [CheckRule::OffScreen]);
+        assert_eq!(at_line(61), [CheckRule::Unsnapped]);
+        assert_eq!(at_line(62), [CheckRule::BrokenSlider]);
+        assert_eq!(at_line(63), [CheckRule::OverlappingSpinner]);
+        assert_eq!(at_line(64), []);
+        let unsnapped = warnings
+            .iter()
+            .find(|x| x.rule == CheckRule::Unsnapped)
+            .unwrap();
+        assert_eq!(unsnapped.time, Some(4010));
+
+        // a second spinner inside the first, with the circle at 6000 overlapping both
+        let source = std::fs::read_to_string("test/check.osu").unwrap().replace(
+            "256,192,6000,1",
+            "256,192,5500,12,0,6500,0:0:0:0:\n256,192,6000,1",
+        );
+        let overlapping = Beatmap::parse(
+            Arc::new(BeatmapSet::open("test/check.osu").unwrap()),
+            &mut source.as_bytes(),
+        )
+        .unwrap();
+        let overlaps: Vec<(Option<u32>, String)> = overlapping
+            .check()
+            .into_iter()
+            .filter(|x| x.rule == CheckRule::OverlappingSpinner)
+            .map(|x| (x.line, x.message))
+            .collect();
+        assert_eq!(
+            overlaps,
+            [
+                (
+                    Some(63),
+                    "overlaps the object at 00:06:000 on line 65".to_string()
+                ),
+                (
+                    Some(64),
+                    "overlaps the object at 00:05:000 on line 63".to_string()
+                ),
+                (
+                    Some(64),
+                    "overlaps the object at 00:06:000 on line 65".to_string()
+                ),
+            ]
+        );
+
+        // a circle right at the end of the spinner is fine
+        let source = std::fs::read_to_string("test/check.osu")
+            .unwrap()
+            .replace("256,192,6000,1", "256,192,7000,1");
+        let touching = Beatmap::parse(
+            Arc::new(BeatmapSet::open("test/check.osu").unwrap()),
+            &mut source.as_bytes(),
+        )
+        .unwrap();
+        assert!(!rules(&touching.check()).contains(&CheckRule::OverlappingSpinner));
+
+        let nan = Beatmap::fixture("test/nan_slider_velocity.osu");
+        assert!(rules(&nan.check()).contains(&CheckRule::BrokenSlider));
+
+        // the test set has everything it needs
+        let set = Arc::new(BeatmapSet::open("test/test_set.osz").unwrap());
+        let beatmaps: Vec<Beatmap> = set
+            .difficulties()
+            .iter()
+            .map(|x| set.parse_difficulty(x).unwrap())
+            .collect();
+        assert!(!rules(&check_set(&beatmaps).concat()).contains(&CheckRule::InconsistentMetadata));
+    }
+
+    #[test]
+    fn test_check_metadata() {
+        let mut beatmaps = vec![
+            Beatmap::fixture("test/jumps.osu"),
+            Beatmap::fixture("test/jumps.osu"),
+        ];
+        beatmaps[1].creator = "someone else".to_string();
+        let warnings = check_set(&beatmaps);
+        assert!(!rules(&warnings[0]).contains(&CheckRule::InconsistentMetadata));
+        let inconsistent: Vec<&CheckWarning> = warnings[1]
+            .iter()
+            .filter(|x| x.rule == CheckRule::InconsistentMetadata)
+            .collect();
+        assert_eq!(inconsistent.len(), 1);
+        assert!(inconsistent[0].message.starts_with("Creator"));
+
+        let warning = CheckWarning::new(
+            CheckRule::Unsnapped,
+            12,
+            None,
+            "a \"quoted\"\nmessage".to_string(),
+        );
+        assert_eq!(
+            warning.to_json(),
+            r#"{"rule":"unsnapped","line":12,"time":null,"message":"a \"quoted\"\nmessage"}"#
+        );
+        assert_eq!(format_timestamp(83456), "01:23:456");
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::json::json_string;
+
+use super::BeatmapParseErr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseMode {
+    Strict,  // fails on anything that stable wouldn't load
+    Lenient, // skips those lines instead, so as much of the map as possible loads
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Warning, // stable skips over these too, the map is still fine
+    Error,   // stable refuses to load the map
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+// something the parser had to skip or fix up
+#[derive(Debug, Clone)]
+pub struct ParseDiagnostic {
+    pub severity: Severity,
+    pub line: u32,
+    pub field: Option<usize>, // which comma separated value it was, starting from 0
+    pub text: String,         // the whole line, as it was in the file
+    pub message: String,
+}
+
+impl ParseDiagnostic {
+    pub fn new(severity: Severity, line_num: u32, text: &str, message: String) -> Self {
+        ParseDiagnostic {
+            severity,
+            line: line_num,
+            field: None,
+            text: text.trim_end().to_string(),
+            message,
+        }
+    }
+
+    pub(super) fn from_err(
+        severity: Severity,
+        line_num: u32,
+        text: &str,
+        err: &BeatmapParseErr,
+    ) -> Self {
+        let (err, field) = match err {
+            BeatmapParseErr::InField(field, inner) => (&**inner, Some(*field)),
+            _ => (err, None),
+        };
+        ParseDiagnostic {
+            field,
+            ..Self::new(severity, line_num, text, err.description())
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"severity\":\"{}\",\"line\":{},\"field\":{},\"text\":{},\"message\":{}}}",
+            self.severity.name(),
+            self.line,
+            self.field
+                .map(|x| x.to_string())
+                .unwrap_or_else(|| "null".to_string()),
+            json_string(&self.text),
+            json_string(&self.message)
+        )
+    }
+}
+
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}", self.line)?;
+        if let Some(field) = self.field {
+            write!(f, ", field {}", field)?;
+        }
+        write!(
+            f,
+            ": {}: {} (\"{}\")",
+            self.severity.name(),
+            self.message,
+            self.text
+        )
+    }
+}
+
+impl BeatmapParseErr {
+    // where the error was, if it came from a specific line
+    pub fn line(&self) -> Option<u32> {
+        match self {
+            BeatmapParseErr::IoError(_)
+            | BeatmapParseErr::UnsupportedFormatVersion
+            | BeatmapParseErr::UnsupportedMode => None,
+            BeatmapParseErr::InField(_, inner) => inner.line(),
+            BeatmapParseErr::InvalidBool(x)
+            | BeatmapParseErr::InvalidInt(x)
+            | BeatmapParseErr::InvalidFloat(x)
+            | BeatmapParseErr::InvalidEnum(x)
+            | BeatmapParseErr::InvalidTimingPoint(x)
+            | BeatmapParseErr::InvalidEvent(x)
+            | BeatmapParseErr::InvalidColour(x) => Some(*x),
+        }
+    }
+
+    // short and stable, for grouping errors together
+    pub fn category(&self) -> &'static str {
+        match self {
+            BeatmapParseErr::IoError(_) => "io",
+            BeatmapParseErr::UnsupportedFormatVersion => "unsupported-format-version",
+            BeatmapParseErr::UnsupportedMode => "unsupported-mode",
+            BeatmapParseErr::InField(_, inner) => inner.category(),
+            BeatmapParseErr::InvalidBool(_) => "invalid-bool",
+            BeatmapParseErr::InvalidInt(_) => "invalid-int",
+            BeatmapParseErr::InvalidFloat(_) => "invalid-float",
+            BeatmapParseErr::InvalidEnum(_) => "invalid-enum",
+            BeatmapParseErr::InvalidTimingPoint(_) => "invalid-timing-point",
+            BeatmapParseErr::InvalidEvent(_) => "invalid-event",
+            BeatmapParseErr::InvalidColour(_) => "invalid-colour",
+        }
+    }
+
+    // what went wrong, without where
+    fn description(&self) -> String {
+        match self {
+            BeatmapParseErr::IoError(e) => e.to_string(),
+            BeatmapParseErr::UnsupportedFormatVersion => "unsupported format version".to_string(),
+            BeatmapParseErr::UnsupportedMode => "unsupported mode".to_string(),
+            BeatmapParseErr::InField(_, inner) => inner.description(),
+            BeatmapParseErr::InvalidBool(_) => "invalid bool".to_string(),
+            BeatmapParseErr::InvalidInt(_) => "invalid integer".to_string(),
+            BeatmapParseErr::InvalidFloat(_) => "invalid number".to_string(),
+            BeatmapParseErr::InvalidEnum(_) => "unknown value".to_string(),
+            BeatmapParseErr::InvalidTimingPoint(_) => "not enough values".to_string(),
+            BeatmapParseErr::InvalidEvent(_) => "invalid event".to_string(),
+            BeatmapParseErr::InvalidColour(_) => "invalid colour".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for BeatmapParseErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())?;
+        if let Some(line) = self.line() {
+            write!(f, " on line {}", line)?;
+        }
+        if let BeatmapParseErr::InField(field, _) = self {
+            write!(f, ", field {}", field)?;
+        }
+        Ok(())
+    }
+}
@@ -1,14 +1,21 @@
+use super::Mods;
+
+// the stats are always the ones from the file, everything below them has the mods applied
 pub struct Difficulty {
     pub approach_rate: f32,
     pub circle_size: f32,
     pub hp_drain: f32,
     pub overall_difficulty: f32,
+    pub mods: Mods,
 
+    // these stay in beatmap time, so dt/ht don't change them
+    // the song just plays faster or slower, which is what makes the windows tighter or looser
     pub hit_50: i32,
     pub hit_100: i32,
     pub hit_300: i32,
     pub preempt: i32,
     pub preempt_slider_complete: i32,
+    pub spins_per_second: f32, // needed to clear a spinner
 
     pub obj_radius: f32,
     pub stack_offset: f32,
@@ -19,8 +26,7 @@ pub struct Difficulty {
     pub slider_scoring_point_distance: f64,
 }
 
-fn map_diff_range(diff: f32, min: f32, mid: f32, max: f32) -> f32 {
-    // TODO: adjust for hr/ez
+pub(crate) fn map_diff_range(diff: f32, min: f32, mid: f32, max: f32) -> f32 {
     match diff {
         diff if diff > 5.0 => mid + (max - mid) * (diff - 5.0) / 5.0,
         diff if diff < 5.0 => mid - (mid - min) * (5.0 - diff) / 5.0,
@@ -30,22 +36,53 @@ fn map_diff_range(diff: f32, min: f32, mid: f32, max: f32) -> f32 {
 
 impl Difficulty {
     pub fn recalculate(&mut self) {
-        self.hit_50 = map_diff_range(self.overall_difficulty, 200.0, 150.0, 100.0) as i32;
-        self.hit_100 = map_diff_range(self.overall_difficulty, 140.0, 100.0, 60.0) as i32;
-        self.hit_300 = map_diff_range(self.overall_difficulty, 80.0, 50.0, 20.0) as i32;
+        let overall_difficulty = self.mods.adjust_stat(self.overall_difficulty, 1.4);
+        self.hit_50 = map_diff_range(overall_difficulty, 200.0, 150.0, 100.0) as i32;
+        self.hit_100 = map_diff_range(overall_difficulty, 140.0, 100.0, 60.0) as i32;
+        self.hit_300 = map_diff_range(overall_difficulty, 80.0, 50.0, 20.0) as i32;
+        self.spins_per_second = map_diff_range(overall_difficulty, 3.0, 5.0, 7.5);
 
-        self.preempt = map_diff_range(self.approach_rate, 1800.0, 1200.0, 450.0) as i32;
+        let approach_rate = self.mods.adjust_stat(self.approach_rate, 1.4);
+        self.preempt = map_diff_range(approach_rate, 1800.0, 1200.0, 450.0) as i32;
         self.preempt_slider_complete = (self.preempt as f32 * (2.0 / 3.0)) as i32;
 
         // stolen from https://github.com/McKay42/McOsu/blob/f6c96abe53b8c2b124366591c672326925e003d4/src/App/Osu/OsuGameRules.h#L344
-        self.obj_radius =
-            ((1.0 - 0.7 * (self.circle_size - 5.0) / 5.0) / 2.0) * 128.0 * 1.00041 / 2.0;
+        let circle_size = self.mods.adjust_stat(self.circle_size, 1.3);
+        self.obj_radius = ((1.0 - 0.7 * (circle_size - 5.0) / 5.0) / 2.0) * 128.0 * 1.00041 / 2.0;
         self.stack_offset = self.obj_radius / 10.0;
 
         self.slider_scoring_point_distance =
             (100.0 * self.slider_multiplier) / self.slider_tick_rate;
     }
 
+    pub fn hp_drain_adjusted(&self) -> f32 {
+        self.mods.adjust_stat(self.hp_drain, 1.4)
+    }
+
+    pub fn circle_size_adjusted(&self) -> f32 {
+        self.mods.adjust_stat(self.circle_size, 1.3)
+    }
+
+    // what the ar and od feel like in real time once dt/ht are applied
+    // can go above 10 or below 0
+    pub fn approach_rate_adjusted(&self) -> f32 {
+        let approach_rate = self.mods.adjust_stat(self.approach_rate, 1.4);
+        let preempt =
+            map_diff_range(approach_rate, 1800.0, 1200.0, 450.0) / self.mods.clock_rate() as f32;
+        if preempt > 1200.0 {
+            5.0 - (preempt - 1200.0) / 120.0
+        } else {
+            5.0 + (1200.0 - preempt) / 150.0
+        }
+    }
+
+    pub fn overall_difficulty_adjusted(&self) -> f32 {
+        let overall_difficulty = self.mods.adjust_stat(self.overall_difficulty, 1.4);
+        let hit_300 =
+            map_diff_range(overall_difficulty, 80.0, 50.0, 20.0) / self.mods.clock_rate() as f32;
+        (80.0 - hit_300) / 6.0
+    }
+
     pub fn new(
         approach_rate: f32,
         circle_size: f32,
@@ -54,18 +91,19 @@ impl Difficulty {
         slider_multiplier: f64,
         slider_tick_rate: f64,
     ) -> Difficulty {
-        // TODO: adjust for hr/ez
         let mut out = Difficulty {
             approach_rate,
             circle_size,
             hp_drain,
             overall_difficulty,
+            mods: Mods::empty(),
 
             hit_50: 0,
             hit_100: 0,
             hit_300: 0,
             preempt: 0,
             preempt_slider_complete: 0,
+            spins_per_second: 0.0,
 
             obj_radius: 0.0,
             stack_offset: 0.0,
@@ -0,0 +1,726 @@
+use crate::math::Vector2;
+
+use super::*;
+
+// breaks shorter than this don't trigger any break effects (letterboxing, etc)
+pub const MIN_BREAK_DURATION: i32 = 650;
+
+#[derive(Default)]
+pub struct Events {
+    pub background: Option<BackgroundEvent>,
+    pub video: Option<VideoEvent>,
+    pub breaks: Vec<BreakPeriod>,
+    pub storyboard: Storyboard,
+}
+
+impl Events {
+    pub fn break_at(&self, time: i32) -> Option<&BreakPeriod> {
+        self.breaks
+            .iter()
+            .find(|x| x.has_effect() && time >= x.start && time <= x.end)
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct BackgroundEvent {
+    pub filename: String,
+    pub offset: Vector2,
+}
+
+#[derive(Default, Debug)]
+pub struct VideoEvent {
+    pub start_time: i32,
+    pub filename: String,
+    pub offset: Vector2,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct BreakPeriod {
+    pub start: i32,
+    pub end: i32,
+}
+
+impl BreakPeriod {
+    pub fn duration(&self) -> i32 {
+        self.end - self.start
+    }
+
+    pub fn has_effect(&self) -> bool {
+        self.duration() >= MIN_BREAK_DURATION
+    }
+}
+
+#[derive(Default)]
+pub struct Storyboard {
+    // $name=value pairs from [Variables], substituted into event lines before they get parsed
+    pub variables: Vec<(String, String)>,
+    pub objects: Vec<StoryboardObject>,
+    pub samples: Vec<StoryboardSample>,
+
+    // which compound command indented commands belong to while parsing
+    open_compound: OpenCompound,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum OpenCompound {
+    None,
+    Loop,
+    Trigger,
+}
+impl Default for OpenCompound {
+    fn default() -> Self {
+        OpenCompound::None
+    }
+}
+
+impl Storyboard {
+    pub fn earliest_event_time(&self) -> Option<i32> {
+        let objects = self.objects.iter().filter_map(|x| x.start_time());
+        let samples = self.samples.iter().map(|x| x.time);
+        objects.chain(samples).min()
+    }
+
+    pub fn latest_event_time(&self) -> Option<i32> {
+        let objects = self.objects.iter().filter_map(|x| x.end_time());
+        let samples = self.samples.iter().map(|x| x.time);
+        objects.chain(samples).max()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StoryboardLayer {
+    Background = 0,
+    Fail,
+    Pass,
+    Foreground,
+    Overlay,
+}
+impl BeatmapParse for StoryboardLayer {
+    fn parse(source: &str, line_num: u32) -> Result<Self, BeatmapParseErr> {
+        match source.trim() {
+            "0" | "Background" => Ok(StoryboardLayer::Background),
+            "1" | "Fail" => Ok(StoryboardLayer::Fail),
+            "2" | "Pass" => Ok(StoryboardLayer::Pass),
+            "3" | "Foreground" => Ok(StoryboardLayer::Foreground),
+            "4" | "Overlay" => Ok(StoryboardLayer::Overlay),
+            _ => Err(BeatmapParseErr::InvalidEnum(line_num)),
+        }
+    }
+}
+impl StoryboardLayer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StoryboardLayer::Background => "Background",
+            StoryboardLayer::Fail => "Fail",
+            StoryboardLayer::Pass => "Pass",
+            StoryboardLayer::Foreground => "Foreground",
+            StoryboardLayer::Overlay => "Overlay",
+        }
+    }
+}
+impl Default for StoryboardLayer {
+    fn default() -> Self {
+        StoryboardLayer::Background
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StoryboardOrigin {
+    TopLeft = 0,
+    Centre,
+    CentreLeft,
+    TopRight,
+    BottomCentre,
+    TopCentre,
+    Custom, // treated as TopLeft by osu
+    CentreRight,
+    BottomLeft,
+    BottomRight,
+}
+impl BeatmapParse for StoryboardOrigin {
+    fn parse(source: &str, line_num: u32) -> Result<Self, BeatmapParseErr> {
+        match source.trim() {
+            "0" | "TopLeft" => Ok(StoryboardOrigin::TopLeft),
+            "1" | "Centre" => Ok(StoryboardOrigin::Centre),
+            "2" | "CentreLeft" => Ok(StoryboardOrigin::CentreLeft),
+            "3" | "TopRight" => Ok(StoryboardOrigin::TopRight),
+            "4" | "BottomCentre" => Ok(StoryboardOrigin::BottomCentre),
+            "5" | "TopCentre" => Ok(StoryboardOrigin::TopCentre),
+            "6" | "Custom" => Ok(StoryboardOrigin::Custom),
+            "7" | "CentreRight" => Ok(StoryboardOrigin::CentreRight),
+            "8" | "BottomLeft" => Ok(StoryboardOrigin::BottomLeft),
+            "9" | "BottomRight" => Ok(StoryboardOrigin::BottomRight),
+            _ => Err(BeatmapParseErr::InvalidEnum(line_num)),
+        }
+    }
+}
+impl StoryboardOrigin {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StoryboardOrigin::TopLeft => "TopLeft",
+            StoryboardOrigin::Centre => "Centre",
+            StoryboardOrigin::CentreLeft => "CentreLeft",
+            StoryboardOrigin::TopRight => "TopRight",
+            StoryboardOrigin::BottomCentre => "BottomCentre",
+            StoryboardOrigin::TopCentre => "TopCentre",
+            StoryboardOrigin::Custom => "Custom",
+            StoryboardOrigin::CentreRight => "CentreRight",
+            StoryboardOrigin::BottomLeft => "BottomLeft",
+            StoryboardOrigin::BottomRight => "BottomRight",
+        }
+    }
+}
+impl Default for StoryboardOrigin {
+    fn default() -> Self {
+        StoryboardOrigin::TopLeft
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnimationLoopType {
+    LoopForever = 0,
+    LoopOnce,
+}
+impl BeatmapParse for AnimationLoopType {
+    fn parse(source: &str, _line_num: u32) -> Result<Self, BeatmapParseErr> {
+        // anything unknown just loops forever in stable
+        match source.trim() {
+            "1" | "LoopOnce" => Ok(AnimationLoopType::LoopOnce),
+            _ => Ok(AnimationLoopType::LoopForever),
+        }
+    }
+}
+impl Default for AnimationLoopType {
+    fn default() -> Self {
+        AnimationLoopType::LoopForever
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct AnimationInfo {
+    pub frame_count: i32,
+    pub frame_delay: f64,
+    pub loop_type: AnimationLoopType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StoryboardParameter {
+    FlipHorizontal,
+    FlipVertical,
+    AdditiveBlending,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StoryboardCommandKind {
+    Fade(f32, f32),
+    Move(Vector2, Vector2),
+    MoveX(f32, f32),
+    MoveY(f32, f32),
+    Scale(f32, f32),
+    VectorScale(Vector2, Vector2),
+    Rotate(f32, f32),
+    Colour(u32, u32), // BGR, same as everything else that gets drawn
+    Parameter(StoryboardParameter),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoryboardCommand {
+    pub easing: i32, // there's 35 of these, see https://osu.ppy.sh/wiki/en/Storyboard/Scripting/Commands
+    pub start: i32,
+    pub end: i32,
+    pub kind: StoryboardCommandKind,
+}
+
+#[derive(Default, Debug)]
+pub struct StoryboardLoop {
+    pub start_time: i32,
+    pub loop_count: i32,
+    // command times are relative to the start of each iteration
+    pub commands: Vec<StoryboardCommand>,
+}
+
+impl StoryboardLoop {
+    pub fn iteration_length(&self) -> i32 {
+        self.commands.iter().map(|x| x.end).max().unwrap_or(0)
+    }
+
+    pub fn end_time(&self) -> i32 {
+        self.start_time + self.iteration_length() * self.loop_count.max(1)
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct StoryboardTrigger {
+    pub trigger_name: String,
+    pub start_time: i32,
+    pub end_time: i32,
+    pub group_number: i32,
+    // command times are relative to when the trigger fires
+    pub commands: Vec<StoryboardCommand>,
+}
+
+#[derive(Default, Debug)]
+pub struct StoryboardObject {
+    pub layer: StoryboardLayer,
+    pub origin: StoryboardOrigin,
+    pub filename: String,
+    pub pos: Vector2,
+    pub animation: Option<AnimationInfo>,
+    pub commands: Vec<StoryboardCommand>,
+    pub loops: Vec<StoryboardLoop>,
+    pub triggers: Vec<StoryboardTrigger>,
+}
+
+impl StoryboardObject {
+    pub fn start_time(&self) -> Option<i32> {
+        let commands = self.commands.iter().map(|x| x.start);
+        let loops = self
+            .loops
+            .iter()
+            .map(|x| x.start_time + x.commands.iter().map(|x| x.start).min().unwrap_or(0));
+        commands.chain(loops).min()
+    }
+
+    pub fn end_time(&self) -> Option<i32> {
+        let commands = self.commands.iter().map(|x| x.end);
+        let loops = self.loops.iter().map(|x| x.end_time());
+        commands.chain(loops).max()
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct StoryboardSample {
+    pub time: i32,
+    pub layer: StoryboardLayer,
+    pub filename: String,
+    pub volume: i32,
+}
+
+impl Beatmap {
+    pub(super) fn handle_variables(&mut self, line: &str) {
+        if let Some((key, val)) = line.trim().split_once('=') {
+            if key.starts_with('$') {
+                self.events
+                    .storyboard
+                    .variables
+                    .push((key.to_string(), val.to_string()));
+            }
+        }
+    }
+
+    // like timing points, osu just skips over broken storyboard lines, so errors from here are only warnings
+    pub(super) fn handle_events(
+        &mut self,
+        line: &str,
+        line_num: u32,
+    ) -> Result<(), BeatmapParseErr> {
+        let line = self.substitute_variables(line);
+        self.handle_events_inner(&line, line_num)
+    }
+
+    fn substitute_variables(&self, line: &str) -> String {
+        let mut out = line.trim_end().to_string();
+        if !out.contains('$') {
+            return out;
+        }
+        // longest names first so $abc doesn't get clobbered by $ab
+        let mut vars: Vec<&(String, String)> = self.events.storyboard.variables.iter().collect();
+        vars.sort_by_key(|x| std::cmp::Reverse(x.0.len()));
+        for (key, val) in vars {
+            out = out.replace(key.as_str(), val);
+        }
+        out
+    }
+
+    fn event_time(&self, source: &str, line_num: u32) -> Result<i32, BeatmapParseErr> {
+        // some old storyboards have decimal times, which get truncated
+        let time = f64::parse(source, line_num)? as i32;
+        Ok(if self.format_version < 5 {
+            time + 24
+        } else {
+            time
+        })
+    }
+
+    fn handle_events_inner(&mut self, line: &str, line_num: u32) -> Result<(), BeatmapParseErr> {
+        let depth = line.chars().take_while(|&x| x == ' ' || x == '_').count();
+        let split: Vec<&str> = line[depth..].split(',').collect();
+
+        if depth > 0 {
+            return self.handle_storyboard_command(&split, depth, line_num);
+        }
+        self.events.storyboard.open_compound = OpenCompound::None;
+
+        match split[0].trim() {
+            "0" | "Background" => {
+                if split.len() < 3 {
+                    return Err(BeatmapParseErr::InvalidEvent(line_num));
+                }
+                let offset = if split.len() >= 5 {
+                    Vector2::new(
+                        f32::parse(split[3], line_num)?,
+                        f32::parse(split[4], line_num)?,
+                    )
+                } else {
+                    Vector2::default()
+                };
+                self.events.background = Some(BackgroundEvent {
+                    filename: Self::clean_filename(split[2]),
+                    offset,
+                });
+            }
+            "1" | "Video" => {
+                if split.len() < 3 {
+                    return Err(BeatmapParseErr::InvalidEvent(line_num));
+                }
+                let offset = if split.len() >= 5 {
+                    Vector2::new(
+                        f32::parse(split[3], line_num)?,
+                        f32::parse(split[4], line_num)?,
+                    )
+                } else {
+                    Vector2::default()
+                };
+                self.events.video = Some(VideoEvent {
+                    start_time: self.event_time(split[1], line_num)?,
+                    filename: Self::clean_filename(split[2]),
+                    offset,
+                });
+            }
+            "2" | "Break" => {
+                if split.len() < 3 {
+                    return Err(BeatmapParseErr::InvalidEvent(line_num));
+                }
+                let start = self.event_time(split[1], line_num)?;
+                let end = self.event_time(split[2], line_num)?.max(start);
+                self.events.breaks.push(BreakPeriod { start, end });
+            }
+            "4" | "Sprite" => {
+                if split.len() < 6 {
+                    return Err(BeatmapParseErr::InvalidEvent(line_num));
+                }
+                self.events.storyboard.objects.push(StoryboardObject {
+                    layer: StoryboardLayer::parse(split[1], line_num)?,
+                    origin: StoryboardOrigin::parse(split[2], line_num)?,
+                    filename: Self::clean_filename(split[3]),
+                    pos: Vector2::new(
+                        f32::parse(split[4], line_num)?,
+                        f32::parse(split[5], line_num)?,
+                    ),
+                    ..Default::default()
+                });
+            }
+            "6" | "Animation" => {
+                if split.len() < 8 {
+                    return Err(BeatmapParseErr::InvalidEvent(line_num));
+                }
+                let loop_type = if split.len() >= 9 {
+                    AnimationLoopType::parse(split[8], line_num)?
+                } else {
+                    AnimationLoopType::LoopForever
+                };
+                self.events.storyboard.objects.push(StoryboardObject {
+                    layer: StoryboardLayer::parse(split[1], line_num)?,
+                    origin: StoryboardOrigin::parse(split[2], line_num)?,
+                    filename: Self::clean_filename(split[3]),
+                    pos: Vector2::new(
+                        f32::parse(split[4], line_num)?,
+                        f32::parse(split[5], line_num)?,
+                    ),
+                    animation: Some(AnimationInfo {
+                        frame_count: i32::parse(split[6], line_num)?,
+                        frame_delay: f64::parse(split[7], line_num)?,
+                        loop_type,
+                    }),
+                    ..Default::default()
+                });
+            }
+            "5" | "Sample" => {
+                if split.len() < 4 {
+                    return Err(BeatmapParseErr::InvalidEvent(line_num));
+                }
+                let volume = if split.len() >= 5 {
+                    i32::parse(split[4], line_num)?
+                } else {
+                    100
+                };
+                self.events.storyboard.samples.push(StoryboardSample {
+                    time: self.event_time(split[1], line_num)?,
+                    layer: StoryboardLayer::parse(split[2], line_num)?,
+                    filename: Self::clean_filename(split[3]),
+                    volume,
+                });
+            }
+            // background colour transformations were removed from the game ages ago
+            "3" | "Colour" => {}
+            _ => return Err(BeatmapParseErr::InvalidEvent(line_num)),
+        }
+
+        Ok(())
+    }
+
+    fn handle_storyboard_command(
+        &mut self,
+        split: &[&str],
+        depth: usize,
+        line_num: u32,
+    ) -> Result<(), BeatmapParseErr> {
+        let format_version = self.format_version;
+        let storyboard = &mut self.events.storyboard;
+        let object = match storyboard.objects.last_mut() {
+            Some(x) => x,
+            None => return Err(BeatmapParseErr::InvalidEvent(line_num)),
+        };
+
+        // loops and triggers are only allowed directly on an object
+        match split[0] {
+            "L" if depth == 1 => {
+                if split.len() < 3 {
+                    return Err(BeatmapParseErr::InvalidEvent(line_num));
+                }
+                let mut start_time = f64::parse(split[1], line_num)? as i32;
+                if format_version < 5 {
+                    start_time += 24;
+                }
+                object.loops.push(StoryboardLoop {
+                    start_time,
+                    loop_count: i32::parse(split[2], line_num)?,
+                    commands: Vec::new(),
+                });
+                storyboard.open_compound = OpenCompound::Loop;
+                return Ok(());
+            }
+            "T" if depth == 1 => {
+                if split.len() < 4 {
+                    return Err(BeatmapParseErr::InvalidEvent(line_num));
+                }
+                let mut start_time = f64::parse(split[2], line_num)? as i32;
+                let mut end_time = f64::parse(split[3], line_num)? as i32;
+                if format_version < 5 {
+                    start_time += 24;
+                    end_time += 24;
+                }
+                let group_number = if split.len() >= 5 {
+                    i32::parse(split[4], line_num)?
+                } else {
+                    0
+                };
+                object.triggers.push(StoryboardTrigger {
+                    trigger_name: split[1].to_string(),
+                    start_time,
+                    end_time,
+                    group_number,
+                    commands: Vec::new(),
+                });
+                storyboard.open_compound = OpenCompound::Trigger;
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let commands = Self::parse_storyboard_command(split, format_version, depth, line_num)?;
+        if depth == 1 {
+            storyboard.open_compound = OpenCompound::None;
+            object.commands.extend(commands);
+        } else {
+            let target = match storyboard.open_compound {
+                OpenCompound::Loop => &mut object.loops.last_mut().unwrap().commands,
+                OpenCompound::Trigger => &mut object.triggers.last_mut().unwrap().commands,
+                OpenCompound::None => return Err(BeatmapParseErr::InvalidEvent(line_num)),
+            };
+            target.extend(commands);
+        }
+
+        Ok(())
+    }
+
+    fn parse_storyboard_command(
+        split: &[&str],
+        format_version: i32,
+        depth: usize,
+        line_num: u32,
+    ) -> Result<Vec<StoryboardCommand>, BeatmapParseErr> {
+        if split.len() < 5 {
+            return Err(BeatmapParseErr::InvalidEvent(line_num));
+        }
+
+        let easing = i32::parse(split[1], line_num)?;
+        let mut start = f64::parse(split[2], line_num)? as i32;
+        let mut end = if split[3].trim().is_empty() {
+            start
+        } else {
+            f64::parse(split[3], line_num)? as i32
+        };
+        // times inside of loops and triggers are relative, so they don't get offset
+        if format_version < 5 && depth == 1 {
+            start += 24;
+            end += 24;
+        }
+        let duration = end - start;
+
+        let params = &split[4..];
+        let param_count = match split[0] {
+            "F" | "MX" | "MY" | "S" | "R" => 1,
+            "M" | "V" => 2,
+            "C" => 3,
+            "P" => {
+                let param = match params[0].trim() {
+                    "H" => StoryboardParameter::FlipHorizontal,
+                    "V" => StoryboardParameter::FlipVertical,
+                    "A" => StoryboardParameter::AdditiveBlending,
+                    _ => return Err(BeatmapParseErr::InvalidEnum(line_num)),
+                };
+                return Ok(vec![StoryboardCommand {
+                    easing,
+                    start,
+                    end,
+                    kind: StoryboardCommandKind::Parameter(param),
+                }]);
+            }
+            _ => return Err(BeatmapParseErr::InvalidEvent(line_num)),
+        };
+
+        let mut values = Vec::with_capacity(params.len());
+        for x in params {
+            values.push(f32::parse(x, line_num)?);
+        }
+        if values.len() < param_count {
+            return Err(BeatmapParseErr::InvalidEvent(line_num));
+        }
+        let sets: Vec<&[f32]> = values.chunks_exact(param_count).collect();
+
+        // extra parameter sets chain together with the same duration
+        // a single set means the value doesn't change
+        let mut out = Vec::with_capacity(sets.len().max(2) - 1);
+        let pairs: Vec<(&[f32], &[f32])> = if sets.len() == 1 {
+            vec![(sets[0], sets[0])]
+        } else {
+            sets.windows(2).map(|x| (x[0], x[1])).collect()
+        };
+        for (i, (a, b)) in pairs.into_iter().enumerate() {
+            let kind = match split[0] {
+                "F" => StoryboardCommandKind::Fade(a[0], b[0]),
+                "MX" => StoryboardCommandKind::MoveX(a[0], b[0]),
+                "MY" => StoryboardCommandKind::MoveY(a[0], b[0]),
+                "S" => StoryboardCommandKind::Scale(a[0], b[0]),
+                "R" => StoryboardCommandKind::Rotate(a[0], b[0]),
+                "M" => {
+                    StoryboardCommandKind::Move(Vector2::new(a[0], a[1]), Vector2::new(b[0], b[1]))
+                }
+                "V" => StoryboardCommandKind::VectorScale(
+                    Vector2::new(a[0], a[1]),
+                    Vector2::new(b[0], b[1]),
+                ),
+                "C" => StoryboardCommandKind::Colour(
+                    Self::storyboard_colour(a),
+                    Self::storyboard_colour(b),
+                ),
+                _ => unreachable!(),
+            };
+            let offset = duration * i as i32;
+            out.push(StoryboardCommand {
+                easing,
+                start: start + offset,
+                end: end + offset,
+                kind,
+            });
+        }
+
+        Ok(out)
+    }
+
+    fn storyboard_colour(rgb: &[f32]) -> u32 {
+        let r = rgb[0].clamp(0.0, 255.0) as u32;
+        let g = rgb[1].clamp(0.0, 255.0) as u32;
+        let b = rgb[2].clamp(0.0, 255.0) as u32;
+        (b << 16) | (g << 8) | r
+    }
+
+    fn clean_filename(source: &str) -> String {
+        source.trim().trim_matches('"').replace('\\', "/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{beatmap::*, math::Vector2};
+
+    #[test]
+    fn test_events() {
+        let beatmap = Beatmap::fixture("test/storyboard_events.osu");
+        let events = &beatmap.events;
+
+        let bg = events.background.as_ref().unwrap();
+        assert_eq!(bg.filename, "bg.jpg");
+        assert_eq!(bg.offset, Vector2::new(0.0, 0.0));
+        let video = events.video.as_ref().unwrap();
+        assert_eq!(video.filename, "video.avi");
+        assert_eq!(video.start_time, -200);
+
+        assert_eq!(
+            events.breaks,
+            &[
+                BreakPeriod {
+                    start: 3000,
+                    end: 6000
+                },
+                BreakPeriod {
+                    start: 7000,
+                    end: 7200
+                }
+            ]
+        );
+        assert!(events.break_at(4000).is_some());
+        assert!(events.break_at(7100).is_none()); // too short to count
+
+        let storyboard = &events.storyboard;
+        assert_eq!(storyboard.objects.len(), 2);
+        let sprite = &storyboard.objects[1];
+        assert_eq!(sprite.layer, StoryboardLayer::Foreground);
+        assert_eq!(sprite.origin, StoryboardOrigin::Centre);
+        assert_eq!(sprite.filename, "sb/star.png");
+        assert!(sprite.animation.is_none());
+        // F with 3 values gets chained into 2 commands
+        assert_eq!(sprite.commands.len(), 4);
+        assert_eq!(
+            sprite.commands[0].kind,
+            StoryboardCommandKind::Fade(0.0, 1.0)
+        );
+        assert_eq!(
+            (sprite.commands[1].start, sprite.commands[1].end),
+            (1500, 2000)
+        );
+        assert_eq!(
+            sprite.commands[1].kind,
+            StoryboardCommandKind::Fade(1.0, 0.5)
+        );
+        assert_eq!(
+            sprite.commands[2].kind,
+            StoryboardCommandKind::Move(Vector2::new(320.0, 240.0), Vector2::new(320.0, 240.0))
+        );
+        assert_eq!(
+            sprite.commands[3].kind,
+            StoryboardCommandKind::Colour(0x0000FF, 0xFF0000)
+        );
+        assert_eq!(sprite.loops.len(), 1);
+        assert_eq!(sprite.loops[0].loop_count, 4);
+        assert_eq!(sprite.loops[0].commands.len(), 2);
+        assert_eq!(sprite.loops[0].end_time(), 2000 + 500 * 4);
+        assert_eq!(sprite.triggers.len(), 1);
+        assert_eq!(sprite.triggers[0].trigger_name, "HitSoundClap");
+        assert_eq!(sprite.triggers[0].commands.len(), 1);
+
+        let anim = &storyboard.objects[0];
+        let anim_info = anim.animation.as_ref().unwrap();
+        assert_eq!(anim_info.frame_count, 4);
+        assert_eq!(anim_info.loop_type, AnimationLoopType::LoopOnce);
+        assert_eq!(
+            anim.commands[0].kind,
+            StoryboardCommandKind::Parameter(StoryboardParameter::AdditiveBlending)
+        );
+
+        assert_eq!(storyboard.samples.len(), 1);
+        assert_eq!(storyboard.samples[0].volume, 70);
+        assert_eq!(storyboard.earliest_event_time(), Some(-500));
+    }
+}
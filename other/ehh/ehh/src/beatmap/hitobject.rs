@@ -2,17 +2,14 @@ use log::warn;
 
 use crate::math::{self, Line};
 
-use super::{
-    timing_point::{self, TimingPoint},
-    Difficulty,
-};
+use super::{Difficulty, HitSample, HitSoundType, SampleSet, TimingIndex};
 
 #[derive(PartialEq)]
 pub enum HitObjectType {
     Circle,
     Slider,
     Spinner,
-    //Hold, // no idea how this should be handled
+    Hold, // mania only
 }
 impl Default for HitObjectType {
     fn default() -> Self {
@@ -51,6 +48,8 @@ impl SliderTick {
 pub struct SliderInfo {
     pub spatial_length: f64,
     pub slides: i32, // yes, this can be negative...
+    pub curve_type: crate::curve::CurveType,
+    pub control_points: Vec<math::Vector2>, // includes the start position
     pub curve: crate::curve::Curve,
     pub ball_path: Vec<(i32, i32, Line)>,
 
@@ -59,6 +58,11 @@ pub struct SliderInfo {
     pub score_times: Vec<i32>,
     pub small_ticks: Vec<SliderTick>,
     pub end_ticks: Vec<SliderTick>,
+
+    // one per edge (head, each repeat, tail)
+    pub edge_sounds: Vec<HitSoundType>,
+    pub edge_sets: Vec<(SampleSet, SampleSet)>, // normal, addition
+    pub has_edge_hitsounds: bool, // whether the line had edgeSounds/edgeSets, so writing it back doesn't add them
 }
 
 #[derive(Default)]
@@ -75,6 +79,12 @@ pub struct HitObject {
     pub stack_count: i32,
     pub time_preempt: i32,
     pub flags: i32,
+    pub combo_index: i32, // includes colour skips, take it modulo the colour count
+    pub combo_number: i32, // the number drawn on the object
+    pub hitsound: HitSoundType,
+    pub hit_sample: HitSample,
+    pub has_hit_sample: bool, // same idea, older maps don't have one
+    pub line: u32,            // in the .osu, 0 if it didn't come from one
 
     pub slider_info: Option<Box<SliderInfo>>,
 }
@@ -107,6 +117,11 @@ impl HitObject {
         self.flags & 4 != 0
     }
 
+    // how many combo colours get skipped when this starts a new combo
+    pub fn combo_skip(&self) -> i32 {
+        (self.flags >> 4) & 7
+    }
+
     pub fn time_at_length(&self, length: f32) -> i32 {
         if let Some(slider_info) = &self.slider_info {
             self.start + ((length / slider_info.velocity as f32) * 1000.0) as i32
@@ -186,11 +201,11 @@ impl HitObject {
     pub fn recalculate_slider(
         &mut self,
         beatmap_version: i32,
-        timing_points: &[TimingPoint],
+        timing: &TimingIndex,
         difficulty: &Difficulty,
     ) {
         if let Some(slider_info) = &mut self.slider_info {
-            let beat_length = timing_point::beat_length_at(timing_points, self.start as f64, true);
+            let beat_length = timing.beat_length_at(self.start as f64, true);
             slider_info.velocity = if beat_length > 0.0 {
                 difficulty.slider_scoring_point_distance
                     * difficulty.slider_tick_rate
@@ -203,7 +218,7 @@ impl HitObject {
                 difficulty.slider_scoring_point_distance
             } else {
                 difficulty.slider_scoring_point_distance
-                    / timing_point::bpm_multiplier_at(timing_points, self.start as f64) as f64
+                    / timing.bpm_multiplier_at(self.start as f64) as f64
             }
             .minimum(slider_info.spatial_length);
 
@@ -358,12 +373,10 @@ impl HitObject {
 
 #[cfg(test)]
 mod tests {
-    use std::{fs::File, io::BufReader};
-
     use crate::Beatmap;
 
     fn test_slider_ticks_inner(path: &str, expected: &[i32]) {
-        let beatmap = Beatmap::parse("", &mut BufReader::new(File::open(path).unwrap())).unwrap();
+        let beatmap = Beatmap::fixture(path);
         let obj = &beatmap.hit_objects[0];
         let slider_info = obj.slider_info.as_ref().unwrap();
         println!("expected: {:?}", expected);
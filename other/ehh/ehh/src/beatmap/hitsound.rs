@@ -0,0 +1,303 @@
+use super::*;
+
+// sample points are looked up a bit after the object so that a point placed right on top of it still counts
+const SAMPLE_POINT_LENIENCY: f64 = 5.0;
+
+bitflags::bitflags! {
+    #[derive(Default)]
+    pub struct HitSoundType: i32 {
+        const NORMAL = 1;
+        const WHISTLE = 2;
+        const FINISH = 4;
+        const CLAP = 8;
+    }
+}
+
+impl SampleSet {
+    // the sample sets in hit objects are stored as numbers, with 0 meaning "inherit from the timing point"
+    pub fn from_i32(x: i32) -> SampleSet {
+        match x {
+            1 => SampleSet::Normal,
+            2 => SampleSet::Soft,
+            3 => SampleSet::Drum,
+            _ => SampleSet::None,
+        }
+    }
+
+    // prefix used by sample filenames
+    pub fn sample_prefix(&self) -> &'static str {
+        match self {
+            SampleSet::Soft => "soft",
+            SampleSet::Drum => "drum",
+            _ => "normal",
+        }
+    }
+}
+
+// normalSet:additionSet:index:volume:filename
+// every number being 0 means it gets inherited from the active timing point
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitSample {
+    pub normal_set: SampleSet,
+    pub addition_set: SampleSet,
+    pub index: i32,
+    pub volume: i32,
+    pub filename: String, // replaces every other sample if set
+}
+impl Default for HitSample {
+    fn default() -> Self {
+        HitSample {
+            normal_set: SampleSet::None,
+            addition_set: SampleSet::None,
+            index: 0,
+            volume: 0,
+            filename: String::new(),
+        }
+    }
+}
+
+impl BeatmapParse for HitSample {
+    fn parse(source: &str, line_num: u32) -> Result<Self, BeatmapParseErr> {
+        let mut out = HitSample::default();
+        let mut split = source.trim().split(':');
+        // old maps only have some of these, so missing ones are fine
+        if let Some(x) = split.next().filter(|x| !x.is_empty()) {
+            out.normal_set = SampleSet::from_i32(i32::parse(x, line_num)?);
+        }
+        if let Some(x) = split.next() {
+            out.addition_set = SampleSet::from_i32(i32::parse(x, line_num)?);
+        }
+        if let Some(x) = split.next() {
+            out.index = i32::parse(x, line_num)?;
+        }
+        if let Some(x) = split.next() {
+            out.volume = i32::parse(x, line_num)?;
+        }
+        if let Some(x) = split.next() {
+            out.filename = x.to_owned();
+        }
+        Ok(out)
+    }
+}
+
+impl std::fmt::Display for HitSample {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}:{}",
+            self.normal_set as i32,
+            self.addition_set as i32,
+            self.index,
+            self.volume,
+            self.filename
+        )
+    }
+}
+
+// one sample file to play, without the extension
+// custom samples come from the beatmap if it has them, and the skin otherwise
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleName {
+    pub name: String, // "soft-hitclap" for example
+    pub index: i32,   // 0 means skin only
+}
+
+impl SampleName {
+    // the filename the beatmap would have for this sample
+    pub fn beatmap_filename(&self) -> Option<String> {
+        match self.index {
+            0 => None,
+            1 => Some(self.name.clone()),
+            x => Some(format!("{}{}", self.name, x)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolvedSample {
+    Skinnable(SampleName),
+    File(String), // straight from the beatmap, never falls back to the skin
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedHitSound {
+    pub samples: Vec<ResolvedSample>,
+    pub volume: i32,
+}
+
+impl HitSample {
+    // fills in everything that gets inherited and figures out which files should play
+    pub fn resolve(
+        &self,
+        sounds: HitSoundType,
+        time: f64,
+        timing: &TimingIndex,
+        default_set: SampleSet,
+    ) -> ResolvedHitSound {
+        let point = timing.segment_at(time + SAMPLE_POINT_LENIENCY);
+
+        let point_set = match point.map(|x| x.sample_set) {
+            Some(SampleSet::Normal) => SampleSet::Normal,
+            Some(SampleSet::Soft) => SampleSet::Soft,
+            Some(SampleSet::Drum) => SampleSet::Drum,
+            _ => default_set,
+        };
+        let normal_set = match self.normal_set {
+            SampleSet::None => point_set,
+            x => x,
+        };
+        let addition_set = match self.addition_set {
+            SampleSet::None => normal_set,
+            x => x,
+        };
+        let index = if self.index != 0 {
+            self.index
+        } else {
+            point.map(|x| x.custom_sample_set).unwrap_or(0)
+        };
+        let volume = if self.volume != 0 {
+            self.volume
+        } else {
+            point.map(|x| x.volume).unwrap_or(100)
+        };
+
+        if !self.filename.is_empty() {
+            return ResolvedHitSound {
+                samples: vec![ResolvedSample::File(self.filename.clone())],
+                volume,
+            };
+        }
+
+        // the normal sound always plays in standard, even if only additions are set
+        let mut samples = vec![ResolvedSample::Skinnable(SampleName {
+            name: format!("{}-hitnormal", normal_set.sample_prefix()),
+            index,
+        })];
+        for (flag, name) in [
+            (HitSoundType::WHISTLE, "hitwhistle"),
+            (HitSoundType::FINISH, "hitfinish"),
+            (HitSoundType::CLAP, "hitclap"),
+        ] {
+            if sounds.contains(flag) {
+                samples.push(ResolvedSample::Skinnable(SampleName {
+                    name: format!("{}-{}", addition_set.sample_prefix(), name),
+                    index,
+                }));
+            }
+        }
+
+        ResolvedHitSound { samples, volume }
+    }
+}
+
+impl HitObject {
+    // slider edges have their own sounds and sample sets, everything else just uses the object's
+    pub fn edge_hitsound(
+        &self,
+        edge: usize,
+        timing: &TimingIndex,
+        default_set: SampleSet,
+    ) -> ResolvedHitSound {
+        let mut time = self.start;
+        let mut sounds = self.hitsound;
+        let mut sample = self.hit_sample.clone();
+        if let Some(slider_info) = &self.slider_info {
+            if edge > 0 {
+                if let Some(tick) = slider_info.end_ticks.get(edge - 1) {
+                    time = tick.time;
+                }
+            }
+            if let Some(x) = slider_info.edge_sounds.get(edge) {
+                sounds = *x;
+            }
+            if let Some(x) = slider_info.edge_sets.get(edge) {
+                sample.normal_set = x.0;
+                sample.addition_set = x.1;
+            }
+        } else if self.object_type == HitObjectType::Spinner {
+            // spinners make their sound once they're finished
+            time = self.end;
+        }
+
+        sample.resolve(sounds, time as f64, timing, default_set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::beatmap::*;
+
+    fn skinnable(name: &str, index: i32) -> ResolvedSample {
+        ResolvedSample::Skinnable(SampleName {
+            name: name.to_string(),
+            index,
+        })
+    }
+
+    #[test]
+    fn test_hitsounds() {
+        let beatmap = Beatmap::fixture("test/hitsounds.osu");
+        let resolve = |obj: usize, edge: usize| {
+            beatmap.hit_objects[obj].edge_hitsound(edge, &beatmap.timing, beatmap.sample_set)
+        };
+
+        // everything inherited from the timing point
+        let sound = resolve(0, 0);
+        assert_eq!(sound.volume, 60);
+        assert_eq!(sound.samples, &[skinnable("soft-hitnormal", 0)]);
+
+        // whistle + clap with a drum addition, custom index and volume
+        let sound = resolve(1, 0);
+        assert_eq!(sound.volume, 30);
+        assert_eq!(
+            sound.samples,
+            &[
+                skinnable("soft-hitnormal", 2),
+                skinnable("drum-hitwhistle", 2),
+                skinnable("drum-hitclap", 2),
+            ]
+        );
+        assert_eq!(
+            SampleName {
+                name: "drum-hitclap".to_string(),
+                index: 2
+            }
+            .beatmap_filename()
+            .unwrap(),
+            "drum-hitclap2"
+        );
+
+        // filename overrides everything
+        let sound = resolve(2, 0);
+        assert_eq!(
+            sound.samples,
+            &[ResolvedSample::File("kick.wav".to_string())]
+        );
+
+        // slider edges, the tail lands after the second timing point
+        let slider = &beatmap.hit_objects[3];
+        let slider_info = slider.slider_info.as_ref().unwrap();
+        assert_eq!(
+            slider_info.edge_sounds,
+            &[HitSoundType::FINISH, HitSoundType::NORMAL]
+        );
+        assert_eq!(
+            resolve(3, 0).samples,
+            &[
+                skinnable("normal-hitnormal", 0),
+                skinnable("soft-hitfinish", 0)
+            ]
+        );
+        let tail = resolve(3, 1);
+        assert_eq!(tail.volume, 90);
+        assert_eq!(tail.samples, &[skinnable("drum-hitnormal", 1)]);
+
+        // no edge info means every edge gets the object's sounds
+        let slider_info = beatmap.hit_objects[4].slider_info.as_ref().unwrap();
+        assert_eq!(slider_info.edge_sounds, &[HitSoundType::WHISTLE; 3]);
+        assert_eq!(
+            slider_info.edge_sets,
+            &[(SampleSet::None, SampleSet::None); 3]
+        );
+    }
+}
@@ -1,18 +1,41 @@
+mod beatmap_set;
+mod check;
+mod diagnostic;
 mod difficulty;
+mod events;
 mod hitobject;
+mod hitsound;
+mod modes;
+mod mods;
 mod parser;
+mod timing_index;
 mod timing_point;
+mod writer;
 
+use std::sync::Arc;
+
+pub use beatmap_set::*;
+pub use check::*;
+pub use diagnostic::*;
 pub use difficulty::*;
+pub use events::*;
 pub use hitobject::*;
+pub use hitsound::*;
+pub use modes::*;
+pub use mods::*;
 pub use parser::*;
+pub use timing_index::*;
 pub use timing_point::*;
 
 // TODO: default on its own doesn't get everything right
 #[derive(Default)]
 pub struct Beatmap {
-    // required to get the actual paths of other files used by the beatmap
-    pub base_path: String,
+    // required to get the other files used by the beatmap
+    pub set: Arc<BeatmapSet>,
+
+    // md5 of the .osu, which is what replays and scores use to find the map
+    // only filled in when the file came out of a set
+    pub hash: String,
 
     // top of the header
     pub format_version: i32,
@@ -39,6 +62,12 @@ pub struct Beatmap {
     pub timeline_zoom: f32,  // editor only
     pub widescreen_storyboard: bool,
 
+    // Editor
+    pub bookmarks: Vec<i32>,
+    pub distance_spacing: f64,
+    pub beat_divisor: i32,
+    pub grid_size: i32,
+
     // Metadata
     pub artist: String, // "ArtistUnicode"
     pub beatmap_id: i32,
@@ -54,21 +83,73 @@ pub struct Beatmap {
     // Difficulty
     pub difficulty: Difficulty,
 
-    // TODO: Events
+    // Events
+    pub events: Events,
 
     // TimingPoints
-    pub timing_points: Vec<TimingPoint>,
+    pub timing_points: Vec<TimingPoint>, // file order, call rebuild_timing_index after changing these
+    pub timing: TimingIndex,             // use this for anything based on time
 
-    // TODO: Colours (british spelling is important)
+    // Colours (british spelling is important)
+    pub combo_colours: Vec<u32>, // BGR, empty if the skin's colours should be used
+    pub slider_track_override: Option<u32>,
+    pub slider_border: Option<u32>,
 
     // HitObjects
     pub hit_objects: Vec<HitObject>,
     pub circle_count: usize,
     pub slider_count: usize,
     pub spinner_count: usize,
+    pub hold_count: usize,
+
+    // everything the parser skipped over, see ParseMode
+    pub diagnostics: Vec<ParseDiagnostic>,
 }
 
-#[derive(Debug)]
+impl Beatmap {
+    // how much time is given before the first object when nothing else asks for more
+    pub const MIN_LEAD_IN: i32 = 2000;
+
+    // the time that playback should start from
+    // usually before 0, but audio lead-in and storyboards can push it back even further
+    pub fn gameplay_start_time(&self) -> i32 {
+        let mut time = 0;
+        if let Some(first) = self.hit_objects.first() {
+            time = time.min(first.start - Self::MIN_LEAD_IN.max(self.difficulty.preempt));
+        }
+        if let Some(earliest) = self.events.storyboard.earliest_event_time() {
+            time = time.min(earliest);
+        }
+        if self.audio_lead_in > 0 {
+            time = time.min(-self.audio_lead_in);
+        }
+        time
+    }
+
+    // when the last object is done, which isn't always the last one to start
+    pub fn last_object_end(&self) -> Option<i32> {
+        self.hit_objects.iter().map(|x| x.end).max()
+    }
+
+    // timing only looks at the points it was built from, so it goes stale whenever they change
+    pub fn rebuild_timing_index(&mut self) {
+        self.timing = TimingIndex::new(&self.timing_points);
+    }
+}
+
+// one of the maps in test/, along with the folder it's in
+#[cfg(test)]
+impl Beatmap {
+    pub(crate) fn fixture(path: &str) -> Beatmap {
+        Self::fixture_with_mods(path, Mods::empty())
+    }
+
+    pub(crate) fn fixture_with_mods(path: &str, mods: Mods) -> Beatmap {
+        BeatmapSet::open_difficulty(path, None, mods).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum Countdown {
     None = 0,
     Normal,
@@ -81,7 +162,7 @@ impl Default for Countdown {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Gamemode {
     Osu = 0,
     Taiko,
@@ -94,7 +175,7 @@ impl Default for Gamemode {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum OverlayPosition {
     NoChange = 0,
     Below,
@@ -111,6 +192,15 @@ impl BeatmapParse for OverlayPosition {
         }
     }
 }
+impl OverlayPosition {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OverlayPosition::NoChange => "NoChange",
+            OverlayPosition::Below => "Below",
+            OverlayPosition::Above => "Above",
+        }
+    }
+}
 impl Default for OverlayPosition {
     fn default() -> Self {
         OverlayPosition::NoChange
@@ -0,0 +1,212 @@
+use super::*;
+
+// taiko and catch reuse the standard object types, they just mean different things
+// see https://osu.ppy.sh/wiki/en/Client/File_formats/Osu_%28file_format%29#hit-objects
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaikoObjectKind {
+    Don,
+    Kat,
+    DrumRoll, // sliders
+    Swell,    // spinners
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CatchObjectKind {
+    Fruit,
+    JuiceStream,  // sliders, with droplets on the ticks
+    BananaShower, // spinners
+}
+
+impl HitObject {
+    pub fn taiko_kind(&self) -> TaikoObjectKind {
+        match self.object_type {
+            HitObjectType::Slider => TaikoObjectKind::DrumRoll,
+            HitObjectType::Spinner => TaikoObjectKind::Swell,
+            _ => {
+                if self
+                    .hitsound
+                    .intersects(HitSoundType::WHISTLE | HitSoundType::CLAP)
+                {
+                    TaikoObjectKind::Kat
+                } else {
+                    TaikoObjectKind::Don
+                }
+            }
+        }
+    }
+
+    // big notes and drum rolls
+    pub fn taiko_is_strong(&self) -> bool {
+        self.hitsound.contains(HitSoundType::FINISH)
+    }
+
+    pub fn catch_kind(&self) -> CatchObjectKind {
+        match self.object_type {
+            HitObjectType::Slider => CatchObjectKind::JuiceStream,
+            HitObjectType::Spinner => CatchObjectKind::BananaShower,
+            _ => CatchObjectKind::Fruit,
+        }
+    }
+
+    // columns split the playfield evenly, left to right
+    pub fn mania_column(&self, key_count: i32) -> i32 {
+        let column = (self.unstacked_start_pos.x * key_count as f32 / 512.0).floor() as i32;
+        column.clamp(0, (key_count - 1).max(0))
+    }
+}
+
+impl Beatmap {
+    // mania stores the key count in CircleSize
+    pub fn mania_key_count(&self) -> i32 {
+        (self.difficulty.circle_size.round() as i32).clamp(1, 18)
+    }
+
+    pub fn max_combo(&self) -> usize {
+        match self.mode {
+            Gamemode::Osu => self
+                .hit_objects
+                .iter()
+                .map(|x| {
+                    1 + x
+                        .slider_info
+                        .as_ref()
+                        .map(|x| x.score_times.len())
+                        .unwrap_or(0)
+                })
+                .sum(),
+            // drum rolls and swells don't give any combo
+            Gamemode::Taiko => self
+                .hit_objects
+                .iter()
+                .filter(|x| matches!(x.taiko_kind(), TaikoObjectKind::Don | TaikoObjectKind::Kat))
+                .count(),
+            // same as standard, except bananas don't count
+            Gamemode::CatchTheBeat => self
+                .hit_objects
+                .iter()
+                .map(|x| match x.catch_kind() {
+                    CatchObjectKind::Fruit => 1,
+                    CatchObjectKind::JuiceStream => {
+                        1 + x.slider_info.as_ref().unwrap().score_times.len()
+                    }
+                    CatchObjectKind::BananaShower => 0,
+                })
+                .sum(),
+            // holds get judged at both ends, same as lazer
+            Gamemode::Mania => self
+                .hit_objects
+                .iter()
+                .map(|x| match x.object_type {
+                    HitObjectType::Hold => 2,
+                    _ => 1,
+                })
+                .sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::beatmap::*;
+    use crate::math::Vector2;
+
+    #[test]
+    fn test_mania() {
+        let beatmap = Beatmap::fixture("test/mania.osu");
+        assert_eq!(beatmap.mode, Gamemode::Mania);
+        assert_eq!(beatmap.mania_key_count(), 4);
+        assert_eq!(beatmap.circle_count, 4);
+        assert_eq!(beatmap.hold_count, 2);
+
+        let columns: Vec<i32> = beatmap
+            .hit_objects
+            .iter()
+            .map(|x| x.mania_column(beatmap.mania_key_count()))
+            .collect();
+        assert_eq!(columns, &[0, 1, 2, 3, 0, 3]);
+
+        let holds: Vec<(i32, i32)> = beatmap
+            .hit_objects
+            .iter()
+            .filter(|x| x.object_type == HitObjectType::Hold)
+            .map(|x| (x.start, x.end))
+            .collect();
+        assert_eq!(holds, &[(1000, 1500), (1000, 2000)]);
+        assert_eq!(beatmap.hit_objects[5].hit_sample.volume, 50);
+
+        // nothing gets stacked even though the x positions line up
+        assert!(beatmap.hit_objects.iter().all(|x| x.stack_count == 0));
+        assert_eq!(beatmap.max_combo(), 8);
+    }
+
+    #[test]
+    fn test_taiko() {
+        let beatmap = Beatmap::fixture("test/taiko.osu");
+        assert_eq!(beatmap.mode, Gamemode::Taiko);
+
+        let kinds: Vec<(TaikoObjectKind, bool)> = beatmap
+            .hit_objects
+            .iter()
+            .map(|x| (x.taiko_kind(), x.taiko_is_strong()))
+            .collect();
+        assert_eq!(
+            kinds,
+            &[
+                (TaikoObjectKind::Don, false),
+                (TaikoObjectKind::Kat, false),
+                (TaikoObjectKind::Kat, true),
+                (TaikoObjectKind::Don, true),
+                (TaikoObjectKind::DrumRoll, false),
+                (TaikoObjectKind::Swell, false),
+            ]
+        );
+        assert_eq!(beatmap.max_combo(), 4);
+        assert_eq!(beatmap.hit_objects[5].end, 5000);
+    }
+
+    #[test]
+    fn test_catch() {
+        let beatmap = Beatmap::fixture("test/catch.osu");
+        assert_eq!(beatmap.mode, Gamemode::CatchTheBeat);
+
+        let kinds: Vec<CatchObjectKind> =
+            beatmap.hit_objects.iter().map(|x| x.catch_kind()).collect();
+        assert_eq!(
+            kinds,
+            &[
+                CatchObjectKind::Fruit,
+                CatchObjectKind::Fruit,
+                CatchObjectKind::JuiceStream,
+                CatchObjectKind::BananaShower,
+                CatchObjectKind::Fruit,
+            ]
+        );
+        assert_eq!(
+            (
+                beatmap.circle_count,
+                beatmap.slider_count,
+                beatmap.spinner_count
+            ),
+            (3, 1, 1)
+        );
+
+        // 280px at 140px a beat, there and back, with a droplet on every beat
+        // the tail gets judged 36ms early like it does in standard
+        let stream = &beatmap.hit_objects[2];
+        assert_eq!(stream.end, 3500);
+        assert_eq!(
+            stream.slider_info.as_ref().unwrap().score_times,
+            &[2000, 2500, 3000, 3464]
+        );
+        assert_eq!(stream.end_pos, Vector2::new(100.0, 192.0));
+        assert_eq!(beatmap.hit_objects[3].end, 5000);
+
+        // fruits keep their x position, nothing gets stacked
+        assert_eq!(beatmap.hit_objects[1].start_pos, Vector2::new(448.0, 192.0));
+        assert!(beatmap.hit_objects.iter().all(|x| x.stack_count == 0));
+
+        // the banana shower doesn't give any combo
+        assert_eq!(beatmap.max_combo(), 8);
+    }
+}
@@ -0,0 +1,159 @@
+// same bits that stable uses in replays and the api
+bitflags::bitflags! {
+    #[derive(Default)]
+    pub struct Mods: u32 {
+        const EASY = 1 << 1;
+        const HIDDEN = 1 << 3;
+        const HARD_ROCK = 1 << 4;
+        const DOUBLE_TIME = 1 << 6;
+        const HALF_TIME = 1 << 8;
+    }
+}
+
+const PLAYFIELD_HEIGHT: f32 = 384.0;
+
+const ACRONYMS: [(Mods, &str); 5] = [
+    (Mods::EASY, "EZ"),
+    (Mods::HIDDEN, "HD"),
+    (Mods::HARD_ROCK, "HR"),
+    (Mods::DOUBLE_TIME, "DT"),
+    (Mods::HALF_TIME, "HT"),
+];
+
+impl Mods {
+    // how fast the song plays
+    pub fn clock_rate(&self) -> f64 {
+        if self.contains(Mods::DOUBLE_TIME) {
+            1.5
+        } else if self.contains(Mods::HALF_TIME) {
+            0.75
+        } else {
+            1.0
+        }
+    }
+
+    // scales ar/od/hp, cs gets a smaller boost from hr
+    pub fn adjust_stat(&self, value: f32, hard_rock_multiplier: f32) -> f32 {
+        if self.contains(Mods::HARD_ROCK) {
+            (value * hard_rock_multiplier).min(10.0)
+        } else if self.contains(Mods::EASY) {
+            value * 0.5
+        } else {
+            value
+        }
+    }
+
+    // hr mirrors the playfield vertically, doing it twice undoes it
+    pub fn flip_y(&self, y: f32) -> f32 {
+        if self.contains(Mods::HARD_ROCK) {
+            PLAYFIELD_HEIGHT - y
+        } else {
+            y
+        }
+    }
+
+    // "HDDT", "hd,dt" or "+HD DT", case doesn't matter
+    // the combinations that make no sense get rejected
+    pub fn from_acronyms(source: &str) -> Result<Mods, String> {
+        let letters: Vec<u8> = source
+            .bytes()
+            .filter(|x| x.is_ascii_alphabetic())
+            .map(|x| x.to_ascii_uppercase())
+            .collect();
+
+        let mut out = Mods::empty();
+        for acronym in letters.chunks(2) {
+            let acronym = std::str::from_utf8(acronym).unwrap();
+            match ACRONYMS.iter().find(|x| x.1 == acronym) {
+                Some(x) => out |= x.0,
+                None => return Err(format!("Unknown mod {}", acronym)),
+            }
+        }
+
+        if out.contains(Mods::EASY | Mods::HARD_ROCK) {
+            return Err("EZ and HR can't be used together".to_string());
+        }
+        if out.contains(Mods::DOUBLE_TIME | Mods::HALF_TIME) {
+            return Err("DT and HT can't be used together".to_string());
+        }
+        Ok(out)
+    }
+}
+
+impl std::fmt::Display for Mods {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "NM");
+        }
+        for (flag, acronym) in ACRONYMS {
+            if self.contains(flag) {
+                write!(f, "{}", acronym)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::beatmap::*;
+
+    #[test]
+    fn test_acronyms() {
+        let mods = Mods::from_acronyms("+hd, DT").unwrap();
+        assert_eq!(mods, Mods::HIDDEN | Mods::DOUBLE_TIME);
+        assert_eq!(mods.to_string(), "HDDT");
+        assert_eq!(mods.clock_rate(), 1.5);
+        assert_eq!(Mods::empty().to_string(), "NM");
+        assert!(Mods::from_acronyms("EZHR").is_err());
+        assert!(Mods::from_acronyms("XX").is_err());
+    }
+
+    #[test]
+    fn test_hard_rock() {
+        let nomod = Beatmap::fixture_with_mods("test/simple_slider.osu", Mods::empty());
+        let hr = Beatmap::fixture_with_mods("test/simple_slider.osu", Mods::HARD_ROCK);
+
+        let (a, b) = (&nomod.hit_objects[0], &hr.hit_objects[0]);
+        assert_eq!(a.unstacked_start_pos.x, b.unstacked_start_pos.x);
+        assert_eq!(a.unstacked_start_pos.y, 384.0 - b.unstacked_start_pos.y);
+        let (a, b) = (
+            a.slider_info.as_ref().unwrap(),
+            b.slider_info.as_ref().unwrap(),
+        );
+        for (a, b) in a.control_points.iter().zip(&b.control_points) {
+            assert_eq!(a.y, 384.0 - b.y);
+        }
+
+        // the stats in the file stay the same, only the derived ones change
+        assert_eq!(nomod.difficulty.approach_rate, hr.difficulty.approach_rate);
+        assert!(hr.difficulty.preempt < nomod.difficulty.preempt);
+        assert!(hr.difficulty.hit_300 < nomod.difficulty.hit_300);
+        assert!(hr.difficulty.obj_radius < nomod.difficulty.obj_radius);
+
+        // writing it back out undoes the flip
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        nomod.write(&mut a).unwrap();
+        hr.write(&mut b).unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_rate_change() {
+        let nomod = Beatmap::fixture_with_mods("test/simple_slider.osu", Mods::empty());
+        let dt = Beatmap::fixture_with_mods("test/simple_slider.osu", Mods::DOUBLE_TIME);
+
+        // beatmap time doesn't change, the windows only get tighter because the song plays faster
+        assert_eq!(dt.difficulty.preempt, nomod.difficulty.preempt);
+        assert_eq!(dt.difficulty.hit_300, nomod.difficulty.hit_300);
+        assert!(dt.difficulty.approach_rate_adjusted() > nomod.difficulty.approach_rate_adjusted());
+        assert!(
+            dt.difficulty.overall_difficulty_adjusted()
+                > nomod.difficulty.overall_difficulty_adjusted()
+        );
+        assert!(
+            (nomod.difficulty.approach_rate_adjusted() - nomod.difficulty.approach_rate).abs()
+                < 0.01
+        );
+    }
+}
@@ -1,7 +1,6 @@
 use std::io;
 use std::io::BufRead;
-
-use log::warn;
+use std::sync::Arc;
 
 use crate::curve::*;
 use crate::math::*;
@@ -21,6 +20,9 @@ pub enum BeatmapParseErr {
     InvalidFloat(u32),
     InvalidEnum(u32),
     InvalidTimingPoint(u32),
+    InvalidEvent(u32),
+    InvalidColour(u32),
+    InField(usize, Box<BeatmapParseErr>), // which comma separated value the error came from
 }
 impl From<io::Error> for BeatmapParseErr {
     fn from(x: io::Error) -> BeatmapParseErr {
@@ -69,25 +71,102 @@ impl BeatmapParse for f64 {
     }
 }
 
+// "r,g,b" with an optional alpha that gets ignored, returned as BGR
+pub fn parse_colour(source: &str, line_num: u32) -> Result<u32, BeatmapParseErr> {
+    let split: Vec<&str> = source.split(',').collect();
+    if split.len() < 3 {
+        return Err(BeatmapParseErr::InvalidColour(line_num));
+    }
+
+    let mut out = 0;
+    for (i, x) in split.iter().take(3).enumerate() {
+        let component = i32::parse(x, line_num)?.clamp(0, 255) as u32;
+        out |= component << (i * 8);
+    }
+    Ok(out)
+}
+
+// comma separated values that remember how far they got, so errors can point at the right one
+struct Fields<'a> {
+    split: std::str::Split<'a, char>,
+    taken: usize,
+}
+
+impl<'a> Fields<'a> {
+    fn new(line: &'a str) -> Self {
+        Fields {
+            split: line.split(','),
+            taken: 0,
+        }
+    }
+
+    // blames the last value that was taken
+    fn wrap(&self, err: BeatmapParseErr) -> BeatmapParseErr {
+        BeatmapParseErr::InField(self.taken.saturating_sub(1), Box::new(err))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let x = self.split.next();
+        if x.is_some() {
+            self.taken += 1;
+        }
+        x
+    }
+}
+
 enum Section {
     None,
     General,
+    Editor,
     Metadata,
     Difficulty,
+    Variables,
+    Events,
     TimingPoints,
+    Colours,
     HitObjects,
 }
 
 impl Beatmap {
-    const LATEST_FORMAT_VERSION: i32 = 14;
+    pub(super) const LATEST_FORMAT_VERSION: i32 = 14;
+
+    pub fn parse(set: Arc<BeatmapSet>, file: &mut impl BufRead) -> Result<Self, BeatmapParseErr> {
+        Self::parse_with_mods(set, file, Mods::empty())
+    }
+
+    // mods have to be known while parsing since hr flips every object
+    pub fn parse_with_mods(
+        set: Arc<BeatmapSet>,
+        file: &mut impl BufRead,
+        mods: Mods,
+    ) -> Result<Self, BeatmapParseErr> {
+        Self::parse_with_mode(set, file, mods, ParseMode::Strict)
+    }
 
-    pub fn parse(base_path: &str, file: &mut impl BufRead) -> Result<Self, BeatmapParseErr> {
+    // whatever gets skipped or fixed up along the way ends up in diagnostics
+    pub fn parse_with_mode(
+        set: Arc<BeatmapSet>,
+        file: &mut impl BufRead,
+        mods: Mods,
+        mode: ParseMode,
+    ) -> Result<Self, BeatmapParseErr> {
         let mut beatmap = Beatmap {
-            base_path: base_path.to_string(),
+            set,
             format_version: Self::LATEST_FORMAT_VERSION,
             preview_time: -1,
             stack_leniency: 0.7,
-            difficulty: Default::default(),
+            timeline_zoom: 1.0,
+            distance_spacing: 1.0,
+            beat_divisor: 4,
+            grid_size: 32,
+            difficulty: Difficulty {
+                mods,
+                ..Default::default()
+            },
 
             ..Default::default()
         };
@@ -97,13 +176,12 @@ impl Beatmap {
 
         // version should always be at the top
         // otherwise, osu just assumes it's the latest version
-        match Self::next_line(file, &mut buffer) {
-            Ok(_) => {
-                if let Some(stripped) = buffer.strip_prefix("osu file format v") {
-                    beatmap.format_version = i32::parse(stripped.trim(), 1)?
-                }
+        Self::next_line(file, &mut buffer)?;
+        if let Some(stripped) = buffer.strip_prefix("osu file format v") {
+            match i32::parse(stripped.trim(), 1) {
+                Ok(x) => beatmap.format_version = x,
+                Err(e) => beatmap.report(mode, Severity::Error, 1, &buffer, e)?,
             }
-            Err(e) => return Err(e),
         }
 
         let mut section = Section::None;
@@ -127,26 +205,42 @@ impl Beatmap {
                 let section_name = &buffer[1..(buffer.trim_end().len() - 1)];
                 section = match section_name {
                     "General" => Section::General,
+                    "Editor" => Section::Editor,
                     "Metadata" => Section::Metadata,
                     "Difficulty" => Section::Difficulty,
+                    "Variables" => Section::Variables,
+                    "Events" => Section::Events,
                     "TimingPoints" => Section::TimingPoints,
+                    "Colours" => Section::Colours,
                     "HitObjects" => Section::HitObjects,
                     _ => Section::None,
                 };
                 continue;
             }
 
-            match section {
+            let res = match section {
                 Section::General => beatmap.handle_general(&buffer, line_num),
+                Section::Editor => beatmap.handle_editor(&buffer, line_num),
                 Section::Metadata => beatmap.handle_metadata(&buffer, line_num),
                 Section::Difficulty => beatmap.handle_difficulty(&buffer, line_num),
-                Section::TimingPoints => {
-                    beatmap.handle_timingpoints(&buffer, line_num);
+                Section::Variables => {
+                    beatmap.handle_variables(&buffer);
                     Ok(())
                 }
+                Section::Events => beatmap.handle_events(&buffer, line_num),
+                Section::TimingPoints => beatmap.handle_timingpoints(&buffer, line_num),
+                Section::Colours => beatmap.handle_colours(&buffer, line_num),
                 Section::HitObjects => beatmap.handle_hitobjects(&buffer, line_num),
                 _ => continue,
-            }?
+            };
+            if let Err(e) = res {
+                // osu is very lenient on timing points and storyboards, it just skips broken lines
+                let severity = match section {
+                    Section::Events | Section::TimingPoints => Severity::Warning,
+                    _ => Severity::Error,
+                };
+                beatmap.report(mode, severity, line_num, &buffer, e)?;
+            }
         }
 
         if beatmap.artist.is_empty() {
@@ -159,20 +253,49 @@ impl Beatmap {
         // misc post-processing stuff
         // should be its own step, but for my purposes i don't need it as one
         beatmap.difficulty.recalculate();
+        beatmap.rebuild_timing_index();
         for x in &mut beatmap.hit_objects {
             if x.object_type == HitObjectType::Slider {
-                x.recalculate_slider(
-                    beatmap.format_version,
-                    &beatmap.timing_points,
-                    &beatmap.difficulty,
-                );
+                x.recalculate_slider(beatmap.format_version, &beatmap.timing, &beatmap.difficulty);
             }
         }
-        beatmap.process_stacking();
+        // other modes never stack objects, they still need their positions set though
+        if beatmap.mode == Gamemode::Osu {
+            beatmap.process_stacking();
+        }
+        beatmap.apply_stack_offsets();
+        beatmap.process_combos();
 
         Ok(beatmap)
     }
 
+    // strict mode gives up on errors, everything else gets written down and the line is skipped
+    fn report(
+        &mut self,
+        mode: ParseMode,
+        severity: Severity,
+        line_num: u32,
+        line: &str,
+        err: BeatmapParseErr,
+    ) -> Result<(), BeatmapParseErr> {
+        // these aren't about any one line, so there's nothing to skip
+        if err.line().is_none() || (mode == ParseMode::Strict && severity == Severity::Error) {
+            return Err(err);
+        }
+        self.diagnostics
+            .push(ParseDiagnostic::from_err(severity, line_num, line, &err));
+        Ok(())
+    }
+
+    fn warn_line(&mut self, line_num: u32, line: &str, message: &str) {
+        self.diagnostics.push(ParseDiagnostic::new(
+            Severity::Warning,
+            line_num,
+            line,
+            message.to_string(),
+        ));
+    }
+
     fn handle_general(&mut self, line: &str, line_num: u32) -> Result<(), BeatmapParseErr> {
         if let Some((key, val)) = Self::split_key_val(line) {
             match key {
@@ -191,11 +314,14 @@ impl Beatmap {
                 }
                 "CountdownOffset" => self.countdown_offset = i32::parse(val, line_num)?,
                 "CustomSamples" => self.custom_samples = bool::parse(val, line_num)?,
-                "EpilespyWarning" => self.epilepsy_warning = bool::parse(val, line_num)?,
+                "EpilepsyWarning" => self.epilepsy_warning = bool::parse(val, line_num)?,
                 "LetterboxInBreaks" => self.letterbox_in_breaks = bool::parse(val, line_num)?,
                 "Mode" => {
                     self.mode = match i32::parse(val, line_num)? {
                         0 => Gamemode::Osu,
+                        1 => Gamemode::Taiko,
+                        2 => Gamemode::CatchTheBeat,
+                        3 => Gamemode::Mania,
                         _ => return Err(BeatmapParseErr::UnsupportedMode),
                     }
                 }
@@ -218,6 +344,26 @@ impl Beatmap {
         Ok(())
     }
 
+    fn handle_editor(&mut self, line: &str, line_num: u32) -> Result<(), BeatmapParseErr> {
+        if let Some((key, val)) = Self::split_key_val(line) {
+            match key {
+                "Bookmarks" => {
+                    self.bookmarks.clear();
+                    for x in val.split(',').filter(|x| !x.trim().is_empty()) {
+                        self.bookmarks.push(i32::parse(x, line_num)?);
+                    }
+                }
+                "DistanceSpacing" => self.distance_spacing = f64::parse(val, line_num)?,
+                "BeatDivisor" => self.beat_divisor = i32::parse(val, line_num)?,
+                "GridSize" => self.grid_size = i32::parse(val, line_num)?,
+                "TimelineZoom" => self.timeline_zoom = f32::parse(val, line_num)?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
     fn handle_metadata(&mut self, line: &str, line_num: u32) -> Result<(), BeatmapParseErr> {
         if let Some((key, val)) = Self::split_key_val(line) {
             match key {
@@ -268,17 +414,18 @@ impl Beatmap {
         Ok(())
     }
 
-    fn handle_timingpoints(&mut self, line: &str, line_num: u32) {
+    fn handle_timingpoints(&mut self, line: &str, line_num: u32) -> Result<(), BeatmapParseErr> {
         // osu is very lenient on TimingPoints, simply skipping over it if there's an exception most of the time
         // i'll take skipping over not loading at all
         let mut new_point: TimingPoint = Default::default();
+        let mut split = Fields::new(line);
         let mut parse = || -> Result<(), BeatmapParseErr> {
-            let mut split = line.split(',');
             let split_num = line.matches(',').count();
 
             new_point.time_signature = 4;
             new_point.timing_change = true;
             new_point.volume = 100;
+            new_point.line = line_num;
 
             // TODO: this looks awful
             if split_num >= 1 {
@@ -310,9 +457,10 @@ impl Beatmap {
                                 if let Some(timing_change) = split.next() {
                                     new_point.timing_change =
                                         bool::parse(timing_change.trim(), line_num)?;
-                                    if let Some(kiai) = split.next() {
-                                        new_point.kiai =
-                                            i32::parse(kiai.trim(), line_num)? & 1 == 1;
+                                    if let Some(effects) = split.next() {
+                                        let effects = i32::parse(effects.trim(), line_num)?;
+                                        new_point.kiai = effects & 1 == 1;
+                                        new_point.omit_first_barline = effects & 8 == 8;
                                     }
                                 }
                             }
@@ -325,56 +473,134 @@ impl Beatmap {
             }
         };
 
-        let res = parse();
-        if res.is_ok() {
-            self.timing_points.push(new_point);
-        } else {
-            warn!("Skipping timing point \"{}\" because parsing failed", line);
+        match parse() {
+            Ok(_) => {
+                self.timing_points.push(new_point);
+                Ok(())
+            }
+            Err(BeatmapParseErr::InvalidTimingPoint(x)) => {
+                Err(BeatmapParseErr::InvalidTimingPoint(x))
+            }
+            Err(e) => Err(split.wrap(e)),
         }
     }
 
-    #[allow(clippy::field_reassign_with_default)]
+    fn handle_colours(&mut self, line: &str, line_num: u32) -> Result<(), BeatmapParseErr> {
+        if let Some((key, val)) = Self::split_key_val(line) {
+            match key {
+                "SliderTrackOverride" => {
+                    self.slider_track_override = Some(parse_colour(val, line_num)?)
+                }
+                "SliderBorder" => self.slider_border = Some(parse_colour(val, line_num)?),
+                // osu only reads Combo1 through Combo8, in whatever order they show up
+                x if x.starts_with("Combo") => {
+                    if let Ok(idx) = x[5..].parse::<usize>() {
+                        if (1..=8).contains(&idx) {
+                            self.combo_colours.push(parse_colour(val, line_num)?);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
     fn handle_hitobjects(&mut self, line: &str, line_num: u32) -> Result<(), BeatmapParseErr> {
-        let mut split = line.split(',');
+        let mut split = Fields::new(line);
+        self.handle_hitobjects_inner(&mut split, line, line_num)
+            .map_err(|e| split.wrap(e))
+    }
+
+    #[allow(clippy::field_reassign_with_default)]
+    fn handle_hitobjects_inner(
+        &mut self,
+        split: &mut Fields,
+        line: &str,
+        line_num: u32,
+    ) -> Result<(), BeatmapParseErr> {
         let split_num = line.matches(',').count() + 1;
         if split_num < 5 {
+            self.warn_line(
+                line_num,
+                line,
+                "not enough values for a hit object, skipping it",
+            );
             return Ok(());
         }
 
         let mut new_obj: HitObject = Default::default();
-        // TODO: flip for hr once mods are implemented
         let x = f32::parse(split.next().unwrap(), line_num)?.clamp(0.0, 512.0) as i32 as f32;
         let y = f32::parse(split.next().unwrap(), line_num)?.clamp(0.0, 512.0) as i32 as f32;
+        let y = self.difficulty.mods.flip_y(y);
         new_obj.unstacked_start_pos = Vector2::new(x, y);
         new_obj.unstacked_end_pos = Vector2::new(x, y);
+        new_obj.line = line_num;
         new_obj.start = i32::parse(split.next().unwrap(), line_num)?;
-        new_obj.end = new_obj.start;
         if self.format_version < 5 {
             new_obj.start += 24;
         }
+        new_obj.end = new_obj.start;
 
         let type_flags = i32::parse(split.next().unwrap(), line_num)?;
-        let _hitsound = split.next().unwrap();
+        new_obj.hitsound =
+            HitSoundType::from_bits_truncate(i32::parse(split.next().unwrap(), line_num)?);
         // TODO: this is disgusting
         new_obj.object_type = if type_flags & 1 > 0 {
-            self.circle_count += 1;
             HitObjectType::Circle
         } else if type_flags & 2 > 0 {
-            self.slider_count += 1;
             HitObjectType::Slider
         } else if type_flags & 8 > 0 {
-            self.spinner_count += 1;
             HitObjectType::Spinner
+        } else if type_flags & 128 > 0 {
+            HitObjectType::Hold
         } else {
-            // TODO: mania hold stuff? you can do cool stuff with that
+            self.warn_line(line_num, line, "unknown hit object type, skipping it");
             return Ok(());
         };
         new_obj.flags = type_flags;
 
         match new_obj.object_type {
-            HitObjectType::Circle | HitObjectType::Spinner => {} // nothing to do here, sample stuff is ignored
+            HitObjectType::Circle => {
+                if let Some(hit_sample) = split.next() {
+                    new_obj.hit_sample = HitSample::parse(hit_sample, line_num)?;
+                    new_obj.has_hit_sample = true;
+                }
+            }
+            HitObjectType::Spinner => {
+                if let Some(end) = split.next() {
+                    new_obj.end = i32::parse(end, line_num)?;
+                    if self.format_version < 5 {
+                        new_obj.end += 24;
+                    }
+                    new_obj.end = new_obj.end.max(new_obj.start);
+                }
+                if let Some(hit_sample) = split.next() {
+                    new_obj.hit_sample = HitSample::parse(hit_sample, line_num)?;
+                    new_obj.has_hit_sample = true;
+                }
+            }
+            HitObjectType::Hold => {
+                // the end time is stuck onto the front of the hit sample
+                if let Some(extras) = split.next() {
+                    let (end, hit_sample) = extras.split_once(':').unwrap_or((extras, ""));
+                    new_obj.end = i32::parse(end, line_num)?;
+                    if self.format_version < 5 {
+                        new_obj.end += 24;
+                    }
+                    new_obj.end = new_obj.end.max(new_obj.start);
+                    new_obj.hit_sample = HitSample::parse(hit_sample, line_num)?;
+                    new_obj.has_hit_sample = !hit_sample.is_empty();
+                }
+            }
             HitObjectType::Slider => {
                 if split_num < 7 {
+                    self.warn_line(
+                        line_num,
+                        line,
+                        "not enough values for a slider, skipping it",
+                    );
                     return Ok(());
                 }
                 let mut slider_info = SliderInfo::default();
@@ -396,6 +622,11 @@ impl Beatmap {
                         let mut point_split = entry.split(':');
                         let point_split_num = entry.matches(':').count() + 1;
                         if point_split_num < 2 {
+                            self.warn_line(
+                                line_num,
+                                line,
+                                "slider has a broken control point, skipping it",
+                            );
                             return Ok(());
                         }
                         // i have no idea why osu parses it like this
@@ -404,7 +635,10 @@ impl Beatmap {
                             f64_to_wrapping_i32(f64::parse(point_split.next().unwrap(), line_num)?);
                         let y =
                             f64_to_wrapping_i32(f64::parse(point_split.next().unwrap(), line_num)?);
-                        control_points.push(Vector2::new(x as f32, y as f32));
+                        control_points.push(Vector2::new(
+                            x as f32,
+                            self.difficulty.mods.flip_y(y as f32),
+                        ));
                     }
                 }
 
@@ -412,24 +646,66 @@ impl Beatmap {
                 if split_num > 7 {
                     slider_info.spatial_length = f64::parse(split.next().unwrap(), line_num)?;
                 }
+                let edge_sounds = split.next();
+                let edge_sets = split.next();
+                slider_info.has_edge_hitsounds = edge_sounds.is_some();
+                if let Some(hit_sample) = split.next() {
+                    new_obj.hit_sample = HitSample::parse(hit_sample, line_num)?;
+                    new_obj.has_hit_sample = true;
+                }
+
+                // edges without their own info just use the object's
+                let edge_count = slider_info.slides as usize + 1;
+                slider_info.edge_sounds = vec![new_obj.hitsound; edge_count];
+                slider_info.edge_sets = vec![
+                    (
+                        new_obj.hit_sample.normal_set,
+                        new_obj.hit_sample.addition_set
+                    );
+                    edge_count
+                ];
+                if let Some(edge_sounds) = edge_sounds.filter(|x| !x.trim().is_empty()) {
+                    for (i, x) in edge_sounds.split('|').take(edge_count).enumerate() {
+                        slider_info.edge_sounds[i] =
+                            HitSoundType::from_bits_truncate(i32::parse(x, line_num)?);
+                    }
+                }
+                if let Some(edge_sets) = edge_sets.filter(|x| !x.trim().is_empty()) {
+                    for (i, x) in edge_sets.split('|').take(edge_count).enumerate() {
+                        let mut sets = x.split(':');
+                        if let (Some(normal), Some(addition)) = (sets.next(), sets.next()) {
+                            slider_info.edge_sets[i] = (
+                                SampleSet::from_i32(i32::parse(normal, line_num)?),
+                                SampleSet::from_i32(i32::parse(addition, line_num)?),
+                            );
+                        }
+                    }
+                }
 
                 slider_info.curve = Curve::new(
                     curve_type,
-                    control_points,
+                    control_points.clone(),
                     self.format_version,
                     slider_info.spatial_length,
                 );
+                slider_info.curve_type = curve_type;
+                slider_info.control_points = control_points;
 
                 new_obj.unstacked_end_pos = slider_info.curve.point_at(1.0);
                 new_obj.slider_info = Some(Box::new(slider_info));
             }
         }
 
-        // sorted insert
-        let pos = self
-            .hit_objects
-            .binary_search(&new_obj)
-            .unwrap_or_else(|x| x);
+        // only counted once it's actually in, anything above can still skip it
+        match new_obj.object_type {
+            HitObjectType::Circle => self.circle_count += 1,
+            HitObjectType::Slider => self.slider_count += 1,
+            HitObjectType::Spinner => self.spinner_count += 1,
+            HitObjectType::Hold => self.hold_count += 1,
+        }
+
+        // sorted insert, objects at the same time keep their file order
+        let pos = self.hit_objects.partition_point(|x| x <= &new_obj);
         self.hit_objects.insert(pos, new_obj);
         //self.hit_objects.push(new_obj);
 
@@ -452,6 +728,37 @@ impl Beatmap {
         }
     }
 
+    fn process_combos(&mut self) {
+        // port from https://github.com/ppy/osu/blob/master/osu.Game/Rulesets/Objects/Legacy/ConvertHitObjectParser.cs
+        // spinners never start a combo themselves, but the object after one does
+        let mut combo_index = 0;
+        let mut combo_number = 0;
+        let mut force_new_combo = false;
+        let mut extra_skip = 0;
+        let mut first = true;
+
+        for x in self.hit_objects.iter_mut() {
+            if x.object_type == HitObjectType::Spinner {
+                force_new_combo |= self.format_version <= 8 || x.is_new_combo();
+                extra_skip += x.combo_skip();
+            } else {
+                if first || x.is_new_combo() || force_new_combo {
+                    if !first {
+                        combo_index += 1 + x.combo_skip() + extra_skip;
+                    }
+                    combo_number = 0;
+                    first = false;
+                }
+                combo_number += 1;
+                force_new_combo = false;
+                extra_skip = 0;
+            }
+
+            x.combo_index = combo_index;
+            x.combo_number = combo_number;
+        }
+    }
+
     fn process_stacking(&mut self) {
         // TODO: this needs a test
         // port from https://github.com/ppy/osu/blob/master/osu.Game.Rulesets.Osu/Beatmaps/OsuBeatmapProcessor.cs
@@ -541,7 +848,7 @@ impl Beatmap {
                             }
                         }
                     }
-                    HitObjectType::Spinner => (),
+                    HitObjectType::Spinner | HitObjectType::Hold => (),
                 }
             }
         } else {
@@ -578,7 +885,9 @@ impl Beatmap {
                 }
             }
         }
+    }
 
+    fn apply_stack_offsets(&mut self) {
         for x in self.hit_objects.iter_mut() {
             let offset = Vector2::new(self.difficulty.stack_offset, self.difficulty.stack_offset)
                 * x.stack_count as f32;
@@ -588,3 +897,110 @@ impl Beatmap {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::beatmap::*;
+
+    #[test]
+    fn test_colours() {
+        let beatmap = Beatmap::fixture("test/combo_colours.osu");
+
+        // file order, BGR, anything past Combo8 is ignored
+        assert_eq!(beatmap.combo_colours, &[0x00CA00, 0x00C0FF]);
+        assert_eq!(beatmap.slider_track_override, Some(0x1200FF));
+        assert_eq!(beatmap.slider_border, Some(0xFFFFFF));
+
+        let combos: Vec<(i32, i32)> = beatmap
+            .hit_objects
+            .iter()
+            .map(|x| (x.combo_index, x.combo_number))
+            .collect();
+        assert_eq!(combos, &[(0, 1), (0, 2), (2, 1), (2, 1), (5, 1)]);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let source = "osu file format v14
+
+[General]
+Mode: 0
+PreviewTime: soon
+
+[TimingPoints]
+0,500,4,1,0,100,1,0
+1000,x
+
+[HitObjects]
+256,192,1000,1,0,0:0:0:0:
+256,192
+256,192,abc,1,0
+256,192,2000,64,0
+";
+        let parse = |mode| {
+            Beatmap::parse_with_mode(
+                Default::default(),
+                &mut source.as_bytes(),
+                Mods::empty(),
+                mode,
+            )
+        };
+
+        // stable won't load a map with a broken PreviewTime
+        match parse(ParseMode::Strict) {
+            Err(BeatmapParseErr::InvalidInt(5)) => {}
+            x => panic!("expected an invalid int on line 5, got {:?}", x.err()),
+        }
+
+        let beatmap = parse(ParseMode::Lenient).unwrap();
+        let found: Vec<(Severity, u32, Option<usize>)> = beatmap
+            .diagnostics
+            .iter()
+            .map(|x| (x.severity, x.line, x.field))
+            .collect();
+        assert_eq!(
+            found,
+            &[
+                (Severity::Error, 5, None),
+                (Severity::Warning, 9, Some(1)),
+                (Severity::Warning, 13, None),
+                (Severity::Error, 14, Some(2)),
+                (Severity::Warning, 15, None),
+            ]
+        );
+        assert_eq!(beatmap.diagnostics[3].text, "256,192,abc,1,0");
+        assert_eq!(beatmap.preview_time, -1);
+        assert_eq!(beatmap.timing_points.len(), 1);
+        assert_eq!(beatmap.hit_objects.len(), 1);
+    }
+
+    #[test]
+    fn test_lenient_counts() {
+        let source = "osu file format v14
+
+[TimingPoints]
+0,500,4,1,0,100,1,0
+
+[HitObjects]
+256,192,1000,1,0,0:0:0:0:
+100,100,1500,2,0,L|200
+100,100,2000,2,0,L|200:100
+100,100,2500,2,0,L|200:100,1,100
+256,192,3000,12,0,4000,0:0:0:0:
+";
+        let beatmap = Beatmap::parse_with_mode(
+            Default::default(),
+            &mut source.as_bytes(),
+            Mods::empty(),
+            ParseMode::Lenient,
+        )
+        .unwrap();
+
+        // a broken control point and a missing length, only the last slider is left
+        assert_eq!(beatmap.diagnostics.len(), 2);
+        assert_eq!(beatmap.hit_objects.len(), 3);
+        assert_eq!(beatmap.circle_count, 1);
+        assert_eq!(beatmap.slider_count, 1);
+        assert_eq!(beatmap.spinner_count, 1);
+    }
+}
@@ -0,0 +1,220 @@
+use super::*;
+
+// what's in effect from one timing point until the next one
+#[derive(Debug, Clone, Copy)]
+pub struct TimingSegment {
+    pub start: f64,
+    pub beat_length: f64, // of the uninherited point, without any slider velocity
+    pub slider_velocity: f32, // from the inherited point, 1.0 if there isn't one
+    pub kiai: bool,
+    pub sample_set: SampleSet,
+    pub custom_sample_set: i32,
+    pub volume: i32,
+
+    // positions in TimingIndex::points
+    point: usize,             // the one that starts this segment
+    inherited: Option<usize>, // only if it comes after the uninherited one
+}
+
+impl TimingSegment {
+    pub fn bpm(&self) -> f64 {
+        60000.0 / self.beat_length
+    }
+
+    // what sliders and scroll speed actually go by
+    pub fn effective_beat_length(&self) -> f64 {
+        self.beat_length * self.slider_velocity as f64
+    }
+}
+
+// every time based lookup goes through here instead of scanning the timing points every time
+// built once after parsing, so it has to be rebuilt if the timing points change after that
+#[derive(Debug, Default)]
+pub struct TimingIndex {
+    points: Vec<TimingPoint>, // sorted by offset, points at the same time keep their file order
+    segments: Vec<TimingSegment>, // one per point
+    uninherited: Vec<usize>,  // positions in points
+    inherited: Vec<usize>,
+}
+
+impl TimingIndex {
+    pub fn new(timing_points: &[TimingPoint]) -> TimingIndex {
+        // nan offsets never apply to anything
+        let mut points: Vec<TimingPoint> = timing_points
+            .iter()
+            .filter(|x| !x.offset.is_nan())
+            .cloned()
+            .collect();
+        points.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+
+        let mut uninherited = Vec::new();
+        let mut inherited = Vec::new();
+        let mut segments = Vec::with_capacity(points.len());
+        for (i, x) in points.iter().enumerate() {
+            if x.timing_change {
+                uninherited.push(i);
+            } else {
+                inherited.push(i);
+            }
+
+            // osu falls back to the very first point for anything that hasn't shown up yet
+            let u = uninherited.last().copied().unwrap_or(0);
+            let s = inherited.last().copied().filter(|&s| s > u);
+            let slider_velocity = match s {
+                Some(s) if points[s].beat_length < 0.0 => points[s].bpm_multiplier(),
+                _ => 1.0,
+            };
+            segments.push(TimingSegment {
+                start: x.offset,
+                beat_length: points[u].beat_length,
+                slider_velocity,
+                kiai: x.kiai,
+                sample_set: x.sample_set,
+                custom_sample_set: x.custom_sample_set,
+                volume: x.volume,
+                point: i,
+                inherited: s,
+            });
+        }
+
+        TimingIndex {
+            points,
+            segments,
+            uninherited,
+            inherited,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    // how many of the sorted points start at or before the time
+    fn count_at(&self, positions: &[usize], time: f64) -> usize {
+        positions.partition_point(|&i| self.points[i].offset <= time)
+    }
+
+    // anything before the first point gets the first segment
+    pub fn segment_at(&self, time: f64) -> Option<&TimingSegment> {
+        let count = self.segments.partition_point(|x| x.start <= time);
+        self.segments.get(count.saturating_sub(1))
+    }
+
+    pub fn segments(&self) -> &[TimingSegment] {
+        &self.segments
+    }
+
+    // the point that starts the segment at the time
+    pub fn point_at(&self, time: f64) -> Option<&TimingPoint> {
+        self.segment_at(time).map(|x| &self.points[x.point])
+    }
+
+    // the one that sets the bpm at the time, or the first one if it's before all of them
+    pub fn uninherited_at(&self, time: f64) -> Option<&TimingPoint> {
+        let count = self.count_at(&self.uninherited, time);
+        self.uninherited
+            .get(count.saturating_sub(1))
+            .map(|&i| &self.points[i])
+    }
+
+    // the one changing the slider velocity at the time, if there's one after the last uninherited point
+    pub fn inherited_at(&self, time: f64) -> Option<&TimingPoint> {
+        self.segment_at(time)
+            .and_then(|x| x.inherited)
+            .map(|i| &self.points[i])
+    }
+
+    pub fn uninherited_points(&self) -> impl Iterator<Item = &TimingPoint> + Clone {
+        self.uninherited.iter().map(|&i| &self.points[i])
+    }
+
+    pub fn inherited_points(&self) -> impl Iterator<Item = &TimingPoint> + Clone {
+        self.inherited.iter().map(|&i| &self.points[i])
+    }
+
+    pub fn beat_length_at(&self, time: f64, use_multiplier: bool) -> f64 {
+        match self.segment_at(time) {
+            Some(x) if use_multiplier => x.effective_beat_length(),
+            Some(x) => x.beat_length,
+            None => 0.0,
+        }
+    }
+
+    // the multiplier of the point starting the segment, even if it's an uninherited one
+    pub fn bpm_multiplier_at(&self, time: f64) -> f32 {
+        self.point_at(time)
+            .map(|x| x.bpm_multiplier())
+            .unwrap_or(1.0)
+    }
+
+    pub fn kiai_at(&self, time: f64) -> bool {
+        self.segment_at(time).map(|x| x.kiai).unwrap_or(false)
+    }
+
+    // lowest and highest bpm out of the valid uninherited points
+    pub fn bpm_range(&self) -> Option<(f64, f64)> {
+        self.uninherited_points()
+            .filter(|x| x.beat_length > 0.0)
+            .map(|x| 60000.0 / x.beat_length)
+            .fold(None, |range, bpm| match range {
+                Some((min, max)) => Some((f64::min(min, bpm), f64::max(max, bpm))),
+                None => Some((bpm, bpm)),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(offset: f64, beat_length: f64, timing_change: bool) -> TimingPoint {
+        TimingPoint {
+            offset,
+            beat_length,
+            timing_change,
+            time_signature: 4,
+            volume: 100,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_timing_index() {
+        assert!(TimingIndex::new(&[]).segment_at(0.0).is_none());
+        assert_eq!(TimingIndex::new(&[]).beat_length_at(0.0, true), 0.0);
+
+        // out of order on purpose
+        let index = TimingIndex::new(&[
+            point(1000.0, 500.0, true),
+            point(0.0, 250.0, true),
+            point(500.0, -50.0, false),
+            point(1000.0, -200.0, false),
+            point(f64::NAN, 100.0, true),
+        ]);
+        assert_eq!(index.segments().len(), 4);
+
+        // before everything, so it's the first point
+        assert_eq!(index.beat_length_at(-100.0, true), 250.0);
+        assert_eq!(index.beat_length_at(0.0, true), 250.0);
+        // 2x slider velocity halves the beat length
+        assert_eq!(index.beat_length_at(750.0, true), 125.0);
+        assert_eq!(index.beat_length_at(750.0, false), 250.0);
+        assert_eq!(index.bpm_multiplier_at(750.0), 0.5);
+        // the inherited point comes after the uninherited one at the same time, so it still applies
+        assert_eq!(index.beat_length_at(1000.0, true), 1000.0);
+        assert_eq!(index.uninherited_at(1500.0).unwrap().beat_length, 500.0);
+        assert_eq!(index.inherited_at(1500.0).unwrap().beat_length, -200.0);
+        assert!(index.inherited_at(250.0).is_none());
+        assert_eq!(index.segment_at(250.0).unwrap().bpm(), 240.0);
+        assert_eq!(index.bpm_range(), Some((120.0, 240.0)));
+
+        // a new uninherited point resets the slider velocity
+        let index = TimingIndex::new(&[
+            point(0.0, 250.0, true),
+            point(500.0, -50.0, false),
+            point(1000.0, 500.0, true),
+        ]);
+        assert_eq!(index.beat_length_at(1500.0, true), 500.0);
+        assert!(index.inherited_at(1500.0).is_none());
+    }
+}
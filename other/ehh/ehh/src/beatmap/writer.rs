@@ -0,0 +1,563 @@
+use std::io::{self, Write};
+
+use crate::curve::CurveType;
+use crate::math::Vector2;
+
+use super::*;
+
+// everything gets written as the latest version, so old maps get upgraded on save
+// the parser already applies the +24ms offset for pre-v5 maps, so times come out correct
+impl Beatmap {
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "osu file format v{}", Self::LATEST_FORMAT_VERSION)?;
+        writeln!(out)?;
+        self.write_general(out)?;
+        self.write_editor(out)?;
+        self.write_metadata(out)?;
+        self.write_difficulty(out)?;
+        self.write_variables(out)?;
+        self.write_events(out)?;
+        self.write_timingpoints(out)?;
+        self.write_colours(out)?;
+        self.write_hitobjects(out)
+    }
+
+    fn write_general(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "[General]")?;
+        writeln!(out, "AudioFilename: {}", self.audio_filename)?;
+        if !self.audio_hash.is_empty() {
+            writeln!(out, "AudioHash: {}", self.audio_hash)?;
+        }
+        writeln!(out, "AudioLeadIn: {}", self.audio_lead_in)?;
+        writeln!(out, "PreviewTime: {}", self.preview_time)?;
+        writeln!(out, "Countdown: {}", self.countdown as i32)?;
+        writeln!(out, "CountdownOffset: {}", self.countdown_offset)?;
+        writeln!(out, "SampleSet: {}", self.sample_set.name())?;
+        writeln!(out, "SampleVolume: {}", self.sample_volume)?;
+        writeln!(out, "StackLeniency: {}", self.stack_leniency)?;
+        writeln!(out, "Mode: {}", self.mode as i32)?;
+        writeln!(
+            out,
+            "LetterboxInBreaks: {}",
+            self.letterbox_in_breaks as i32
+        )?;
+        writeln!(
+            out,
+            "WidescreenStoryboard: {}",
+            self.widescreen_storyboard as i32
+        )?;
+        writeln!(
+            out,
+            "AlwaysShowPlayfield: {}",
+            self.always_show_playfield as i32
+        )?;
+        writeln!(out, "CustomSamples: {}", self.custom_samples as i32)?;
+        writeln!(out, "EpilepsyWarning: {}", self.epilepsy_warning as i32)?;
+        writeln!(out, "SpecialStyle: {}", self.special_style as i32)?;
+        writeln!(
+            out,
+            "SamplesMatchPlaybackRate: {}",
+            self.samples_match_playback_rate as i32
+        )?;
+        writeln!(out, "OverlayPosition: {}", self.overlay_position.name())?;
+        if !self.skin_preference.is_empty() {
+            writeln!(out, "SkinPreference: {}", self.skin_preference)?;
+        }
+        writeln!(out)
+    }
+
+    fn write_editor(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "[Editor]")?;
+        if !self.bookmarks.is_empty() {
+            let bookmarks: Vec<String> = self.bookmarks.iter().map(|x| x.to_string()).collect();
+            writeln!(out, "Bookmarks: {}", bookmarks.join(","))?;
+        }
+        writeln!(out, "DistanceSpacing: {}", self.distance_spacing)?;
+        writeln!(out, "BeatDivisor: {}", self.beat_divisor)?;
+        writeln!(out, "GridSize: {}", self.grid_size)?;
+        writeln!(out, "TimelineZoom: {}", self.timeline_zoom)?;
+        writeln!(out)
+    }
+
+    fn write_metadata(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "[Metadata]")?;
+        writeln!(out, "Title:{}", self.romanized_title)?;
+        writeln!(out, "TitleUnicode:{}", self.title)?;
+        writeln!(out, "Artist:{}", self.romanized_artist)?;
+        writeln!(out, "ArtistUnicode:{}", self.artist)?;
+        writeln!(out, "Creator:{}", self.creator)?;
+        writeln!(out, "Version:{}", self.version)?;
+        writeln!(out, "Source:{}", self.source)?;
+        writeln!(out, "Tags:{}", self.tags)?;
+        writeln!(out, "BeatmapID:{}", self.beatmap_id)?;
+        writeln!(out, "BeatmapSetID:{}", self.beatmap_set_id)?;
+        writeln!(out)
+    }
+
+    fn write_difficulty(&self, out: &mut impl Write) -> io::Result<()> {
+        let diff = &self.difficulty;
+        writeln!(out, "[Difficulty]")?;
+        writeln!(out, "HPDrainRate:{}", diff.hp_drain)?;
+        writeln!(out, "CircleSize:{}", diff.circle_size)?;
+        writeln!(out, "OverallDifficulty:{}", diff.overall_difficulty)?;
+        writeln!(out, "ApproachRate:{}", diff.approach_rate)?;
+        writeln!(out, "SliderMultiplier:{}", diff.slider_multiplier)?;
+        writeln!(out, "SliderTickRate:{}", diff.slider_tick_rate)?;
+        writeln!(out)
+    }
+
+    fn write_variables(&self, out: &mut impl Write) -> io::Result<()> {
+        // events are written with variables already substituted, but keep them around anyway
+        let variables = &self.events.storyboard.variables;
+        if variables.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "[Variables]")?;
+        for (key, val) in variables {
+            writeln!(out, "{}={}", key, val)?;
+        }
+        writeln!(out)
+    }
+
+    fn write_events(&self, out: &mut impl Write) -> io::Result<()> {
+        let events = &self.events;
+        writeln!(out, "[Events]")?;
+        writeln!(out, "//Background and Video events")?;
+        if let Some(bg) = &events.background {
+            writeln!(
+                out,
+                "0,0,\"{}\",{},{}",
+                bg.filename, bg.offset.x, bg.offset.y
+            )?;
+        }
+        if let Some(video) = &events.video {
+            writeln!(
+                out,
+                "Video,{},\"{}\",{},{}",
+                video.start_time, video.filename, video.offset.x, video.offset.y
+            )?;
+        }
+
+        writeln!(out, "//Break Periods")?;
+        for x in &events.breaks {
+            writeln!(out, "2,{},{}", x.start, x.end)?;
+        }
+
+        // objects are written in file order instead of being grouped under the layer comments
+        // draw order within a layer depends on it
+        writeln!(out, "//Storyboard Objects")?;
+        for x in &events.storyboard.objects {
+            Self::write_storyboard_object(out, x)?;
+        }
+
+        writeln!(out, "//Storyboard Sound Samples")?;
+        for x in &events.storyboard.samples {
+            writeln!(
+                out,
+                "Sample,{},{},\"{}\",{}",
+                x.time,
+                x.layer.name(),
+                x.filename,
+                x.volume
+            )?;
+        }
+        writeln!(out)
+    }
+
+    fn write_storyboard_object(out: &mut impl Write, object: &StoryboardObject) -> io::Result<()> {
+        let header = format!(
+            "{},{},\"{}\",{},{}",
+            object.layer.name(),
+            object.origin.name(),
+            object.filename,
+            object.pos.x,
+            object.pos.y
+        );
+        if let Some(animation) = &object.animation {
+            let loop_type = match animation.loop_type {
+                AnimationLoopType::LoopForever => "LoopForever",
+                AnimationLoopType::LoopOnce => "LoopOnce",
+            };
+            writeln!(
+                out,
+                "Animation,{},{},{},{}",
+                header, animation.frame_count, animation.frame_delay, loop_type
+            )?;
+        } else {
+            writeln!(out, "Sprite,{}", header)?;
+        }
+
+        for x in &object.commands {
+            Self::write_storyboard_command(out, x, 1)?;
+        }
+        for x in &object.loops {
+            writeln!(out, " L,{},{}", x.start_time, x.loop_count)?;
+            for command in &x.commands {
+                Self::write_storyboard_command(out, command, 2)?;
+            }
+        }
+        for x in &object.triggers {
+            writeln!(
+                out,
+                " T,{},{},{},{}",
+                x.trigger_name, x.start_time, x.end_time, x.group_number
+            )?;
+            for command in &x.commands {
+                Self::write_storyboard_command(out, command, 2)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_storyboard_command(
+        out: &mut impl Write,
+        command: &StoryboardCommand,
+        depth: usize,
+    ) -> io::Result<()> {
+        let (name, params) = match command.kind {
+            StoryboardCommandKind::Fade(a, b) => ("F", format!("{},{}", a, b)),
+            StoryboardCommandKind::Move(a, b) => ("M", format!("{},{}", vec2(a), vec2(b))),
+            StoryboardCommandKind::MoveX(a, b) => ("MX", format!("{},{}", a, b)),
+            StoryboardCommandKind::MoveY(a, b) => ("MY", format!("{},{}", a, b)),
+            StoryboardCommandKind::Scale(a, b) => ("S", format!("{},{}", a, b)),
+            StoryboardCommandKind::VectorScale(a, b) => ("V", format!("{},{}", vec2(a), vec2(b))),
+            StoryboardCommandKind::Rotate(a, b) => ("R", format!("{},{}", a, b)),
+            StoryboardCommandKind::Colour(a, b) => ("C", format!("{},{}", rgb(a), rgb(b))),
+            StoryboardCommandKind::Parameter(x) => (
+                "P",
+                match x {
+                    StoryboardParameter::FlipHorizontal => "H",
+                    StoryboardParameter::FlipVertical => "V",
+                    StoryboardParameter::AdditiveBlending => "A",
+                }
+                .to_string(),
+            ),
+        };
+
+        writeln!(
+            out,
+            "{}{},{},{},{},{}",
+            " ".repeat(depth),
+            name,
+            command.easing,
+            command.start,
+            command.end,
+            params
+        )
+    }
+
+    fn write_timingpoints(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "[TimingPoints]")?;
+        for x in &self.timing_points {
+            let effects = x.kiai as i32 | (x.omit_first_barline as i32) << 3;
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{}",
+                x.offset,
+                x.beat_length,
+                x.time_signature,
+                x.sample_set as i32,
+                x.custom_sample_set,
+                x.volume,
+                x.timing_change as i32,
+                effects
+            )?;
+        }
+        writeln!(out)
+    }
+
+    fn write_colours(&self, out: &mut impl Write) -> io::Result<()> {
+        if self.combo_colours.is_empty()
+            && self.slider_track_override.is_none()
+            && self.slider_border.is_none()
+        {
+            return Ok(());
+        }
+
+        writeln!(out, "[Colours]")?;
+        for (i, x) in self.combo_colours.iter().enumerate() {
+            writeln!(out, "Combo{} : {}", i + 1, rgb(*x))?;
+        }
+        if let Some(x) = self.slider_track_override {
+            writeln!(out, "SliderTrackOverride : {}", rgb(x))?;
+        }
+        if let Some(x) = self.slider_border {
+            writeln!(out, "SliderBorder : {}", rgb(x))?;
+        }
+        writeln!(out)
+    }
+
+    fn write_hitobjects(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "[HitObjects]")?;
+        // always write the map as it is without mods, hr has to be flipped back
+        let mods = self.difficulty.mods;
+        for x in &self.hit_objects {
+            write!(
+                out,
+                "{},{},{},{},{}",
+                x.unstacked_start_pos.x,
+                mods.flip_y(x.unstacked_start_pos.y),
+                x.start,
+                x.flags,
+                x.hitsound.bits()
+            )?;
+
+            // only write the optional fields if the map had them or they've been changed since
+            let hit_sample = x.has_hit_sample || x.hit_sample != HitSample::default();
+            match x.object_type {
+                HitObjectType::Circle => {}
+                HitObjectType::Spinner => write!(out, ",{}", x.end)?,
+                HitObjectType::Hold => {
+                    // the hit sample gets glued onto the end time
+                    if hit_sample {
+                        writeln!(out, ",{}:{}", x.end, x.hit_sample)?;
+                    } else {
+                        writeln!(out, ",{}", x.end)?;
+                    }
+                    continue;
+                }
+                HitObjectType::Slider => {
+                    let slider_info = x.slider_info.as_ref().unwrap();
+                    let curve_type = match slider_info.curve_type {
+                        CurveType::Catmull => "C",
+                        CurveType::Bezier => "B",
+                        CurveType::Linear => "L",
+                        CurveType::PerfectCircle => "P",
+                    };
+                    write!(out, ",{}", curve_type)?;
+                    // the first control point is the slider's position
+                    for point in slider_info.control_points.iter().skip(1) {
+                        write!(out, "|{}:{}", point.x, mods.flip_y(point.y))?;
+                    }
+                    write!(
+                        out,
+                        ",{},{}",
+                        slider_info.slides, slider_info.spatial_length
+                    )?;
+
+                    // the hit sample comes after the edges, so they have to be there for it
+                    let inherited = (x.hit_sample.normal_set, x.hit_sample.addition_set);
+                    let edges = hit_sample
+                        || slider_info.has_edge_hitsounds
+                        || slider_info.edge_sounds.iter().any(|y| *y != x.hitsound)
+                        || slider_info.edge_sets.iter().any(|y| *y != inherited);
+                    if !edges {
+                        writeln!(out)?;
+                        continue;
+                    }
+
+                    let edge_sounds: Vec<String> = slider_info
+                        .edge_sounds
+                        .iter()
+                        .map(|x| x.bits().to_string())
+                        .collect();
+                    let edge_sets: Vec<String> = slider_info
+                        .edge_sets
+                        .iter()
+                        .map(|x| format!("{}:{}", x.0 as i32, x.1 as i32))
+                        .collect();
+                    write!(out, ",{},{}", edge_sounds.join("|"), edge_sets.join("|"))?;
+                }
+            }
+
+            if hit_sample {
+                writeln!(out, ",{}", x.hit_sample)?;
+            } else {
+                writeln!(out)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn vec2(x: Vector2) -> String {
+    format!("{},{}", x.x, x.y)
+}
+
+// BGR to "r,g,b"
+fn rgb(x: u32) -> String {
+    format!("{},{},{}", x & 0xFF, (x >> 8) & 0xFF, (x >> 16) & 0xFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::beatmap::*;
+
+    fn write_to_string(beatmap: &Beatmap) -> String {
+        let mut out = Vec::new();
+        beatmap.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    // nan beat lengths are allowed, and should stay nan
+    fn same_f64(a: f64, b: f64) -> bool {
+        a == b || (a.is_nan() && b.is_nan())
+    }
+
+    fn assert_same(a: &Beatmap, b: &Beatmap, path: &std::path::Path) {
+        assert_eq!(a.format_version, b.format_version, "{:?}", path);
+        assert_eq!(a.audio_filename, b.audio_filename, "{:?}", path);
+        assert_eq!(a.audio_lead_in, b.audio_lead_in, "{:?}", path);
+        assert_eq!(a.preview_time, b.preview_time, "{:?}", path);
+        assert_eq!(a.mode, b.mode, "{:?}", path);
+        assert_eq!(a.sample_set, b.sample_set, "{:?}", path);
+        assert_eq!(a.stack_leniency, b.stack_leniency, "{:?}", path);
+        assert_eq!(a.bookmarks, b.bookmarks, "{:?}", path);
+        assert_eq!(a.artist, b.artist, "{:?}", path);
+        assert_eq!(a.romanized_artist, b.romanized_artist, "{:?}", path);
+        assert_eq!(a.title, b.title, "{:?}", path);
+        assert_eq!(a.romanized_title, b.romanized_title, "{:?}", path);
+        assert_eq!(a.creator, b.creator, "{:?}", path);
+        assert_eq!(a.version, b.version, "{:?}", path);
+        assert_eq!(a.source, b.source, "{:?}", path);
+        assert_eq!(a.tags, b.tags, "{:?}", path);
+        assert_eq!(a.beatmap_id, b.beatmap_id, "{:?}", path);
+        assert_eq!(a.beatmap_set_id, b.beatmap_set_id, "{:?}", path);
+
+        let (x, y) = (&a.difficulty, &b.difficulty);
+        assert_eq!(x.hp_drain, y.hp_drain, "{:?}", path);
+        assert_eq!(x.circle_size, y.circle_size, "{:?}", path);
+        assert_eq!(x.overall_difficulty, y.overall_difficulty, "{:?}", path);
+        assert_eq!(x.approach_rate, y.approach_rate, "{:?}", path);
+        assert_eq!(x.slider_multiplier, y.slider_multiplier, "{:?}", path);
+        assert_eq!(x.slider_tick_rate, y.slider_tick_rate, "{:?}", path);
+
+        assert_eq!(a.events.breaks, b.events.breaks, "{:?}", path);
+        assert_eq!(a.combo_colours, b.combo_colours, "{:?}", path);
+        assert_eq!(
+            a.slider_track_override, b.slider_track_override,
+            "{:?}",
+            path
+        );
+        assert_eq!(a.slider_border, b.slider_border, "{:?}", path);
+
+        assert_eq!(a.timing_points.len(), b.timing_points.len(), "{:?}", path);
+        for (x, y) in a.timing_points.iter().zip(&b.timing_points) {
+            let line = x.line;
+            assert!(same_f64(x.offset, y.offset), "{:?} line {}", path, line);
+            assert!(
+                same_f64(x.beat_length, y.beat_length),
+                "{:?} line {}",
+                path,
+                line
+            );
+            assert_eq!(
+                x.time_signature, y.time_signature,
+                "{:?} line {}",
+                path, line
+            );
+            assert_eq!(x.sample_set, y.sample_set, "{:?} line {}", path, line);
+            assert_eq!(
+                x.custom_sample_set, y.custom_sample_set,
+                "{:?} line {}",
+                path, line
+            );
+            assert_eq!(x.volume, y.volume, "{:?} line {}", path, line);
+            assert_eq!(x.timing_change, y.timing_change, "{:?} line {}", path, line);
+            assert_eq!(x.kiai, y.kiai, "{:?} line {}", path, line);
+            assert_eq!(
+                x.omit_first_barline, y.omit_first_barline,
+                "{:?} line {}",
+                path, line
+            );
+        }
+
+        assert_eq!(a.hit_objects.len(), b.hit_objects.len(), "{:?}", path);
+        for (x, y) in a.hit_objects.iter().zip(&b.hit_objects) {
+            let line = x.line;
+            assert!(x.object_type == y.object_type, "{:?} line {}", path, line);
+            assert_eq!(x.start, y.start, "{:?} line {}", path, line);
+            assert_eq!(x.end, y.end, "{:?} line {}", path, line);
+            assert_eq!(
+                x.unstacked_start_pos, y.unstacked_start_pos,
+                "{:?} line {}",
+                path, line
+            );
+            assert_eq!(x.start_pos, y.start_pos, "{:?} line {}", path, line);
+            assert_eq!(x.end_pos, y.end_pos, "{:?} line {}", path, line);
+            assert_eq!(x.flags, y.flags, "{:?} line {}", path, line);
+            assert_eq!(x.combo_index, y.combo_index, "{:?} line {}", path, line);
+            assert_eq!(x.combo_number, y.combo_number, "{:?} line {}", path, line);
+            assert_eq!(x.hitsound, y.hitsound, "{:?} line {}", path, line);
+            assert_eq!(x.hit_sample, y.hit_sample, "{:?} line {}", path, line);
+            assert_eq!(
+                x.has_hit_sample, y.has_hit_sample,
+                "{:?} line {}",
+                path, line
+            );
+
+            assert_eq!(x.slider_info.is_some(), y.slider_info.is_some());
+            if let (Some(x), Some(y)) = (&x.slider_info, &y.slider_info) {
+                assert_eq!(x.curve_type, y.curve_type, "{:?} line {}", path, line);
+                assert_eq!(
+                    x.control_points, y.control_points,
+                    "{:?} line {}",
+                    path, line
+                );
+                assert_eq!(x.slides, y.slides, "{:?} line {}", path, line);
+                assert!(
+                    same_f64(x.spatial_length, y.spatial_length),
+                    "{:?} line {}",
+                    path,
+                    line
+                );
+                assert_eq!(x.edge_sounds, y.edge_sounds, "{:?} line {}", path, line);
+                assert_eq!(x.edge_sets, y.edge_sets, "{:?} line {}", path, line);
+                assert_eq!(
+                    x.has_edge_hitsounds, y.has_edge_hitsounds,
+                    "{:?} line {}",
+                    path, line
+                );
+                assert_eq!(x.score_times, y.score_times, "{:?} line {}", path, line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for entry in std::fs::read_dir("test").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some(std::ffi::OsStr::new("osu")) {
+                continue;
+            }
+
+            let original = Beatmap::fixture(path.to_str().unwrap());
+            let written = write_to_string(&original);
+            let reparsed = Beatmap::parse(Default::default(), &mut written.as_bytes()).unwrap();
+
+            // writing again should give back the exact same file
+            assert_eq!(written, write_to_string(&reparsed), "{:?}", path);
+
+            // the same mistake twice would still give the same file, so check everything survived too
+            assert_same(&original, &reparsed, &path);
+        }
+    }
+
+    #[test]
+    fn test_write_edited() {
+        let mut beatmap = Beatmap::fixture("test/simple_slider.osu");
+        beatmap.difficulty.approach_rate = 9.3;
+        for x in &mut beatmap.hit_objects {
+            x.start += 500;
+        }
+        beatmap.timing_points[0].beat_length = 500.0;
+        beatmap.rebuild_timing_index();
+        assert_eq!(beatmap.timing.beat_length_at(1500.0, false), 500.0);
+
+        let written = write_to_string(&beatmap);
+        assert!(written.contains("ApproachRate:9.3\n"));
+        assert!(written
+            .contains("193,103,1500,2,0,B|62:157|110:262|261:337|452:151,1,466.666666666667\n"));
+
+        let reparsed = Beatmap::parse(Default::default(), &mut written.as_bytes()).unwrap();
+        assert_eq!(reparsed.difficulty.approach_rate, 9.3);
+        assert_eq!(reparsed.hit_objects[0].start, 1500);
+        assert_eq!(reparsed.timing.beat_length_at(1500.0, false), 500.0);
+        assert_eq!(
+            reparsed.timing.beat_length_at(1500.0, true),
+            beatmap.timing.beat_length_at(1500.0, true)
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    io::{self, Read},
+    marker::PhantomData,
+};
+
+// what a caller's error type needs for the reader to be able to fail with it
+pub trait BinaryReadErr: From<io::Error> {
+    fn invalid_string() -> Self;
+}
+
+// little endian reads for the binary formats (replays, the library database)
+// errors come out as the caller's own type so that they can keep their specific variants
+pub struct BinaryReader<R: Read, E: BinaryReadErr> {
+    inner: R,
+    _err: PhantomData<E>,
+}
+
+macro_rules! read_le {
+    ($name:ident, $type:ty) => {
+        pub fn $name(&mut self) -> Result<$type, E> {
+            let mut buf = [0; std::mem::size_of::<$type>()];
+            self.inner.read_exact(&mut buf)?;
+            Ok(<$type>::from_le_bytes(buf))
+        }
+    };
+}
+
+impl<R: Read, E: BinaryReadErr> BinaryReader<R, E> {
+    pub fn new(inner: R) -> Self {
+        BinaryReader {
+            inner,
+            _err: PhantomData,
+        }
+    }
+
+    // for anything that isn't a plain value, like compressed blocks
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    read_le!(read_u8, u8);
+    read_le!(read_u16, u16);
+    read_le!(read_u32, u32);
+    read_le!(read_i32, i32);
+    read_le!(read_u64, u64);
+    read_le!(read_i64, i64);
+    read_le!(read_f32, f32);
+    read_le!(read_f64, f64);
+
+    // u32 length prefixed utf-8
+    pub fn read_string(&mut self) -> Result<String, E> {
+        let len = self.read_u32()? as usize;
+        self.read_utf8(len)
+    }
+
+    // .net's BinaryWriter strings, with an extra byte in front saying if there's anything there
+    pub fn read_dotnet_string(&mut self) -> Result<String, E> {
+        match self.read_u8()? {
+            0x00 => Ok(String::new()),
+            0x0B => {
+                let mut len = 0usize;
+                let mut shift = 0;
+                loop {
+                    let byte = self.read_u8()?;
+                    len |= ((byte & 0x7F) as usize) << shift;
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                    shift += 7;
+                    if shift > 28 {
+                        return Err(E::invalid_string());
+                    }
+                }
+                self.read_utf8(len)
+            }
+            _ => Err(E::invalid_string()),
+        }
+    }
+
+    // doesn't trust the length enough to allocate all of it up front
+    fn read_utf8(&mut self, len: usize) -> Result<String, E> {
+        let mut buf = Vec::new();
+        (&mut self.inner).take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        String::from_utf8(buf).map_err(|_| E::invalid_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    enum TestErr {
+        Io(io::ErrorKind),
+        InvalidString,
+    }
+    impl From<io::Error> for TestErr {
+        fn from(x: io::Error) -> TestErr {
+            TestErr::Io(x.kind())
+        }
+    }
+    impl BinaryReadErr for TestErr {
+        fn invalid_string() -> TestErr {
+            TestErr::InvalidString
+        }
+    }
+
+    #[test]
+    fn test_read() {
+        let mut data = vec![0x2A];
+        data.extend((-2i32).to_le_bytes());
+        data.extend(1.5f64.to_le_bytes());
+        data.extend(3u32.to_le_bytes());
+        data.extend(b"abc");
+        data.extend([0x0B, 0x02]);
+        data.extend(b"hi");
+        data.push(0x00);
+
+        let mut file = BinaryReader::<_, TestErr>::new(data.as_slice());
+        assert_eq!(file.read_u8().unwrap(), 0x2A);
+        assert_eq!(file.read_i32().unwrap(), -2);
+        assert_eq!(file.read_f64().unwrap(), 1.5);
+        assert_eq!(file.read_string().unwrap(), "abc");
+        assert_eq!(file.read_dotnet_string().unwrap(), "hi");
+        assert_eq!(file.read_dotnet_string().unwrap(), "");
+        assert!(matches!(
+            file.read_u16(),
+            Err(TestErr::Io(io::ErrorKind::UnexpectedEof))
+        ));
+
+        // a length that runs off the end, and bytes that aren't utf-8
+        let data = [0x0B, 0x05, b'a'];
+        let mut file = BinaryReader::<_, TestErr>::new(&data[..]);
+        assert!(matches!(
+            file.read_dotnet_string(),
+            Err(TestErr::Io(io::ErrorKind::UnexpectedEof))
+        ));
+        let data = [0x0B, 0x01, 0xFF];
+        let mut file = BinaryReader::<_, TestErr>::new(&data[..]);
+        assert!(matches!(
+            file.read_dotnet_string(),
+            Err(TestErr::InvalidString)
+        ));
+        let mut file = BinaryReader::<_, TestErr>::new(&[0x07][..]);
+        assert!(matches!(
+            file.read_dotnet_string(),
+            Err(TestErr::InvalidString)
+        ));
+    }
+}
@@ -5,13 +5,18 @@ use log::warn;
 use crate::math;
 use crate::math::{Line, Vector2};
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CurveType {
     Catmull,
     Bezier,
     Linear,
     PerfectCircle,
 }
+impl Default for CurveType {
+    fn default() -> Self {
+        CurveType::Catmull
+    }
+}
 
 #[derive(Default)]
 pub struct Curve {
@@ -117,6 +122,24 @@ impl Curve {
         line.point_at((target_length - self.line_lengths[target_idx]) / line.length())
     }
 
+    // the path between two points along it, for drawing a slider that's only partly snaked in or out
+    // just the one point if there's nothing in between
+    pub fn points_between(&self, from: f32, to: f32) -> Vec<Vector2> {
+        if self.lines.is_empty() {
+            return Vec::new();
+        }
+        if self.length == 0.0 || from >= to {
+            return vec![self.point_at(from)];
+        }
+        let (first, _) = self.line_at(from);
+        let (last, _) = self.line_at(to);
+        let mut out = Vec::with_capacity(last - first + 2);
+        out.push(self.point_at(from));
+        out.extend(self.lines[(first + 1)..=last].iter().map(|x| x.p1));
+        out.push(self.point_at(to));
+        out
+    }
+
     pub fn angle_at(&self, amount: f32) -> f32 {
         if self.length == 0.0 || self.lines.is_empty() {
             return 0.0;
@@ -309,6 +332,45 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_points_between() {
+        use crate::curve::*;
+
+        let curve = Curve::new(
+            CurveType::Linear,
+            vec![
+                Vector2::new(0.0, 0.0),
+                Vector2::new(100.0, 0.0),
+                Vector2::new(100.0, 100.0),
+            ],
+            14,
+            200.0,
+        );
+        assert_eq!(
+            curve.points_between(0.0, 1.0),
+            &[
+                Vector2::new(0.0, 0.0),
+                Vector2::new(100.0, 0.0),
+                Vector2::new(100.0, 100.0)
+            ]
+        );
+        // snaked out past the corner
+        assert_eq!(
+            curve.points_between(0.75, 1.0),
+            &[Vector2::new(100.0, 50.0), Vector2::new(100.0, 100.0)]
+        );
+        assert_eq!(
+            curve.points_between(0.25, 0.75),
+            &[
+                Vector2::new(50.0, 0.0),
+                Vector2::new(100.0, 0.0),
+                Vector2::new(100.0, 50.0)
+            ]
+        );
+        assert_eq!(curve.points_between(0.5, 0.5).len(), 1);
+        assert!(Curve::default().points_between(0.0, 1.0).is_empty());
+    }
+
     #[test]
     fn test_bezier() {
         // TODO: generate test case that uses specified pixel length
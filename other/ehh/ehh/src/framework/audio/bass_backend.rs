@@ -0,0 +1,126 @@
+use std::rc::Rc;
+
+use crate::framework::bass::{Bass, BassChannelCommon, BassMixer, BassSample, BassStream};
+
+use super::{AudioBackend, AudioSample, AudioTrack};
+
+// everything goes through one mixer so that hitsounds and the song stay in sync
+pub struct BassBackend {
+    bass: Rc<Bass>,
+    mixer: Rc<BassMixer>,
+}
+
+impl BassBackend {
+    pub fn new(bass: Rc<Bass>) -> Result<BassBackend, String> {
+        let mixer = Rc::new(
+            bass.create_mixer(
+                44100,
+                2,
+                bassmix_sys::BASS_MIXER_NONSTOP | bass_sys::BASS_SAMPLE_FLOAT,
+            )
+            .map_err(|e| format!("Failed to create mixer: {}", e))?,
+        );
+        mixer.set_attrib(bass_sys::BASS_ATTRIB_BUFFER, 0.0);
+        let device = bass
+            .get_device()
+            .map_err(|e| format!("Failed to get the current device: {}", e))?;
+        mixer
+            .set_device(device)
+            .map_err(|e| format!("Failed to set mixer device: {}", e))?;
+        mixer
+            .play(false)
+            .map_err(|e| format!("Failed to start the mixer: {}", e))?;
+
+        Ok(BassBackend { bass, mixer })
+    }
+}
+
+impl AudioBackend for BassBackend {
+    fn create_track(&self, data: Vec<u8>) -> Result<Rc<dyn AudioTrack>, String> {
+        let stream = self
+            .bass
+            .create_stream_from_memory(
+                data,
+                bass_sys::BASS_STREAM_DECODE | bass_sys::BASS_STREAM_PRESCAN,
+            )
+            .map_err(|e| format!("Failed to load audio track: {}", e))?;
+        self.mixer
+            .add_channel(stream.clone(), bassmix_sys::BASS_MIXER_CHAN_PAUSE)
+            .map_err(|e| format!("Failed to add audio track to the mixer: {}", e))?;
+        let base_freq = stream
+            .get_attrib(bass_sys::BASS_ATTRIB_FREQ)
+            .unwrap_or(44100.0);
+
+        Ok(Rc::new(BassTrack {
+            mixer: self.mixer.clone(),
+            stream,
+            base_freq,
+        }))
+    }
+
+    fn create_sample(&self, data: &[u8], max_channels: u32) -> Result<Rc<dyn AudioSample>, String> {
+        self.bass
+            .create_sample_from_memory(Some(self.mixer.clone()), data, max_channels, 0)
+            .map(|x| x as Rc<dyn AudioSample>)
+            .map_err(|e| format!("Failed to load sample: {}", e))
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.mixer.set_attrib(bass_sys::BASS_ATTRIB_VOL, volume);
+    }
+
+    fn output_latency(&self) -> f64 {
+        15.0 // offset used by osu for wasapi backend
+    }
+}
+
+pub struct BassTrack {
+    mixer: Rc<BassMixer>,
+    stream: Rc<BassStream>,
+    base_freq: f32,
+}
+
+impl AudioTrack for BassTrack {
+    fn play(&self) {
+        self.mixer.resume_channel(self.stream.clone());
+    }
+
+    fn pause(&self) {
+        self.mixer.pause_channel(self.stream.clone());
+    }
+
+    fn is_playing(&self) -> bool {
+        self.stream.get_mixer_is_active()
+    }
+
+    fn position(&self) -> f64 {
+        self.stream.get_mixer_position()
+    }
+
+    fn seek(&self, pos: f64) -> bool {
+        self.stream.set_mixer_position(pos)
+    }
+
+    fn length(&self) -> f64 {
+        self.stream.get_length()
+    }
+
+    // this speeds up the sample rate, so the pitch goes up with it like in the software backend
+    // keeping the pitch would need a bass_fx tempo stream
+    fn set_rate(&self, rate: f64) -> bool {
+        // the mixer resamples the stream, so positions are still in song time
+        self.stream
+            .set_attrib(bass_sys::BASS_ATTRIB_FREQ, self.base_freq * rate as f32)
+            .is_some()
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.stream.set_attrib(bass_sys::BASS_ATTRIB_VOL, volume);
+    }
+}
+
+impl AudioSample for BassSample {
+    fn play(&self, pan: f32, volume: f32) {
+        self.play_mixer(pan, volume);
+    }
+}
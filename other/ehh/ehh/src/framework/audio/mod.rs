@@ -0,0 +1,50 @@
+#[cfg(feature = "bass")]
+mod bass_backend;
+mod software;
+
+#[cfg(feature = "bass")]
+pub use bass_backend::*;
+pub use software::*;
+
+use std::rc::Rc;
+
+use super::clock::InstantClock;
+
+// everything the game needs from an audio library
+// positions and lengths are in ms of song time, no matter what the rate is
+pub trait AudioBackend {
+    // the whole file is handed over since some backends keep reading from it while playing
+    fn create_track(&self, data: Vec<u8>) -> Result<Rc<dyn AudioTrack>, String>;
+    // max_channels is how many copies of the sample can play over each other
+    fn create_sample(&self, data: &[u8], max_channels: u32) -> Result<Rc<dyn AudioSample>, String>;
+
+    fn set_volume(&self, volume: f32);
+    // how late sound actually comes out of the speakers
+    fn output_latency(&self) -> f64 {
+        0.0
+    }
+    // what keeps time while the track isn't playing
+    fn create_clock(&self) -> InstantClock {
+        InstantClock::new(false)
+    }
+}
+
+// the song, starts out paused
+pub trait AudioTrack {
+    fn play(&self);
+    fn pause(&self);
+    fn is_playing(&self) -> bool;
+
+    fn position(&self) -> f64;
+    fn seek(&self, pos: f64) -> bool; // fails outside of the track, but still seeks as close as it can
+    fn length(&self) -> f64;
+
+    // changes pitch too for now, like nightcore/daycore
+    fn set_rate(&self, rate: f64) -> bool;
+    fn set_volume(&self, volume: f32);
+}
+
+pub trait AudioSample {
+    // pan goes from -1.0 (left) to 1.0 (right)
+    fn play(&self, pan: f32, volume: f32);
+}
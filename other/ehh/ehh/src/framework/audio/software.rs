@@ -0,0 +1,438 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{BufWriter, Cursor},
+    rc::{Rc, Weak},
+};
+
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error as SymphoniaError,
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+use crate::framework::clock::InstantClock;
+
+use super::{AudioBackend, AudioSample, AudioTrack};
+
+// everything gets decoded and mixed at this rate, in stereo
+pub const SOFTWARE_SAMPLE_RATE: u32 = 44100;
+
+// where mixed audio ends up
+pub enum AudioSink {
+    Null,
+    Wav(hound::WavWriter<BufWriter<File>>),
+}
+
+struct TrackState {
+    data: Rc<[f32]>, // interleaved stereo
+    pos: f64,        // in frames
+    playing: bool,
+    rate: f64,
+    volume: f32,
+}
+
+impl TrackState {
+    fn frames(&self) -> usize {
+        self.data.len() / 2
+    }
+}
+
+struct Voice {
+    data: Rc<[f32]>,
+    pos: usize,
+    left: f32,
+    right: f32,
+}
+
+struct Mixer {
+    tracks: Vec<Weak<RefCell<TrackState>>>,
+    voices: Vec<Voice>,
+    volume: f32,
+    sink: AudioSink,
+    pending_frames: f64, // fractions of a frame that didn't get mixed yet
+    frames_mixed: u64,
+}
+
+impl Mixer {
+    fn mix_frame(&mut self) -> (f32, f32) {
+        let (mut left, mut right) = (0.0, 0.0);
+
+        for track in self.tracks.iter().filter_map(|x| x.upgrade()) {
+            let mut track = track.borrow_mut();
+            if !track.playing || track.pos >= track.frames() as f64 {
+                continue;
+            }
+            // linear interpolation is fine, this isn't meant to sound amazing
+            let idx = track.pos as usize;
+            let next = (idx + 1).min(track.frames() - 1);
+            let frac = (track.pos - idx as f64) as f32;
+            left +=
+                (track.data[idx * 2] * (1.0 - frac) + track.data[next * 2] * frac) * track.volume;
+            right += (track.data[idx * 2 + 1] * (1.0 - frac) + track.data[next * 2 + 1] * frac)
+                * track.volume;
+            track.pos += track.rate;
+        }
+
+        for voice in &mut self.voices {
+            left += voice.data[voice.pos * 2] * voice.left;
+            right += voice.data[voice.pos * 2 + 1] * voice.right;
+            voice.pos += 1;
+        }
+        self.voices.retain(|x| x.pos * 2 < x.data.len());
+
+        (left * self.volume, right * self.volume)
+    }
+}
+
+// pure rust, doesn't need a sound device
+// nothing moves until mix() gets called, so it runs exactly as fast as whoever is driving it
+pub struct SoftwareBackend {
+    mixer: Rc<RefCell<Mixer>>,
+}
+
+impl SoftwareBackend {
+    pub fn new(sink: AudioSink) -> SoftwareBackend {
+        SoftwareBackend {
+            mixer: Rc::new(RefCell::new(Mixer {
+                tracks: Vec::new(),
+                voices: Vec::new(),
+                volume: 1.0,
+                sink,
+                pending_frames: 0.0,
+                frames_mixed: 0,
+            })),
+        }
+    }
+
+    pub fn new_wav(path: &str) -> Result<SoftwareBackend, String> {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: SOFTWARE_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec)
+            .map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        Ok(SoftwareBackend::new(AudioSink::Wav(writer)))
+    }
+
+    // renders the next ms of audio into the sink
+    pub fn mix(&self, ms: f64) -> Result<(), String> {
+        let mut mixer = self.mixer.borrow_mut();
+        mixer.tracks.retain(|x| x.strong_count() > 0);
+
+        mixer.pending_frames += ms * SOFTWARE_SAMPLE_RATE as f64 / 1000.0;
+        let frames = mixer.pending_frames.floor();
+        mixer.pending_frames -= frames;
+        mixer.frames_mixed += frames as u64;
+
+        for _ in 0..frames as u64 {
+            let (left, right) = mixer.mix_frame();
+            if let AudioSink::Wav(writer) = &mut mixer.sink {
+                for x in [left, right] {
+                    writer
+                        .write_sample((x.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+                        .map_err(|e| format!("Failed to write audio: {}", e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // fixes up the wav header, dropping the backend does the same thing but ignores errors
+    pub fn finish(&self) -> Result<(), String> {
+        let sink = std::mem::replace(&mut self.mixer.borrow_mut().sink, AudioSink::Null);
+        match sink {
+            AudioSink::Wav(writer) => writer
+                .finalize()
+                .map_err(|e| format!("Failed to finish writing audio: {}", e)),
+            AudioSink::Null => Ok(()),
+        }
+    }
+}
+
+impl AudioBackend for SoftwareBackend {
+    fn create_track(&self, data: Vec<u8>) -> Result<Rc<dyn AudioTrack>, String> {
+        let track = Rc::new(RefCell::new(TrackState {
+            data: decode(data)?.into(),
+            pos: 0.0,
+            playing: false,
+            rate: 1.0,
+            volume: 1.0,
+        }));
+        self.mixer.borrow_mut().tracks.push(Rc::downgrade(&track));
+        Ok(Rc::new(SoftwareTrack(track)))
+    }
+
+    fn create_sample(&self, data: &[u8], max_channels: u32) -> Result<Rc<dyn AudioSample>, String> {
+        if max_channels == 0 {
+            return Err("Samples need at least one channel".to_string());
+        }
+        Ok(Rc::new(SoftwareSample {
+            mixer: Rc::downgrade(&self.mixer),
+            data: decode(data.to_vec())?.into(),
+            max_channels: max_channels as usize,
+        }))
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.mixer.borrow_mut().volume = volume;
+    }
+
+    // time only passes as audio gets mixed
+    fn create_clock(&self) -> InstantClock {
+        let mixer = Rc::downgrade(&self.mixer);
+        InstantClock::with_time_source(
+            false,
+            Box::new(move || {
+                mixer.upgrade().map_or(0.0, |x| {
+                    x.borrow().frames_mixed as f64 * 1000.0 / SOFTWARE_SAMPLE_RATE as f64
+                })
+            }),
+        )
+    }
+}
+
+pub struct SoftwareTrack(Rc<RefCell<TrackState>>);
+
+impl AudioTrack for SoftwareTrack {
+    fn play(&self) {
+        self.0.borrow_mut().playing = true;
+    }
+
+    fn pause(&self) {
+        self.0.borrow_mut().playing = false;
+    }
+
+    fn is_playing(&self) -> bool {
+        let track = self.0.borrow();
+        track.playing && track.pos < track.frames() as f64
+    }
+
+    fn position(&self) -> f64 {
+        self.0.borrow().pos * 1000.0 / SOFTWARE_SAMPLE_RATE as f64
+    }
+
+    fn seek(&self, pos: f64) -> bool {
+        let mut track = self.0.borrow_mut();
+        let frames = track.frames() as f64;
+        let target = pos * SOFTWARE_SAMPLE_RATE as f64 / 1000.0;
+        track.pos = target.clamp(0.0, frames);
+        (0.0..=frames).contains(&target)
+    }
+
+    fn length(&self) -> f64 {
+        self.0.borrow().frames() as f64 * 1000.0 / SOFTWARE_SAMPLE_RATE as f64
+    }
+
+    fn set_rate(&self, rate: f64) -> bool {
+        self.0.borrow_mut().rate = rate;
+        true
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.0.borrow_mut().volume = volume;
+    }
+}
+
+pub struct SoftwareSample {
+    mixer: Weak<RefCell<Mixer>>,
+    data: Rc<[f32]>,
+    max_channels: usize,
+}
+
+impl AudioSample for SoftwareSample {
+    fn play(&self, pan: f32, volume: f32) {
+        let mixer = match self.mixer.upgrade() {
+            Some(x) => x,
+            None => return,
+        };
+        if self.data.is_empty() {
+            return;
+        }
+        let mut mixer = mixer.borrow_mut();
+
+        // same as bass, the copy that was played the longest ago gets cut off
+        let playing: Vec<usize> = (0..mixer.voices.len())
+            .filter(|&i| Rc::ptr_eq(&mixer.voices[i].data, &self.data))
+            .collect();
+        if playing.len() >= self.max_channels {
+            mixer.voices.remove(playing[0]);
+        }
+
+        // bass pans by turning down the other side
+        let pan = pan.clamp(-1.0, 1.0);
+        mixer.voices.push(Voice {
+            data: self.data.clone(),
+            pos: 0,
+            left: volume * (1.0 - pan).min(1.0),
+            right: volume * (1.0 + pan).min(1.0),
+        });
+    }
+}
+
+// decodes wav, ogg or mp3 into interleaved stereo at SOFTWARE_SAMPLE_RATE
+// mp3 padding doesn't get trimmed, osu configures bass the same way
+fn decode(data: Vec<u8>) -> Result<Vec<f32>, String> {
+    let stream = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &Hint::new(),
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| format!("Unsupported audio file: {}", e))?;
+    let mut format = probed.format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| "Audio file has no tracks".to_string())?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| "Audio file has no sample rate".to_string())?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("Unsupported audio codec: {}", e))?;
+
+    let mut out = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(x) => x,
+            // this is how symphonia says that the file ended
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(format!("Failed to read audio: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(x) => x,
+            // broken frames get skipped instead of failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Failed to decode audio: {}", e)),
+        };
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        samples.copy_interleaved_ref(decoded);
+        for frame in samples.samples().chunks_exact(channels) {
+            out.push(frame[0]);
+            out.push(if channels > 1 { frame[1] } else { frame[0] });
+        }
+    }
+
+    Ok(resample(&out, sample_rate, SOFTWARE_SAMPLE_RATE))
+}
+
+fn resample(data: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || data.len() < 4 {
+        return data.to_vec();
+    }
+
+    let in_frames = data.len() / 2;
+    let out_frames = (in_frames as u64 * to as u64 / from as u64) as usize;
+    let step = from as f64 / to as f64;
+    let mut out = Vec::with_capacity(out_frames * 2);
+    for i in 0..out_frames {
+        let pos = i as f64 * step;
+        let idx = (pos as usize).min(in_frames - 1);
+        let next = (idx + 1).min(in_frames - 1);
+        let frac = (pos - idx as f64) as f32;
+        for channel in 0..2 {
+            out.push(data[idx * 2 + channel] * (1.0 - frac) + data[next * 2 + channel] * frac);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a second of a 440hz sine wave
+    fn sine_wav(sample_rate: u32) -> Vec<u8> {
+        let mut out = Cursor::new(Vec::new());
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::new(&mut out, spec).unwrap();
+        for i in 0..sample_rate {
+            let x = (i as f32 / sample_rate as f32 * 440.0 * std::f32::consts::TAU).sin();
+            writer.write_sample((x * 16384.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_track() {
+        let backend = SoftwareBackend::new(AudioSink::Null);
+        let track = backend.create_track(sine_wav(22050)).unwrap();
+        assert!((track.length() - 1000.0).abs() < 1.0);
+
+        // paused until told otherwise
+        backend.mix(100.0).unwrap();
+        assert_eq!(track.position(), 0.0);
+
+        track.play();
+        backend.mix(250.0).unwrap();
+        assert!((track.position() - 250.0).abs() < 0.1);
+
+        // positions stay in song time
+        track.set_rate(1.5);
+        backend.mix(100.0).unwrap();
+        assert!((track.position() - 400.0).abs() < 0.1);
+
+        assert!(!track.seek(-100.0));
+        assert_eq!(track.position(), 0.0);
+        assert!(track.seek(990.0));
+        backend.mix(100.0).unwrap();
+        assert!(!track.is_playing());
+    }
+
+    #[test]
+    fn test_clock() {
+        use crate::framework::clock::Clock;
+
+        let backend = SoftwareBackend::new(AudioSink::Null);
+        let mut clock = backend.create_clock();
+        clock.start();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        clock.update();
+        assert_eq!(clock.get_time(), 0.0);
+
+        backend.mix(500.0).unwrap();
+        clock.update();
+        assert!((clock.get_time() - 500.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_wav_output() {
+        let path = std::env::temp_dir().join("ehh_test_wav_output.wav");
+        let path = path.to_str().unwrap();
+
+        let backend = SoftwareBackend::new_wav(path).unwrap();
+        assert!(backend.create_sample(&sine_wav(44100), 0).is_err());
+        let sample = backend.create_sample(&sine_wav(44100), 2).unwrap();
+        sample.play(-1.0, 1.0);
+        backend.mix(500.0).unwrap();
+        backend.finish().unwrap();
+
+        let mut reader = hound::WavReader::open(path).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.duration(), 22050);
+        let samples: Vec<i16> = reader.samples::<i16>().map(|x| x.unwrap()).collect();
+        // panned all the way left
+        assert!(samples.iter().step_by(2).any(|x| x.abs() > 10000));
+        assert!(samples.iter().skip(1).step_by(2).all(|&x| x == 0));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -37,6 +37,14 @@ pub trait BassChannelCommon {
         ) && pos >= 0.0
     }
 
+    fn get_length(&self) -> f64 {
+        let bytes = bass_sys::BASS_ChannelGetLength(self.get_handle(), bass_sys::BASS_POS_BYTE);
+        if bytes == u64::MAX {
+            return 0.0;
+        }
+        bass_sys::BASS_ChannelBytes2Seconds(self.get_handle(), bytes) * 1000.0
+    }
+
     fn get_mixer_is_active(&self) -> bool {
         // TODO: is this correct?
         bassmix_sys::BASS_Mixer_ChannelIsActive(self.get_handle()) == bass_sys::BASS_ACTIVE_PLAYING
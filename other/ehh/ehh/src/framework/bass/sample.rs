@@ -10,8 +10,6 @@ impl Bass {
         max_chans: u32,
         flags: u32,
     ) -> Result<Rc<BassSample>, i32> {
-        assert!(max_chans > 0);
-
         let mut encoded: Vec<u16> = path.encode_utf16().collect();
         encoded.push(0);
         let handle = bass_sys::BASS_SampleLoad(
@@ -22,6 +20,36 @@ impl Bass {
             max_chans,
             flags | bass_sys::BASS_UNICODE,
         );
+        self.create_sample_internal(mixer, handle, max_chans)
+    }
+
+    // unlike streams, bass makes its own copy of the data here
+    pub fn create_sample_from_memory(
+        &self,
+        mixer: Option<Rc<BassMixer>>,
+        data: &[u8],
+        max_chans: u32,
+        flags: u32,
+    ) -> Result<Rc<BassSample>, i32> {
+        let handle = bass_sys::BASS_SampleLoad(
+            1,
+            data.as_ptr() as *const c_void,
+            0,
+            data.len() as u32,
+            max_chans,
+            flags,
+        );
+        self.create_sample_internal(mixer, handle, max_chans)
+    }
+
+    fn create_sample_internal(
+        &self,
+        mixer: Option<Rc<BassMixer>>,
+        handle: bass_sys::HSAMPLE,
+        max_chans: u32,
+    ) -> Result<Rc<BassSample>, i32> {
+        assert!(max_chans > 0);
+
         if handle != 0 {
             // BASS_SAMCHAN_STREAM doesn't respect max channels, so its functionality has to be imitated
             let mixer_data = mixer.map(|mixer| {
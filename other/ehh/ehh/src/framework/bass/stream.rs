@@ -17,6 +17,32 @@ impl Bass {
             Ok(Rc::new(BassStream {
                 bassdrop: self.bassdrop.clone(),
                 handle,
+                _data: None,
+            }))
+        } else {
+            Err(bass_sys::BASS_ErrorGetCode())
+        }
+    }
+
+    pub fn create_stream_from_memory(
+        &self,
+        data: Vec<u8>,
+        flags: u32,
+    ) -> Result<Rc<BassStream>, i32> {
+        // bass reads from the buffer for the stream's entire lifetime, so the stream has to own it
+        let data = data.into_boxed_slice();
+        let handle = bass_sys::BASS_StreamCreateFile(
+            1,
+            data.as_ptr() as *const c_void,
+            0,
+            data.len() as u64,
+            flags,
+        );
+        if handle != 0 {
+            Ok(Rc::new(BassStream {
+                bassdrop: self.bassdrop.clone(),
+                handle,
+                _data: Some(data),
             }))
         } else {
             Err(bass_sys::BASS_ErrorGetCode())
@@ -27,6 +53,7 @@ impl Bass {
 pub struct BassStream {
     pub(super) bassdrop: Rc<BassDrop>,
     pub(super) handle: bass_sys::HSTREAM,
+    _data: Option<Box<[u8]>>,
 }
 
 impl BassChannelCommon for BassStream {
@@ -1,6 +1,6 @@
 use std::{rc::Rc, time::Instant};
 
-use super::bass::{BassChannelCommon, BassMixer, BassStream};
+use super::audio::AudioTrack;
 
 pub trait Clock {
     fn update(&mut self);
@@ -11,30 +11,41 @@ pub trait Clock {
     fn is_running(&self) -> bool;
     fn get_time(&self) -> f64;
     fn get_rate(&self) -> f64;
+    fn set_rate(&mut self, rate: f64);
     fn get_elapsed_frame_time(&self) -> f64;
 }
 
-// TODO: rate accumulation once i get around to that...
 pub struct InstantClock {
-    instant: Instant,
-    time: f64, // user-facing, updated per-frame
+    now: Box<dyn Fn() -> f64>, // ms since some point, only ever goes forward
+    time: f64,                 // user-facing, updated per-frame
     start: f64,
     elapsed: f64,
     seek_offset: f64,
     running: bool,
     last_frame_time: f64,
+    rate: f64,
 }
 
 impl InstantClock {
     pub fn new(start: bool) -> InstantClock {
+        let instant = Instant::now();
+        InstantClock::with_time_source(
+            start,
+            Box::new(move || instant.elapsed().as_secs_f64() * 1000.0),
+        )
+    }
+
+    // for anything that isn't real time, like an offline mixer
+    pub fn with_time_source(start: bool, now: Box<dyn Fn() -> f64>) -> InstantClock {
         let mut ret = InstantClock {
-            instant: Instant::now(),
+            now,
             time: 0.0,
             start: 0.0,
             elapsed: 0.0,
             seek_offset: 0.0,
             running: false,
             last_frame_time: 0.0,
+            rate: 1.0,
         };
         if start {
             ret.start();
@@ -50,21 +61,21 @@ impl Clock for InstantClock {
 
         let mut elapsed = self.elapsed;
         if self.running {
-            elapsed += self.instant.elapsed().as_secs_f64() * 1000.0 - self.start;
+            elapsed += ((self.now)() - self.start) * self.rate;
         }
         self.time = elapsed;
     }
 
     fn start(&mut self) {
         if !self.running {
-            self.start = self.instant.elapsed().as_secs_f64() * 1000.0;
+            self.start = (self.now)();
             self.running = true;
         }
     }
 
     fn pause(&mut self) {
         if self.running {
-            self.elapsed += self.instant.elapsed().as_secs_f64() * 1000.0 - self.start;
+            self.elapsed += ((self.now)() - self.start) * self.rate;
             self.running = false;
         }
     }
@@ -83,7 +94,87 @@ impl Clock for InstantClock {
     }
 
     fn get_rate(&self) -> f64 {
-        1.0
+        self.rate
+    }
+
+    fn set_rate(&mut self, rate: f64) {
+        // everything up until now was at the old rate
+        if self.running {
+            self.pause();
+            self.rate = rate;
+            self.start();
+        } else {
+            self.rate = rate;
+        }
+    }
+
+    fn get_elapsed_frame_time(&self) -> f64 {
+        self.time - self.last_frame_time
+    }
+}
+
+// only moves when told to, so the same inputs always end up at the same times
+pub struct ManualClock {
+    time: f64,
+    pending: f64, // real time that passed since the last update
+    running: bool,
+    last_frame_time: f64,
+    rate: f64,
+}
+
+impl ManualClock {
+    pub fn new(time: f64) -> ManualClock {
+        ManualClock {
+            time,
+            pending: 0.0,
+            running: false,
+            last_frame_time: time,
+            rate: 1.0,
+        }
+    }
+
+    // gets applied on the next update, same as real time passing
+    pub fn advance(&mut self, ms: f64) {
+        self.pending += ms;
+    }
+}
+
+impl Clock for ManualClock {
+    fn update(&mut self) {
+        self.last_frame_time = self.time;
+        if self.running {
+            self.time += self.pending * self.rate;
+        }
+        self.pending = 0.0;
+    }
+
+    fn start(&mut self) {
+        self.running = true;
+    }
+
+    fn pause(&mut self) {
+        self.running = false;
+    }
+
+    fn seek(&mut self, pos: f64) -> bool {
+        self.time = pos;
+        true
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    fn get_rate(&self) -> f64 {
+        self.rate
+    }
+
+    fn set_rate(&mut self, rate: f64) {
+        self.rate = rate;
     }
 
     fn get_elapsed_frame_time(&self) -> f64 {
@@ -135,50 +226,55 @@ impl Clock for OffsetClock {
         self.inner.get_rate()
     }
 
+    fn set_rate(&mut self, rate: f64) {
+        self.inner.set_rate(rate);
+    }
+
     fn get_elapsed_frame_time(&self) -> f64 {
         self.inner.get_elapsed_frame_time()
     }
 }
 
+// follows wherever the audio backend says the track is
 // doesn't seem to require any interpolation?
-pub struct BassStreamClock {
-    mixer: Rc<BassMixer>,
-    stream: Rc<BassStream>,
+pub struct TrackClock {
+    track: Rc<dyn AudioTrack>,
     time: f64,
     last_frame_time: f64,
+    rate: f64,
 }
 
-impl BassStreamClock {
-    pub fn new(mixer: Rc<BassMixer>, stream: Rc<BassStream>) -> BassStreamClock {
-        BassStreamClock {
-            mixer,
-            stream,
+impl TrackClock {
+    pub fn new(track: Rc<dyn AudioTrack>) -> TrackClock {
+        TrackClock {
+            track,
             time: 0.0,
             last_frame_time: 0.0,
+            rate: 1.0,
         }
     }
 }
 
-impl Clock for BassStreamClock {
+impl Clock for TrackClock {
     fn update(&mut self) {
         self.last_frame_time = self.time;
-        self.time = self.stream.get_mixer_position();
+        self.time = self.track.position();
     }
 
     fn start(&mut self) {
-        self.mixer.resume_channel(self.stream.clone());
+        self.track.play();
     }
 
     fn pause(&mut self) {
-        self.mixer.pause_channel(self.stream.clone());
+        self.track.pause();
     }
 
     fn seek(&mut self, pos: f64) -> bool {
-        self.stream.set_mixer_position(pos)
+        self.track.seek(pos)
     }
 
     fn is_running(&self) -> bool {
-        self.stream.get_mixer_is_active()
+        self.track.is_playing()
     }
 
     fn get_time(&self) -> f64 {
@@ -186,8 +282,16 @@ impl Clock for BassStreamClock {
     }
 
     fn get_rate(&self) -> f64 {
-        // TODO: need bassfx for rate changing
-        1.0
+        self.rate
+    }
+
+    fn set_rate(&mut self, rate: f64) {
+        // the old rate still matches the track if this fails
+        if self.track.set_rate(rate) {
+            self.rate = rate;
+        } else {
+            log::warn!("Failed to set the track rate to {}x", rate);
+        }
     }
 
     fn get_elapsed_frame_time(&self) -> f64 {
@@ -205,10 +309,11 @@ pub struct DecoupledClock {
 }
 
 impl DecoupledClock {
-    pub fn new(source_clock: Box<dyn Clock>) -> DecoupledClock {
+    // decoupled_clock keeps time whenever the source can't
+    pub fn new(source_clock: Box<dyn Clock>, decoupled_clock: InstantClock) -> DecoupledClock {
         DecoupledClock {
             source_clock,
-            decoupled_clock: InstantClock::new(false),
+            decoupled_clock,
             current_time: 0.0,
             elapsed_frame_time: 0.0,
         }
@@ -289,7 +394,12 @@ impl Clock for DecoupledClock {
     }
 
     fn get_rate(&self) -> f64 {
-        1.0
+        self.decoupled_clock.get_rate()
+    }
+
+    fn set_rate(&mut self, rate: f64) {
+        self.source_clock.set_rate(rate);
+        self.decoupled_clock.set_rate(rate);
     }
 
     fn get_elapsed_frame_time(&self) -> f64 {
@@ -1,3 +1,5 @@
+pub mod audio;
+#[cfg(feature = "bass")]
 pub mod bass;
 pub mod clock;
 pub mod render;
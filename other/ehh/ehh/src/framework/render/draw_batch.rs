@@ -101,7 +101,7 @@ impl DrawBatch {
         let scale = scale / tex.dpi_scale;
         let offset = match origin {
             Origin::Center => Vector2::new(0.0, 0.0),
-            Origin::TopLeft => Vector2::new(-tex.width / 2.0, -tex.height / 2.0),
+            Origin::TopLeft => Vector2::new(tex.width / 2.0, tex.height / 2.0),
         } * scale;
         self.queue.push(DrawBatchCommand {
             tex,
@@ -0,0 +1,106 @@
+use gl::types::*;
+
+use super::util::vertical_flip_texture;
+
+// offscreen RGBA target, for rendering without showing anything
+// the colour is a texture so it can be drawn back onto something else
+pub struct Framebuffer {
+    id: GLuint,
+    color: GLuint,
+    depth: GLuint, // slider bodies need it
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.id);
+            gl::DeleteTextures(1, &self.color);
+            gl::DeleteRenderbuffers(1, &self.depth);
+        }
+    }
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Result<Framebuffer, String> {
+        unsafe {
+            let mut color: GLuint = 0;
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut color);
+            gl::TextureParameteri(color, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TextureParameteri(color, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TextureStorage2D(color, 1, gl::RGBA8, width as i32, height as i32);
+
+            let mut depth: GLuint = 0;
+            gl::CreateRenderbuffers(1, &mut depth);
+            gl::NamedRenderbufferStorage(depth, gl::DEPTH_COMPONENT24, width as i32, height as i32);
+
+            let mut id: GLuint = 0;
+            gl::CreateFramebuffers(1, &mut id);
+            gl::NamedFramebufferTexture(id, gl::COLOR_ATTACHMENT0, color, 0);
+            gl::NamedFramebufferRenderbuffer(id, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, depth);
+
+            // dropping it here cleans everything up
+            let fb = Framebuffer {
+                id,
+                color,
+                depth,
+                width,
+                height,
+            };
+            let status = gl::CheckNamedFramebufferStatus(id, gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(format!("Framebuffer is incomplete: {:x}", status));
+            }
+            Ok(fb)
+        }
+    }
+
+    // everything drawn after this ends up in here instead of the window
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(0, 0, self.width as i32, self.height as i32);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    // transparent, with the depth all the way at the back
+    pub fn clear(&self) {
+        unsafe {
+            gl::ClearNamedFramebufferfv(self.id, gl::COLOR, 0, [0.0f32; 4].as_ptr());
+            gl::ClearNamedFramebufferfv(self.id, gl::DEPTH, 0, &1.0);
+        }
+    }
+
+    pub fn bind_color_texture(&self, unit: u32) {
+        unsafe {
+            gl::BindTextureUnit(unit, self.color);
+        }
+    }
+
+    // RGBA, top row first like every image format expects
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let mut out = vec![0; (self.width * self.height * 4) as usize];
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                self.width as i32,
+                self.height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                out.as_mut_ptr() as *mut _,
+            );
+        }
+        vertical_flip_texture(&mut out, self.width as usize, self.height as usize, 4);
+        out
+    }
+}
@@ -1,7 +1,9 @@
 pub mod draw_batch;
 pub mod element_buffer;
+pub mod framebuffer;
 pub mod instance;
 pub mod shader;
+pub mod slider_renderer;
 pub mod text_renderer;
 pub mod texture;
 pub mod util;
@@ -12,8 +14,10 @@ pub mod vertex_storage;
 
 pub use draw_batch::*;
 pub use element_buffer::*;
+pub use framebuffer::*;
 pub use instance::*;
 pub use shader::*;
+pub use slider_renderer::*;
 pub use text_renderer::*;
 pub use texture::*;
 pub use util::*;
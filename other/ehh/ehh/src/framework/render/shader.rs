@@ -151,6 +151,26 @@ impl ShaderProgram {
         }
     }
 
+    pub fn set_uniform_f32(&self, name: &str, data: f32) {
+        if let Some(location) = self.get_uniform_location(name) {
+            unsafe {
+                gl::Uniform1f(location, data);
+            }
+        } else {
+            panic!("Couldn't find uniform named {}", name);
+        }
+    }
+
+    pub fn set_uniform_4f(&self, name: &str, data: [f32; 4]) {
+        if let Some(location) = self.get_uniform_location(name) {
+            unsafe {
+                gl::Uniform4f(location, data[0], data[1], data[2], data[3]);
+            }
+        } else {
+            panic!("Couldn't find uniform named {}", name);
+        }
+    }
+
     pub fn set_uniform_matrix_4fv(&self, name: &str, data: cgmath::Matrix4<f32>) {
         if let Some(location) = self.get_uniform_location(name) {
             unsafe {
@@ -0,0 +1,413 @@
+use std::{collections::HashMap, f32::consts::PI};
+
+use cgmath::Matrix4;
+
+use crate::math::Vector2;
+
+use super::{Framebuffer, Shader, ShaderProgram, Vertex, VertexArray, VertexBuffer};
+
+// how much of the radius is border, same as stable
+pub const SLIDER_BORDER_WIDTH: f32 = 0.128;
+
+// stable's track is a bit see-through, the border isn't
+const SLIDER_BODY_ALPHA: f32 = 0.7;
+
+// the most a round part of the track can turn in one triangle
+const MAX_WEDGE_ANGLE: f32 = PI / 16.0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SliderVertex {
+    pub position: (f32, f32),
+    pub dist: f32, // 0 in the middle of the track, 1 at the outside of the border
+}
+
+impl Vertex for SliderVertex {
+    fn setup_vertex_attrib(vao: &VertexArray, vbo: &VertexBuffer) {
+        unsafe {
+            vbo.bind();
+            vao.bind();
+
+            let stride = std::mem::size_of::<SliderVertex>() as i32;
+
+            // position
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+
+            // dist
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(
+                1,
+                1,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (2 * std::mem::size_of::<f32>()) as *const _,
+            );
+        }
+    }
+}
+
+struct SliderDrawCommand {
+    key: usize,
+    border: [f32; 4],
+    inner: [f32; 4],
+    outer: [f32; 4],
+    alpha: f32,
+}
+
+// a body's triangles stay on the gpu for as long as it keeps getting drawn with the same range
+struct CachedBody {
+    range: (f32, f32),
+    vao: VertexArray,
+    vbo: VertexBuffer,
+    count: usize,
+    used: bool,
+}
+
+// slider bodies can't go through DrawBatch since they aren't sprites
+// each one gets drawn fully opaque into its own target first, then blended on with its alpha,
+// so wherever the track overlaps itself it doesn't get darker
+pub struct SliderRenderer {
+    program: ShaderProgram,
+    composite: ShaderProgram,
+    composite_vao: VertexArray, // nothing in it, but something has to be bound
+    target: Option<Framebuffer>,
+    radius: f32,
+    cache: HashMap<usize, CachedBody>,
+    queue: Vec<SliderDrawCommand>,
+}
+
+impl SliderRenderer {
+    pub fn new(proj: Matrix4<f32>, radius: f32) -> SliderRenderer {
+        let vert = Shader::from_string(
+            include_str!("../../../assets/shaders/slider.vert"),
+            gl::VERTEX_SHADER,
+        )
+        .unwrap();
+        let frag = Shader::from_string(
+            include_str!("../../../assets/shaders/slider.frag"),
+            gl::FRAGMENT_SHADER,
+        )
+        .unwrap();
+        let program = ShaderProgram::new(&[&vert, &frag]).unwrap();
+
+        program.bind();
+        program.set_uniform_matrix_4fv("proj", proj);
+        program.set_uniform_f32("border_width", SLIDER_BORDER_WIDTH);
+
+        let vert = Shader::from_string(
+            include_str!("../../../assets/shaders/slider_composite.vert"),
+            gl::VERTEX_SHADER,
+        )
+        .unwrap();
+        let frag = Shader::from_string(
+            include_str!("../../../assets/shaders/slider_composite.frag"),
+            gl::FRAGMENT_SHADER,
+        )
+        .unwrap();
+        let composite = ShaderProgram::new(&[&vert, &frag]).unwrap();
+
+        composite.bind();
+        composite.set_uniform_i32("body", 0);
+
+        SliderRenderer {
+            program,
+            composite,
+            composite_vao: VertexArray::new(),
+            target: None,
+            radius,
+            cache: HashMap::new(),
+            queue: Vec::new(),
+        }
+    }
+
+    // bodies are drawn in the order they're added, so the one on top goes last
+    // key is anything that stays the same for the slider, range is how much of it is snaked in (see Curve::points_between)
+    // points only gets called when the body has to be tessellated again
+    // colours are BGR, alpha is for the whole body
+    pub fn add(
+        &mut self,
+        key: usize,
+        range: (f32, f32),
+        track: u32,
+        border: u32,
+        alpha: f32,
+        points: impl FnOnce() -> Vec<Vector2>,
+    ) {
+        if alpha <= 0.0 {
+            return;
+        }
+
+        let radius = self.radius;
+        let tessellate = || {
+            let mut vertices = Vec::new();
+            tessellate_track(&points(), radius, &mut vertices);
+            vertices
+        };
+        match self.cache.get_mut(&key) {
+            Some(body) if body.range == range => {}
+            Some(body) => {
+                let vertices = tessellate();
+                body.vbo.set_data(&vertices, gl::DYNAMIC_DRAW);
+                body.count = vertices.len();
+                body.range = range;
+            }
+            None => {
+                let vertices = tessellate();
+                let vbo = VertexBuffer::from_data(&vertices, gl::DYNAMIC_DRAW);
+                let vao = VertexArray::new();
+                SliderVertex::setup_vertex_attrib(&vao, &vbo);
+                self.cache.insert(
+                    key,
+                    CachedBody {
+                        range,
+                        vao,
+                        vbo,
+                        count: vertices.len(),
+                        used: false,
+                    },
+                );
+            }
+        }
+        let body = self.cache.get_mut(&key).unwrap();
+        body.used = true;
+        if body.count == 0 {
+            return;
+        }
+
+        let track = colour_to_vec4(track);
+        self.queue.push(SliderDrawCommand {
+            key,
+            border: colour_to_vec4(border),
+            inner: lighten(track, 0.5),
+            outer: darken(track, 0.1),
+            alpha: alpha.min(1.0),
+        });
+    }
+
+    // draws everything added since the last call, this can happen more than once a frame
+    pub fn draw(&mut self) {
+        if self.queue.is_empty() {
+            return;
+        }
+
+        // draws into whatever's bound right now (the window or a render's framebuffer), so put that back after each body
+        // target.bind() sets its own viewport, so that gets saved too
+        let mut viewport = [0i32; 4];
+        let mut previous = 0i32;
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous);
+        }
+        let width = (viewport[0] + viewport[2]).max(1) as u32;
+        let height = (viewport[1] + viewport[3]).max(1) as u32;
+        if !self
+            .target
+            .as_ref()
+            .is_some_and(|x| x.width == width && x.height == height)
+        {
+            self.target = Some(Framebuffer::new(width, height).unwrap());
+        }
+        let target = self.target.as_ref().unwrap();
+
+        for x in self.queue.drain(..) {
+            let body = &self.cache[&x.key];
+
+            target.bind();
+            target.clear();
+            unsafe {
+                gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+                gl::Disable(gl::BLEND);
+                gl::Enable(gl::DEPTH_TEST);
+                gl::DepthFunc(gl::LESS);
+            }
+            self.program.bind();
+            self.program.set_uniform_4f("border_color", x.border);
+            self.program.set_uniform_4f("inner_color", x.inner);
+            self.program.set_uniform_4f("outer_color", x.outer);
+            body.vao.bind();
+            unsafe {
+                gl::DrawArrays(gl::TRIANGLES, 0, body.count as i32);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, previous as u32);
+                gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Enable(gl::BLEND);
+            }
+            self.composite.bind();
+            self.composite.set_uniform_f32("alpha", x.alpha);
+            target.bind_color_texture(0);
+            self.composite_vao.bind();
+            unsafe {
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            }
+        }
+    }
+
+    // once everything's been drawn, anything that wasn't added this frame has gone off screen
+    pub fn finish_frame(&mut self) {
+        self.cache.retain(|_, x| std::mem::take(&mut x.used));
+    }
+}
+
+fn colour_to_vec4(colour: u32) -> [f32; 4] {
+    [
+        (colour & 0xFF) as f32 / 255.0,
+        ((colour >> 8) & 0xFF) as f32 / 255.0,
+        ((colour >> 16) & 0xFF) as f32 / 255.0,
+        1.0,
+    ]
+}
+
+// both of these are what lazer does to get stable's gradient
+fn lighten(colour: [f32; 4], amount: f32) -> [f32; 4] {
+    let channel = |x: f32| (x * (1.0 + 0.5 * amount) + amount).min(1.0);
+    [
+        channel(colour[0]),
+        channel(colour[1]),
+        channel(colour[2]),
+        SLIDER_BODY_ALPHA,
+    ]
+}
+
+fn darken(colour: [f32; 4], amount: f32) -> [f32; 4] {
+    let scale = 1.0 + amount.max(0.0);
+    [
+        colour[0] / scale,
+        colour[1] / scale,
+        colour[2] / scale,
+        SLIDER_BODY_ALPHA,
+    ]
+}
+
+// triangles between the middle of the track and its edge, turning from one angle to another
+fn add_wedge(center: Vector2, start: f32, sweep: f32, radius: f32, out: &mut Vec<SliderVertex>) {
+    let steps = (sweep.abs() / MAX_WEDGE_ANGLE).ceil().max(1.0) as usize;
+    let rim = |i: usize| {
+        let angle = start + sweep * i as f32 / steps as f32;
+        SliderVertex {
+            position: (center + Vector2::new(angle.cos(), angle.sin()) * radius).into(),
+            dist: 1.0,
+        }
+    };
+    let middle = SliderVertex {
+        position: center.into(),
+        dist: 0.0,
+    };
+    for i in 0..steps {
+        out.extend([middle, rim(i), rim(i + 1)]);
+    }
+}
+
+// turns a path into triangles, with round ends and joins
+// the outside of each join is filled in, the inside overlaps and gets sorted out by the depth test
+pub fn tessellate_track(points: &[Vector2], radius: f32, out: &mut Vec<SliderVertex>) {
+    let mut path: Vec<Vector2> = Vec::with_capacity(points.len());
+    for &x in points {
+        if !path.last().is_some_and(|last| last.distance(x) <= 0.01) {
+            path.push(x);
+        }
+    }
+
+    let Some(&first) = path.first() else {
+        return;
+    };
+    if path.len() == 1 {
+        add_wedge(first, 0.0, PI * 2.0, radius, out);
+        return;
+    }
+
+    let angles: Vec<f32> = path
+        .windows(2)
+        .map(|x| (x[1].y - x[0].y).atan2(x[1].x - x[0].x))
+        .collect();
+
+    for (x, &angle) in path.windows(2).zip(&angles) {
+        let normal = Vector2::new(-angle.sin(), angle.cos()) * radius;
+        let vertex = |position: Vector2, dist: f32| SliderVertex {
+            position: position.into(),
+            dist,
+        };
+        for side in [normal, normal * -1.0] {
+            let (a, b) = (vertex(x[0], 0.0), vertex(x[1], 0.0));
+            let (c, d) = (vertex(x[0] + side, 1.0), vertex(x[1] + side, 1.0));
+            out.extend([a, b, c, c, b, d]);
+        }
+    }
+
+    // joins only need the outside of the turn
+    for (i, x) in angles.windows(2).enumerate() {
+        let turn = (x[1] - x[0] + PI).rem_euclid(PI * 2.0) - PI;
+        if turn != 0.0 {
+            add_wedge(
+                path[i + 1],
+                x[0] - turn.signum() * PI / 2.0,
+                turn,
+                radius,
+                out,
+            );
+        }
+    }
+
+    // round caps facing away from the track
+    let last = *path.last().unwrap();
+    add_wedge(first, angles[0] + PI / 2.0, PI, radius, out);
+    add_wedge(last, angles[angles.len() - 1] - PI / 2.0, PI, radius, out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tessellate_track() {
+        let mut out = Vec::new();
+        tessellate_track(&[], 10.0, &mut out);
+        assert!(out.is_empty());
+
+        // a single point (or a zero length slider) is just a circle
+        tessellate_track(
+            &[Vector2::new(5.0, 5.0), Vector2::new(5.0, 5.0)],
+            10.0,
+            &mut out,
+        );
+        assert_eq!(out.len(), 32 * 3);
+        for x in &out {
+            let pos = Vector2::new(x.position.0, x.position.1);
+            let expected = if x.dist == 0.0 { 0.0 } else { 10.0 };
+            assert!((pos.distance(Vector2::new(5.0, 5.0)) - expected).abs() < 0.001);
+        }
+
+        // straight line: two quads and two half circles
+        out.clear();
+        tessellate_track(
+            &[Vector2::new(0.0, 0.0), Vector2::new(100.0, 0.0)],
+            10.0,
+            &mut out,
+        );
+        assert_eq!(out.len(), 2 * 6 + 2 * 16 * 3);
+        assert!(out.iter().all(|x| x.dist == 0.0 || x.dist == 1.0));
+        let min_x = out.iter().map(|x| x.position.0).fold(f32::MAX, f32::min);
+        let max_y = out.iter().map(|x| x.position.1).fold(f32::MIN, f32::max);
+        assert!((min_x + 10.0).abs() < 0.001);
+        assert!((max_y - 10.0).abs() < 0.001);
+
+        // a right angle adds a quarter circle on the outside of the corner
+        out.clear();
+        tessellate_track(
+            &[
+                Vector2::new(0.0, 0.0),
+                Vector2::new(100.0, 0.0),
+                Vector2::new(100.0, 100.0),
+            ],
+            10.0,
+            &mut out,
+        );
+        assert_eq!(out.len(), 4 * 6 + 8 * 3 + 2 * 16 * 3);
+        let corner = &out[4 * 6..4 * 6 + 8 * 3];
+        assert!(corner
+            .iter()
+            .all(|x| x.position.0 >= 100.0 - 0.001 && x.position.1 <= 0.001));
+    }
+}
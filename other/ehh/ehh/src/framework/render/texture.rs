@@ -287,6 +287,23 @@ pub struct TextureRegion {
     pub height: f32,
 }
 
+impl TextureRegion {
+    // a 1x1 white texture on its own, for drawing plain rectangles when there's no atlas around
+    pub fn white() -> Rc<TextureRegion> {
+        Rc::new(TextureRegion {
+            tex: Rc::new(
+                Texture2DArray::from_memory(&[0xFF; 4], 1, 1, gl::RGBA, false)
+                    .expect("Failed to create texture"),
+            ),
+            layer: 0,
+            uvs: [Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0)],
+            dpi_scale: 1.0,
+            width: 1.0,
+            height: 1.0,
+        })
+    }
+}
+
 // TODO: okay this sucks ass
 #[derive(Clone, Copy)]
 pub struct TextureRegionWithoutTexture {
@@ -28,6 +28,20 @@ impl VertexBuffer {
         }
     }
 
+    // replaces everything in the buffer, for data that changes every frame
+    pub fn set_data<T>(&mut self, data: &[T], usage: GLenum) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(data) as isize,
+                data.as_ptr() as *const c_void,
+                usage,
+            );
+        }
+        self.size = data.len();
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
@@ -6,9 +6,14 @@ pub mod app;
 pub mod framework;
 
 pub mod beatmap;
+pub mod binary_reader;
 pub mod curve;
+pub mod json;
+pub mod library;
 pub mod math;
 pub mod num_util;
+pub mod rating;
+pub mod replay;
 
 pub use beatmap::Beatmap;
 pub use beatmap::BeatmapParseErr;
@@ -0,0 +1,489 @@
+use std::{
+    any::Any,
+    io::{self, Write},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    beatmap::{Beatmap, BeatmapParseErr, Gamemode, Mods, ParseDiagnostic, ParseMode},
+    json::json_string,
+};
+
+use super::*;
+
+const MODES: [Gamemode; 4] = [
+    Gamemode::Osu,
+    Gamemode::Taiko,
+    Gamemode::CatchTheBeat,
+    Gamemode::Mania,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseStatus {
+    Parsed,
+    Skipped, // unsupported mode
+    Failed,
+    Unreadable, // couldn't even get the file out of the folder or archive
+}
+
+impl ParseStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParseStatus::Parsed => "parsed",
+            ParseStatus::Skipped => "skipped",
+            ParseStatus::Failed => "failed",
+            ParseStatus::Unreadable => "unreadable",
+        }
+    }
+}
+
+// one difficulty, or a whole file if it couldn't be opened
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub path: String,
+    pub difficulty: String, // empty if the file couldn't be opened
+    pub status: ParseStatus,
+    pub error_category: Option<&'static str>,
+    pub error: Option<String>,
+    pub mode: Option<Gamemode>,
+    pub circles: usize,
+    pub sliders: usize,
+    pub spinners: usize,
+    pub holds: usize,
+    pub diagnostics: Vec<ParseDiagnostic>,
+    pub parse_time: Duration,
+    pub stars: Option<f64>,
+    pub pp: Option<f64>,       // for an SS
+    pub problems: Vec<String>, // anything the caller found on top of parsing, like failing to round-trip
+}
+
+impl FileReport {
+    fn new(path: &str, difficulty: &str, status: ParseStatus) -> FileReport {
+        FileReport {
+            path: path.to_string(),
+            difficulty: difficulty.to_string(),
+            status,
+            error_category: None,
+            error: None,
+            mode: None,
+            circles: 0,
+            sliders: 0,
+            spinners: 0,
+            holds: 0,
+            diagnostics: Vec::new(),
+            parse_time: Duration::ZERO,
+            stars: None,
+            pp: None,
+            problems: Vec::new(),
+        }
+    }
+
+    fn failed(path: &str, difficulty: &str, err: &BeatmapParseErr) -> FileReport {
+        let status = match err {
+            BeatmapParseErr::UnsupportedMode => ParseStatus::Skipped,
+            BeatmapParseErr::IoError(_) => ParseStatus::Unreadable,
+            _ => ParseStatus::Failed,
+        };
+        FileReport {
+            error_category: Some(err.category()),
+            error: Some(err.to_string()),
+            ..FileReport::new(path, difficulty, status)
+        }
+    }
+
+    fn panicked(path: &str, difficulty: &str, payload: &(dyn Any + Send)) -> FileReport {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|x| x.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        FileReport {
+            error_category: Some("panic"),
+            error: Some(format!("panicked: {}", message)),
+            ..FileReport::new(path, difficulty, ParseStatus::Failed)
+        }
+    }
+}
+
+pub struct BatchReport {
+    pub files: Vec<FileReport>, // sorted by path, then difficulty
+    pub elapsed: Duration,
+    pub threads: usize,
+}
+
+// every difficulty under a folder, parsed on as many threads as asked for
+// each gets passed to the callback on whatever thread parsed it, so it can add its own checks to the report
+pub fn batch_parse<F>(
+    path: impl AsRef<Path>,
+    mode: ParseMode,
+    threads: usize,
+    each: F,
+) -> BatchReport
+where
+    F: Fn(&Beatmap, &mut FileReport) + Sync,
+{
+    let now = Instant::now();
+    let root = path.as_ref().to_string_lossy().to_string();
+    let mut files = Vec::new();
+    let mut found = Vec::new();
+    for x in walk_songs(&path) {
+        match x {
+            Ok(x) => found.push(x),
+            Err(e) => {
+                let err = BeatmapParseErr::IoError(e);
+                files.push(FileReport::failed(&root, "", &err));
+            }
+        }
+    }
+
+    let threads = threads.clamp(1, found.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(files);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                let mut done = Vec::new();
+                while let Some(x) = found.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    parse_found(x, mode, &each, &mut done);
+                }
+                results.lock().unwrap().extend(done);
+            });
+        }
+    });
+
+    let mut files = results.into_inner().unwrap();
+    files.sort_by(|a, b| (&a.path, &a.difficulty).cmp(&(&b.path, &b.difficulty)));
+    BatchReport {
+        files,
+        elapsed: now.elapsed(),
+        threads,
+    }
+}
+
+fn parse_found<F>(found: &FoundBeatmaps, mode: ParseMode, each: &F, out: &mut Vec<FileReport>)
+where
+    F: Fn(&Beatmap, &mut FileReport),
+{
+    let path = found.path.to_string_lossy().to_string();
+    let (set, difficulties) = match found.open() {
+        Ok(x) => x,
+        Err(e) => {
+            out.push(FileReport::failed(&path, "", &BeatmapParseErr::IoError(e)));
+            return;
+        }
+    };
+
+    for x in difficulties {
+        let now = Instant::now();
+        let mut parse_time = None;
+        // a panic in the parser (or the callback) only loses this difficulty, not the whole thread's results
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let parsed = set.parse_difficulty_with_mode(&x, Mods::empty(), mode);
+            parse_time = Some(now.elapsed());
+            match parsed {
+                Ok(beatmap) => {
+                    let mut report = FileReport {
+                        mode: Some(beatmap.mode),
+                        circles: beatmap.circle_count,
+                        sliders: beatmap.slider_count,
+                        spinners: beatmap.spinner_count,
+                        holds: beatmap.hold_count,
+                        diagnostics: beatmap.diagnostics.clone(),
+                        ..FileReport::new(&path, &x, ParseStatus::Parsed)
+                    };
+                    each(&beatmap, &mut report);
+                    report
+                }
+                Err(e) => FileReport::failed(&path, &x, &e),
+            }
+        }));
+        let mut report = result.unwrap_or_else(|e| FileReport::panicked(&path, &x, &*e));
+        report.parse_time = parse_time.unwrap_or_else(|| now.elapsed());
+        out.push(report);
+    }
+}
+
+fn csv_field(source: &str) -> String {
+    if source.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", source.replace('"', "\"\""))
+    } else {
+        source.to_string()
+    }
+}
+
+fn json_option(source: Option<String>) -> String {
+    source.unwrap_or_else(|| "null".to_string())
+}
+
+fn json_number(source: Option<f64>) -> String {
+    json_option(source.filter(|x| x.is_finite()).map(|x| x.to_string()))
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl BatchReport {
+    pub fn count(&self, status: ParseStatus) -> usize {
+        self.files.iter().filter(|x| x.status == status).count()
+    }
+
+    // most common first
+    pub fn error_histogram(&self) -> Vec<(&'static str, usize)> {
+        let mut out: Vec<(&'static str, usize)> = Vec::new();
+        for category in self.files.iter().filter_map(|x| x.error_category) {
+            match out.iter_mut().find(|x| x.0 == category) {
+                Some(x) => x.1 += 1,
+                None => out.push((category, 1)),
+            }
+        }
+        out.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        out
+    }
+
+    pub fn mode_counts(&self) -> Vec<(Gamemode, usize)> {
+        MODES
+            .iter()
+            .map(|&mode| {
+                let count = self.files.iter().filter(|x| x.mode == Some(mode)).count();
+                (mode, count)
+            })
+            .collect()
+    }
+
+    // circles, sliders, spinners and holds out of everything that parsed
+    pub fn object_totals(&self) -> (usize, usize, usize, usize) {
+        self.files.iter().fold((0, 0, 0, 0), |acc, x| {
+            (
+                acc.0 + x.circles,
+                acc.1 + x.sliders,
+                acc.2 + x.spinners,
+                acc.3 + x.holds,
+            )
+        })
+    }
+
+    pub fn slowest(&self, count: usize) -> Vec<&FileReport> {
+        let mut out: Vec<&FileReport> = self.files.iter().collect();
+        out.sort_by_key(|x| std::cmp::Reverse(x.parse_time));
+        out.truncate(count);
+        out
+    }
+
+    pub fn write_json(&self, out: &mut impl Write) -> io::Result<()> {
+        let (circles, sliders, spinners, holds) = self.object_totals();
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"elapsed_ms\": {},", millis(self.elapsed))?;
+        writeln!(out, "  \"threads\": {},", self.threads)?;
+        writeln!(
+            out,
+            "  \"totals\": {{\"files\": {}, \"parsed\": {}, \"skipped\": {}, \"failed\": {}, \"unreadable\": {}, \"circles\": {}, \"sliders\": {}, \"spinners\": {}, \"holds\": {}}},",
+            self.files.len(),
+            self.count(ParseStatus::Parsed),
+            self.count(ParseStatus::Skipped),
+            self.count(ParseStatus::Failed),
+            self.count(ParseStatus::Unreadable),
+            circles,
+            sliders,
+            spinners,
+            holds
+        )?;
+
+        let errors: Vec<String> = self
+            .error_histogram()
+            .iter()
+            .map(|(category, count)| format!("{}: {}", json_string(category), count))
+            .collect();
+        writeln!(out, "  \"errors\": {{{}}},", errors.join(", "))?;
+        let modes: Vec<String> = self
+            .mode_counts()
+            .iter()
+            .map(|(mode, count)| format!("{}: {}", json_string(&format!("{:?}", mode)), count))
+            .collect();
+        writeln!(out, "  \"modes\": {{{}}},", modes.join(", "))?;
+
+        writeln!(out, "  \"files\": [")?;
+        for (i, x) in self.files.iter().enumerate() {
+            let diagnostics: Vec<String> = x.diagnostics.iter().map(|x| x.to_json()).collect();
+            let problems: Vec<String> = x.problems.iter().map(|x| json_string(x)).collect();
+            write!(
+                out,
+                "    {{\"path\": {}, \"difficulty\": {}, \"status\": \"{}\", \"error_category\": {}, \"error\": {}, \"mode\": {}, \"circles\": {}, \"sliders\": {}, \"spinners\": {}, \"holds\": {}, \"parse_ms\": {}, \"stars\": {}, \"pp\": {}, \"diagnostics\": [{}], \"problems\": [{}]}}",
+                json_string(&x.path),
+                json_string(&x.difficulty),
+                x.status.name(),
+                json_option(x.error_category.map(json_string)),
+                json_option(x.error.as_deref().map(json_string)),
+                json_option(x.mode.map(|x| json_string(&format!("{:?}", x)))),
+                x.circles,
+                x.sliders,
+                x.spinners,
+                x.holds,
+                millis(x.parse_time),
+                json_number(x.stars),
+                json_number(x.pp),
+                diagnostics.join(", "),
+                problems.join(", ")
+            )?;
+            writeln!(out, "{}", if i + 1 < self.files.len() { "," } else { "" })?;
+        }
+        writeln!(out, "  ]")?;
+        writeln!(out, "}}")
+    }
+
+    // one row per file, the summary can be worked out from these
+    pub fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "path,difficulty,status,error_category,error,mode,circles,sliders,spinners,holds,diagnostics,parse_ms,stars,pp,problems")?;
+        let number = |x: Option<f64>| x.map(|x| format!("{:.4}", x)).unwrap_or_default();
+        for x in &self.files {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{},{},{:.3},{},{},{}",
+                csv_field(&x.path),
+                csv_field(&x.difficulty),
+                x.status.name(),
+                x.error_category.unwrap_or_default(),
+                csv_field(x.error.as_deref().unwrap_or_default()),
+                x.mode.map(|x| format!("{:?}", x)).unwrap_or_default(),
+                x.circles,
+                x.sliders,
+                x.spinners,
+                x.holds,
+                x.diagnostics.len(),
+                millis(x.parse_time),
+                number(x.stars),
+                number(x.pp),
+                csv_field(&x.problems.join("; "))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fresh folder for one test, so tests running at the same time never share one
+    fn temp_dir(test: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("ehh_{}_{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_batch_parse() {
+        let report = batch_parse("test", ParseMode::Lenient, 4, |beatmap, report| {
+            if beatmap.version.is_empty() {
+                report.problems.push("no version".to_string());
+            }
+        });
+        assert!(report.threads >= 1 && report.threads <= 4);
+        assert!(report.count(ParseStatus::Parsed) > 0);
+        assert_eq!(report.count(ParseStatus::Failed), 0);
+
+        // the same as parsing them one at a time
+        let single = batch_parse("test", ParseMode::Lenient, 1, |_, _| {});
+        assert_eq!(single.threads, 1);
+        let names = |x: &BatchReport| {
+            x.files
+                .iter()
+                .map(|x| (x.path.clone(), x.difficulty.clone(), x.circles))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&report), names(&single));
+
+        let jumps = report
+            .files
+            .iter()
+            .find(|x| x.difficulty == "jumps.osu")
+            .unwrap();
+        assert_eq!(jumps.mode, Some(Gamemode::Osu));
+        assert!(jumps.circles > 0);
+        let (circles, ..) = report.object_totals();
+        assert!(circles >= jumps.circles);
+        let modes: usize = report.mode_counts().iter().map(|x| x.1).sum();
+        assert_eq!(modes, report.count(ParseStatus::Parsed));
+        assert_eq!(report.slowest(3).len(), 3);
+        assert!(report.slowest(2)[0].parse_time >= report.slowest(2)[1].parse_time);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap().lines().count(),
+            report.files.len() + 1
+        );
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with('{') && json.trim_end().ends_with('}'));
+        assert!(json.contains("\"difficulty\": \"jumps.osu\""));
+    }
+
+    #[test]
+    fn test_batch_parse_unreadable() {
+        let dir = temp_dir("test_batch_parse_unreadable");
+        std::fs::write(dir.join("broken.osz"), b"not a zip").unwrap();
+        std::fs::copy("test/jumps.osu", dir.join("jumps.osu")).unwrap();
+
+        let report = batch_parse(&dir, ParseMode::Strict, 2, |_, _| {});
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.files.len(), 2);
+        assert_eq!(report.count(ParseStatus::Unreadable), 1);
+        assert_eq!(report.count(ParseStatus::Parsed), 1);
+        assert_eq!(report.error_histogram(), &[("io", 1)]);
+        assert_eq!(
+            csv_field("a \"quoted\", value"),
+            "\"a \"\"quoted\"\", value\""
+        );
+    }
+
+    #[test]
+    fn test_batch_parse_panic() {
+        let dir = temp_dir("test_batch_parse_panic");
+        std::fs::copy("test/jumps.osu", dir.join("jumps.osu")).unwrap();
+        let mut broken = std::fs::read_to_string("test/jumps.osu").unwrap();
+        broken.push_str("\n256,192,abc,1,0\n");
+        std::fs::write(dir.join("lenient.osu"), broken).unwrap();
+
+        let report = batch_parse(&dir, ParseMode::Lenient, 2, |_, report| {
+            if report.difficulty == "jumps.osu" {
+                panic!("callback blew up");
+            }
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // the other file still gets its report
+        assert_eq!(report.files.len(), 2);
+        assert_eq!(report.count(ParseStatus::Failed), 1);
+        assert_eq!(report.count(ParseStatus::Parsed), 1);
+        assert_eq!(report.error_histogram(), &[("panic", 1)]);
+        let panicked = &report.files[0];
+        assert_eq!(panicked.difficulty, "jumps.osu");
+        assert!(panicked
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("callback blew up"));
+
+        let lenient = &report.files[1];
+        assert_eq!(lenient.diagnostics.len(), 1);
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains(&format!(
+            "\"diagnostics\": [{}]",
+            lenient.diagnostics[0].to_json()
+        )));
+        assert!(json.contains("\"text\":\"256,192,abc,1,0\""));
+    }
+}
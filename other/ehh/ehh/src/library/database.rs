@@ -0,0 +1,164 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    beatmap::Gamemode,
+    binary_reader::{BinaryReadErr, BinaryReader},
+};
+
+use super::*;
+
+// bump this whenever the layout changes, old databases just get rescanned from scratch
+const DATABASE_MAGIC: &[u8; 4] = b"EHDB";
+const DATABASE_VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum LibraryReadErr {
+    IoError(io::Error),
+    InvalidMagic,
+    OldVersion(u32),
+    InvalidString,
+    InvalidMode,
+}
+impl From<io::Error> for LibraryReadErr {
+    fn from(x: io::Error) -> LibraryReadErr {
+        LibraryReadErr::IoError(x)
+    }
+}
+
+impl BinaryReadErr for LibraryReadErr {
+    fn invalid_string() -> LibraryReadErr {
+        LibraryReadErr::InvalidString
+    }
+}
+
+// everything is little endian
+type Reader<R> = BinaryReader<R, LibraryReadErr>;
+
+fn write_string(out: &mut impl Write, source: &str) -> io::Result<()> {
+    out.write_all(&(source.len() as u32).to_le_bytes())?;
+    out.write_all(source.as_bytes())
+}
+
+fn read_entry(file: &mut Reader<impl Read>) -> Result<LibraryEntry, LibraryReadErr> {
+    Ok(LibraryEntry {
+        path: file.read_string()?,
+        filename: file.read_string()?,
+        modified: file.read_u64()?,
+        artist: file.read_string()?,
+        romanized_artist: file.read_string()?,
+        title: file.read_string()?,
+        romanized_title: file.read_string()?,
+        creator: file.read_string()?,
+        version: file.read_string()?,
+        source: file.read_string()?,
+        tags: file.read_string()?,
+        beatmap_id: file.read_i32()?,
+        beatmap_set_id: file.read_i32()?,
+        mode: match file.read_u8()? {
+            0 => Gamemode::Osu,
+            1 => Gamemode::Taiko,
+            2 => Gamemode::CatchTheBeat,
+            3 => Gamemode::Mania,
+            _ => return Err(LibraryReadErr::InvalidMode),
+        },
+        approach_rate: file.read_f32()?,
+        circle_size: file.read_f32()?,
+        hp_drain: file.read_f32()?,
+        overall_difficulty: file.read_f32()?,
+        length: file.read_i32()?,
+        min_bpm: file.read_f64()?,
+        max_bpm: file.read_f64()?,
+        circle_count: file.read_u32()? as usize,
+        slider_count: file.read_u32()? as usize,
+        spinner_count: file.read_u32()? as usize,
+        hold_count: file.read_u32()? as usize,
+        max_combo: file.read_u32()? as usize,
+        stars: match file.read_u8()? {
+            0 => None,
+            _ => Some(file.read_f64()?),
+        },
+    })
+}
+
+fn write_entry(out: &mut impl Write, entry: &LibraryEntry) -> io::Result<()> {
+    write_string(out, &entry.path)?;
+    write_string(out, &entry.filename)?;
+    out.write_all(&entry.modified.to_le_bytes())?;
+    write_string(out, &entry.artist)?;
+    write_string(out, &entry.romanized_artist)?;
+    write_string(out, &entry.title)?;
+    write_string(out, &entry.romanized_title)?;
+    write_string(out, &entry.creator)?;
+    write_string(out, &entry.version)?;
+    write_string(out, &entry.source)?;
+    write_string(out, &entry.tags)?;
+    out.write_all(&entry.beatmap_id.to_le_bytes())?;
+    out.write_all(&entry.beatmap_set_id.to_le_bytes())?;
+    out.write_all(&[entry.mode as u8])?;
+    out.write_all(&entry.approach_rate.to_le_bytes())?;
+    out.write_all(&entry.circle_size.to_le_bytes())?;
+    out.write_all(&entry.hp_drain.to_le_bytes())?;
+    out.write_all(&entry.overall_difficulty.to_le_bytes())?;
+    out.write_all(&entry.length.to_le_bytes())?;
+    out.write_all(&entry.min_bpm.to_le_bytes())?;
+    out.write_all(&entry.max_bpm.to_le_bytes())?;
+    for x in [
+        entry.circle_count,
+        entry.slider_count,
+        entry.spinner_count,
+        entry.hold_count,
+        entry.max_combo,
+    ] {
+        out.write_all(&(x as u32).to_le_bytes())?;
+    }
+    match entry.stars {
+        Some(stars) => {
+            out.write_all(&[1])?;
+            out.write_all(&stars.to_le_bytes())
+        }
+        None => out.write_all(&[0]),
+    }
+}
+
+impl Library {
+    pub fn read(file: &mut impl Read) -> Result<Library, LibraryReadErr> {
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        let mut file = Reader::new(file);
+        if &magic != DATABASE_MAGIC {
+            return Err(LibraryReadErr::InvalidMagic);
+        }
+        let version = file.read_u32()?;
+        if version != DATABASE_VERSION {
+            return Err(LibraryReadErr::OldVersion(version));
+        }
+
+        let count = file.read_u32()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            entries.push(read_entry(&mut file)?);
+        }
+        let count = file.read_u32()?;
+        let mut failed = HashMap::new();
+        for _ in 0..count {
+            let path = file.read_string()?;
+            failed.insert(path, file.read_u64()?);
+        }
+        Ok(Library { entries, failed })
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(DATABASE_MAGIC)?;
+        out.write_all(&DATABASE_VERSION.to_le_bytes())?;
+        out.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for x in &self.entries {
+            write_entry(out, x)?;
+        }
+        out.write_all(&(self.failed.len() as u32).to_le_bytes())?;
+        for (path, modified) in &self.failed {
+            write_string(out, path)?;
+            out.write_all(&modified.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
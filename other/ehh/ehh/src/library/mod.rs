@@ -0,0 +1,427 @@
+// every beatmap in the songs folder, with enough cached to search and sort without parsing anything again
+
+mod batch;
+mod database;
+mod scan;
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+use log::warn;
+
+use crate::{
+    beatmap::{Beatmap, BeatmapParseErr, Gamemode},
+    rating,
+};
+
+pub use batch::*;
+pub use database::*;
+pub use scan::*;
+
+// next to the config, same as osu!.db
+pub const LIBRARY_FILENAME: &str = "ehh.db";
+
+pub fn library_path(config_path: &str) -> String {
+    Path::new(config_path)
+        .with_file_name(LIBRARY_FILENAME)
+        .to_string_lossy()
+        .to_string()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LibraryEntry {
+    pub path: String,     // the loose .osu, or the .osz it came out of
+    pub filename: String, // the .osu in the set
+    pub modified: u64,    // of path in ms since the epoch, 0 if it couldn't be read
+
+    pub artist: String,
+    pub romanized_artist: String,
+    pub title: String,
+    pub romanized_title: String,
+    pub creator: String,
+    pub version: String,
+    pub source: String,
+    pub tags: String,
+    pub beatmap_id: i32,
+    pub beatmap_set_id: i32,
+    pub mode: Gamemode,
+
+    // without mods
+    pub approach_rate: f32,
+    pub circle_size: f32,
+    pub hp_drain: f32,
+    pub overall_difficulty: f32,
+    pub length: i32, // end of the last object
+    pub min_bpm: f64,
+    pub max_bpm: f64,
+    pub circle_count: usize,
+    pub slider_count: usize,
+    pub spinner_count: usize,
+    pub hold_count: usize,
+    pub max_combo: usize,
+    pub stars: Option<f64>, // only standard can be rated
+}
+
+impl LibraryEntry {
+    pub fn new(beatmap: &Beatmap, path: &str, filename: &str, modified: u64) -> LibraryEntry {
+        let (min_bpm, max_bpm) = beatmap.timing.bpm_range().unwrap_or((0.0, 0.0));
+
+        LibraryEntry {
+            path: path.to_string(),
+            filename: filename.to_string(),
+            modified,
+            artist: beatmap.artist.clone(),
+            romanized_artist: beatmap.romanized_artist.clone(),
+            title: beatmap.title.clone(),
+            romanized_title: beatmap.romanized_title.clone(),
+            creator: beatmap.creator.clone(),
+            version: beatmap.version.clone(),
+            source: beatmap.source.clone(),
+            tags: beatmap.tags.clone(),
+            beatmap_id: beatmap.beatmap_id,
+            beatmap_set_id: beatmap.beatmap_set_id,
+            mode: beatmap.mode,
+            approach_rate: beatmap.difficulty.approach_rate,
+            circle_size: beatmap.difficulty.circle_size,
+            hp_drain: beatmap.difficulty.hp_drain,
+            overall_difficulty: beatmap.difficulty.overall_difficulty,
+            length: beatmap.hit_objects.last().map(|x| x.end).unwrap_or(0),
+            min_bpm,
+            max_bpm,
+            circle_count: beatmap.circle_count,
+            slider_count: beatmap.slider_count,
+            spinner_count: beatmap.spinner_count,
+            hold_count: beatmap.hold_count,
+            max_combo: beatmap.max_combo(),
+            stars: rating::calculate(beatmap).map(|x| x.stars),
+        }
+    }
+
+    // every term has to show up somewhere in here
+    fn matches(&self, terms: &[String]) -> bool {
+        let haystack = [
+            &self.artist,
+            &self.romanized_artist,
+            &self.title,
+            &self.romanized_title,
+            &self.creator,
+            &self.version,
+            &self.source,
+            &self.tags,
+        ]
+        .iter()
+        .map(|x| x.to_lowercase())
+        .collect::<Vec<String>>()
+        .join(" ");
+        terms.iter().all(|x| haystack.contains(x.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortMode {
+    Artist,
+    Title,
+    Creator,
+    Stars,
+    Length,
+    Bpm,
+}
+
+impl SortMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortMode::Artist => "Artist",
+            SortMode::Title => "Title",
+            SortMode::Creator => "Creator",
+            SortMode::Stars => "Stars",
+            SortMode::Length => "Length",
+            SortMode::Bpm => "BPM",
+        }
+    }
+
+    // for cycling through them with a single key
+    pub fn next(&self) -> SortMode {
+        match self {
+            SortMode::Artist => SortMode::Title,
+            SortMode::Title => SortMode::Creator,
+            SortMode::Creator => SortMode::Stars,
+            SortMode::Stars => SortMode::Length,
+            SortMode::Length => SortMode::Bpm,
+            SortMode::Bpm => SortMode::Artist,
+        }
+    }
+
+    fn compare(&self, a: &LibraryEntry, b: &LibraryEntry) -> Ordering {
+        let text = |x: &str, y: &str| x.to_lowercase().cmp(&y.to_lowercase());
+        let stars = |x: &LibraryEntry| x.stars.unwrap_or(0.0);
+        let primary = match self {
+            SortMode::Artist => text(&a.romanized_artist, &b.romanized_artist),
+            SortMode::Title => text(&a.romanized_title, &b.romanized_title),
+            SortMode::Creator => text(&a.creator, &b.creator),
+            SortMode::Stars => stars(a).total_cmp(&stars(b)),
+            SortMode::Length => a.length.cmp(&b.length),
+            SortMode::Bpm => a.max_bpm.total_cmp(&b.max_bpm),
+        };
+        // keeps difficulties of the same set together, easiest first
+        primary
+            .then_with(|| text(&a.romanized_title, &b.romanized_title))
+            .then_with(|| a.path.cmp(&b.path))
+            .then_with(|| stars(a).total_cmp(&stars(b)))
+            .then_with(|| a.filename.cmp(&b.filename))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ScanResult {
+    pub parsed: usize,
+    pub cached: usize, // unchanged since the last scan, so they didn't get parsed again
+    pub removed: usize, // in the database but not in the folder anymore
+    pub skipped: usize, // unsupported mode
+    pub errored: usize,
+    pub failed_unchanged: usize, // sets that had nothing usable last time and still haven't changed
+}
+
+#[derive(Default)]
+pub struct Library {
+    pub entries: Vec<LibraryEntry>,
+    // sets that didn't give any entries, by modified time, so they only get parsed again once they change
+    pub failed: HashMap<String, u64>,
+}
+
+fn modified_time(path: &Path) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|x| x.modified())
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl Library {
+    // a missing or broken database is the same as an empty one, it just means a full rescan
+    pub fn load(path: &str) -> Library {
+        let file = match File::open(path) {
+            Ok(x) => x,
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("Failed to open {}: {}", path, e);
+                }
+                return Library::default();
+            }
+        };
+        match Library::read(&mut BufReader::new(file)) {
+            Ok(x) => x,
+            Err(e) => {
+                warn!(
+                    "Failed to read {}, everything will be rescanned: {:?}",
+                    path, e
+                );
+                Library::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()
+    }
+
+    // only parses what changed since the last scan, based on the modified time of each file
+    pub fn scan(&mut self, songs_path: impl AsRef<Path>) -> ScanResult {
+        let mut result = ScanResult::default();
+        let mut old: HashMap<String, Vec<LibraryEntry>> = HashMap::new();
+        for x in self.entries.drain(..) {
+            old.entry(x.path.clone()).or_default().push(x);
+        }
+        let mut old_failed = std::mem::take(&mut self.failed);
+
+        for found in walk_songs(songs_path) {
+            let found = match found {
+                Ok(x) => x,
+                Err(e) => {
+                    warn!("Failed to read the songs folder: {}", e);
+                    result.errored += 1;
+                    continue;
+                }
+            };
+            let path = found.path.to_string_lossy().to_string();
+            let modified = modified_time(&found.path);
+            if let Some(cached) = old.remove(&path) {
+                if modified != 0 && cached.iter().all(|x| x.modified == modified) {
+                    result.cached += cached.len();
+                    self.entries.extend(cached);
+                    continue;
+                }
+            }
+            if let Some(failed) = old_failed.remove(&path) {
+                if modified != 0 && failed == modified {
+                    result.failed_unchanged += 1;
+                    self.failed.insert(path, modified);
+                    continue;
+                }
+            }
+
+            let (set, difficulties) = match found.open() {
+                Ok(x) => x,
+                Err(e) => {
+                    warn!("Failed to open {}: {}", path, e);
+                    result.errored += 1;
+                    if modified != 0 {
+                        self.failed.insert(path, modified);
+                    }
+                    continue;
+                }
+            };
+            let entry_count = self.entries.len();
+            for x in difficulties {
+                match set.parse_difficulty(&x) {
+                    Ok(beatmap) => {
+                        self.entries
+                            .push(LibraryEntry::new(&beatmap, &path, &x, modified));
+                        result.parsed += 1;
+                    }
+                    Err(BeatmapParseErr::UnsupportedMode) => result.skipped += 1,
+                    Err(e) => {
+                        warn!("Failed to parse {} ({}) because {:?}", path, x, e);
+                        result.errored += 1;
+                    }
+                }
+            }
+            if self.entries.len() == entry_count && modified != 0 {
+                self.failed.insert(path, modified);
+            }
+        }
+
+        result.removed = old.values().map(|x| x.len()).sum();
+        result
+    }
+
+    // indices into entries, an empty query matches everything
+    pub fn search(&self, query: &str, sort: SortMode) -> Vec<usize> {
+        let terms: Vec<String> = query.split_whitespace().map(|x| x.to_lowercase()).collect();
+        let mut out: Vec<usize> = (0..self.entries.len())
+            .filter(|&i| self.entries[i].matches(&terms))
+            .collect();
+        out.sort_by(|&a, &b| sort.compare(&self.entries[a], &self.entries[b]));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_library_path() {
+        assert_eq!(library_path("ehh.cfg"), "ehh.db");
+        assert_eq!(
+            Path::new(&library_path("some/folder/other.cfg")),
+            Path::new("some/folder/ehh.db")
+        );
+    }
+
+    #[test]
+    fn test_library() {
+        let mut library = Library::default();
+        let result = library.scan("test");
+        assert!(result.parsed > 0);
+        assert_eq!(result.cached, 0);
+        assert_eq!(library.entries.len(), result.parsed);
+
+        // nothing changed, so nothing gets parsed again
+        let result = library.scan("test");
+        assert_eq!(result.parsed, 0);
+        assert_eq!(result.cached, library.entries.len());
+        assert_eq!(result.removed, 0);
+
+        let jumps = library
+            .entries
+            .iter()
+            .find(|x| x.filename == "jumps.osu")
+            .unwrap();
+        assert_eq!(jumps.mode, Gamemode::Osu);
+        assert!(jumps.stars.unwrap() > 0.0);
+        assert!(jumps.max_bpm >= jumps.min_bpm && jumps.min_bpm > 0.0);
+        assert!(jumps.length > 0);
+
+        // every term has to match, case doesn't matter
+        let query = format!(
+            "{} {}",
+            jumps.romanized_artist.to_uppercase(),
+            jumps.version.to_lowercase()
+        );
+        let found = library.search(&query, SortMode::Title);
+        assert!(found
+            .iter()
+            .any(|&i| library.entries[i].filename == "jumps.osu"));
+        assert!(library
+            .search("no map has this in it", SortMode::Title)
+            .is_empty());
+
+        let by_stars = library.search("", SortMode::Stars);
+        assert_eq!(by_stars.len(), library.entries.len());
+        for x in by_stars.windows(2) {
+            let stars = |i: usize| library.entries[i].stars.unwrap_or(0.0);
+            assert!(stars(x[0]) <= stars(x[1]));
+        }
+
+        let mut written = Vec::new();
+        library.write(&mut written).unwrap();
+        let reread = Library::read(&mut Cursor::new(&written)).unwrap();
+        assert_eq!(reread.entries.len(), library.entries.len());
+        for (a, b) in library.entries.iter().zip(&reread.entries) {
+            assert_eq!(a.path, b.path);
+            assert_eq!(a.filename, b.filename);
+            assert_eq!(a.modified, b.modified);
+            assert_eq!(a.title, b.title);
+            assert_eq!(a.stars, b.stars);
+            assert_eq!(a.max_combo, b.max_combo);
+        }
+        assert!(matches!(
+            Library::read(&mut Cursor::new(b"nope")),
+            Err(LibraryReadErr::InvalidMagic)
+        ));
+    }
+
+    #[test]
+    fn test_library_failed() {
+        let dir =
+            std::env::temp_dir().join(format!("ehh_test_library_failed_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("broken.osz");
+        std::fs::write(&path, b"not a zip").unwrap();
+
+        let mut library = Library::default();
+        let result = library.scan(&dir);
+        assert_eq!(result.errored, 1);
+        assert_eq!(library.failed.len(), 1);
+
+        // survives a save, and doesn't get parsed again until it changes
+        let mut written = Vec::new();
+        library.write(&mut written).unwrap();
+        let mut library = Library::read(&mut Cursor::new(&written)).unwrap();
+        let result = library.scan(&dir);
+        assert_eq!(result.errored, 0);
+        assert_eq!(result.failed_unchanged, 1);
+        assert_eq!(library.failed.len(), 1);
+
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_modified(UNIX_EPOCH + std::time::Duration::from_secs(1_000_000))
+            .unwrap();
+        let result = library.scan(&dir);
+        assert_eq!(result.errored, 1);
+        assert_eq!(result.failed_unchanged, 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
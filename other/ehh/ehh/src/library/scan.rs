@@ -0,0 +1,61 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::beatmap::BeatmapSet;
+
+// a loose .osu or an .osz, found by walk_songs
+pub struct FoundBeatmaps {
+    pub path: PathBuf,
+    pub archive: bool,
+}
+
+impl FoundBeatmaps {
+    // a loose .osu only has itself in it, an .osz has every difficulty inside it
+    pub fn open(&self) -> io::Result<(Arc<BeatmapSet>, Vec<String>)> {
+        if self.archive {
+            let set = BeatmapSet::from_osz(&self.path)?;
+            let difficulties = set.difficulties();
+            Ok((Arc::new(set), difficulties))
+        } else {
+            let name = self
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            Ok((Arc::new(BeatmapSet::open(&self.path)?), vec![name]))
+        }
+    }
+}
+
+// everything that has beatmaps in it under a folder, nothing gets opened until it's asked for
+pub fn walk_songs(path: impl AsRef<Path>) -> impl Iterator<Item = io::Result<FoundBeatmaps>> {
+    walkdir::WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| {
+            let entry = match entry {
+                Ok(x) => x,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if !entry.file_type().is_file() {
+                return None;
+            }
+
+            let ext = entry.path().extension()?.to_str()?;
+            let archive = if ext.eq_ignore_ascii_case("osz") {
+                true
+            } else if ext.eq_ignore_ascii_case("osu") {
+                false
+            } else {
+                return None;
+            };
+            Some(Ok(FoundBeatmaps {
+                path: entry.into_path(),
+                archive,
+            }))
+        })
+}
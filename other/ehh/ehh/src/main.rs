@@ -1,12 +1,29 @@
-use std::{fs::File, io::BufReader, path::PathBuf, rc::Rc, time::Instant};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    rc::Rc,
+    sync::Arc,
+    thread,
+    time::Instant,
+};
 
 use clap::{Parser, Subcommand};
+#[cfg(feature = "bass")]
+use ehh::framework::bass::{Bass, BassChannelCommon};
 use ehh::{
-    app::EhhApp,
-    framework::bass::{Bass, BassChannelCommon},
+    app::{
+        render_play, simulate_replay, Config, EhhApp, PlayOptions, RenderOptions, StartScene,
+        DEFAULT_CONFIG_PATH,
+    },
+    beatmap::{check_set, BeatmapSet, Gamemode, Mods, ParseMode},
+    json::json_string,
+    library::{batch_parse, library_path, Library, ParseStatus},
+    rating::{self, ScoreInfo},
+    replay::Replay,
     Beatmap,
 };
-use log::{error, info};
+use log::{error, info, warn};
 
 #[rustfmt::skip]
 fn dump_beatmap_info(beatmap: Beatmap) {
@@ -67,95 +84,614 @@ fn dump_beatmap_info(beatmap: Beatmap) {
     }
     */
 
-    println!("Max combo: {}", {
-        let mut ret: usize = 0;
-        for x in beatmap.hit_objects {
-            ret += 1;
-            if let Some(slider_info) = x.slider_info {
-                ret += slider_info.score_times.len();
-            }
-        }
-        ret
-    });
+    if beatmap.mode == Gamemode::Mania {
+        println!("Keys: {}", beatmap.mania_key_count());
+    }
+    println!("Max combo: {}", beatmap.max_combo());
+    if let Some(attributes) = rating::calculate(&beatmap) {
+        println!(
+            "Stars: {:.2} ({:.2} aim, {:.2} speed)",
+            attributes.stars, attributes.aim, attributes.speed
+        );
+    }
     println!(
-        "{} circles, {} sliders, {} spinners",
-        beatmap.circle_count, beatmap.slider_count, beatmap.spinner_count
+        "{} circles, {} sliders, {} spinners, {} holds",
+        beatmap.circle_count, beatmap.slider_count, beatmap.spinner_count, beatmap.hold_count
     );
 }
 
+fn dump_replay_info(path: &str) -> Result<(), String> {
+    let file = std::fs::File::open(path).map_err(|x| format!("Failed to open replay: {x}"))?;
+    let replay = Replay::parse(&mut std::io::BufReader::new(file))
+        .map_err(|x| format!("Failed to parse replay: {x:?}"))?;
+
+    println!("Mode:         {:?}", replay.mode);
+    println!("Version:      {}", replay.version);
+    println!("Player:       {}", replay.player_name);
+    println!("Beatmap hash: {}", replay.beatmap_hash);
+    println!("Mods:         {}", replay.mods);
+    println!("Score:        {}", replay.score);
+    println!(
+        "Max combo:    {}{}",
+        replay.max_combo,
+        if replay.perfect { " (perfect)" } else { "" }
+    );
+    println!(
+        "Hits:         {} 300s ({} geki), {} 100s ({} katu), {} 50s, {} misses",
+        replay.count_300,
+        replay.count_geki,
+        replay.count_100,
+        replay.count_katu,
+        replay.count_50,
+        replay.count_miss
+    );
+    println!(
+        "Frames:       {} over {}ms",
+        replay.frames.len(),
+        replay.frames.last().map(|x| x.time).unwrap_or(0)
+    );
+    Ok(())
+}
+
+// plays the replay back headlessly and compares what happened with what the replay says happened
+fn verify_replay(
+    beatmap_path: &str,
+    difficulty: Option<&str>,
+    replay_path: &str,
+    ignore_hash: bool,
+) -> Result<(), String> {
+    let file =
+        std::fs::File::open(replay_path).map_err(|x| format!("Failed to open replay: {x}"))?;
+    let replay = Replay::parse(&mut std::io::BufReader::new(file))
+        .map_err(|x| format!("Failed to parse replay: {x:?}"))?;
+    let beatmap = BeatmapSet::open_difficulty(beatmap_path, difficulty, replay.mods)
+        .map_err(|x| format!("Failed to load beatmap: {x:?}"))?;
+
+    // a replay played on a different version of the map won't line up with it
+    if !beatmap.hash.eq_ignore_ascii_case(&replay.beatmap_hash) {
+        if !ignore_hash {
+            return Err(format!(
+                "The replay is for a different beatmap ({} instead of {}), use --ignore-hash to verify it anyway",
+                replay.beatmap_hash, beatmap.hash
+            ));
+        }
+        warn!(
+            "The replay is for a different beatmap ({} instead of {})",
+            replay.beatmap_hash, beatmap.hash
+        );
+    }
+    if beatmap.mode != Gamemode::Osu || replay.mode != Gamemode::Osu {
+        return Err("Only osu!standard replays can be verified for now".to_string());
+    }
+
+    let score = simulate_replay(Rc::new(beatmap), &replay);
+    println!("{:<10} {:>10} {:>10}", "", "replay", "simulated");
+    for (name, expected, actual) in [
+        ("300s", replay.count_300 as u64, score.count_300 as u64),
+        ("100s", replay.count_100 as u64, score.count_100 as u64),
+        ("50s", replay.count_50 as u64, score.count_50 as u64),
+        ("misses", replay.count_miss as u64, score.count_miss as u64),
+        ("max combo", replay.max_combo as u64, score.max_combo as u64),
+        ("score", replay.score as u64, score.score),
+    ] {
+        println!(
+            "{:<10} {:>10} {:>10}{}",
+            name,
+            expected,
+            actual,
+            if expected != actual { " *" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+fn override_config<T>(value: &mut T, flag: Option<T>) {
+    if let Some(x) = flag {
+        *value = x;
+    }
+}
+
+// relative paths that don't exist here get looked up in the songs folder
+fn resolve_beatmap_path(config: &Config, path: &str) -> String {
+    match &config.songs_path {
+        Some(songs) if Path::new(path).is_relative() && !Path::new(path).exists() => {
+            Path::new(songs).join(path).to_string_lossy().to_string()
+        }
+        _ => path.to_string(),
+    }
+}
+
+// "start,end" in ms
+fn parse_time_range(source: &str) -> Result<(i32, i32), String> {
+    let (start, end) = source
+        .split_once(',')
+        .ok_or_else(|| "should look like \"start,end\"".to_string())?;
+    let parse = |x: &str| x.trim().parse::<i32>().map_err(|e| e.to_string());
+    let (start, end) = (parse(start)?, parse(end)?);
+    if end <= start {
+        return Err("the end has to be after the start".to_string());
+    }
+    Ok((start, end))
+}
+
+// brings the database up to date with the songs folder
+fn scan_library(config: &Config, config_path: &str) -> Library {
+    let path = library_path(config_path);
+    let mut library = Library::load(&path);
+    match &config.songs_path {
+        Some(songs) => {
+            info!("Scanning {}...", songs);
+            let now = Instant::now();
+            let result = library.scan(songs);
+            info!(
+                "Scanned {} in {:.2} seconds ({} parsed, {} unchanged, {} removed, {} skipped, {} errored, {} broken sets unchanged)",
+                songs,
+                now.elapsed().as_secs_f64(),
+                result.parsed,
+                result.cached,
+                result.removed,
+                result.skipped,
+                result.errored,
+                result.failed_unchanged
+            );
+            if let Err(e) = library.save(&path) {
+                error!("Failed to save {}: {}", path, e);
+            }
+        }
+        None => error!("There's no songs folder to scan, set SongsFolder in the config"),
+    }
+    library
+}
+
 #[derive(Parser)]
 #[clap(version, about, long_about = None)]
 struct Cli {
     #[clap(subcommand)]
     command: Commands,
+    /// Config file to use, the defaults get used if it doesn't exist. The library database goes next to it
+    #[clap(long, global = true, default_value = DEFAULT_CONFIG_PATH)]
+    config: String,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    Parse { beatmap: Option<String> },
-    BatchParse { beatmap_dir: Option<String> },
-    TestBass { song: Option<String> },
-    Play { beatmap: Option<String> },
+    /// Dump info about a .osu, or every difficulty in a folder or .osz
+    Parse {
+        beatmap: Option<String>,
+        /// Skip lines that osu! would refuse to load instead of failing
+        #[clap(long)]
+        lenient: bool,
+    },
+    BatchParse {
+        beatmap_dir: Option<String>,
+        /// Skip lines that osu! would refuse to load instead of failing
+        #[clap(long)]
+        lenient: bool,
+        /// Also check that every map survives being written back out
+        #[clap(long)]
+        round_trip: bool,
+        /// Print the star rating and SS pp of every map
+        #[clap(long)]
+        rating: bool,
+        /// How many maps to parse at once, defaults to one per core
+        #[clap(long)]
+        threads: Option<usize>,
+        /// Write a report of every file as JSON
+        #[clap(long)]
+        json: Option<String>,
+        /// Write a report of every file as CSV
+        #[clap(long)]
+        csv: Option<String>,
+    },
+    /// Look for common mapping mistakes in a .osu, or every difficulty in a folder or .osz
+    Check {
+        beatmap: Option<String>,
+        /// Print the warnings as JSON instead
+        #[clap(long)]
+        json: bool,
+    },
+    /// Calculate the star rating and pp of a map for a given score
+    Rating {
+        beatmap: Option<String>,
+        /// Which difficulty to use out of a folder or .osz
+        #[clap(long)]
+        difficulty: Option<String>,
+        /// Accuracy in percent
+        #[clap(long, default_value_t = 100.0)]
+        accuracy: f64,
+        #[clap(long, default_value_t = 0)]
+        misses: usize,
+        /// Defaults to the max combo
+        #[clap(long)]
+        combo: Option<usize>,
+        /// Mods to rate the map with, like "HDDT"
+        #[clap(long, parse(try_from_str = Mods::from_acronyms), default_value = "")]
+        mods: Mods,
+    },
+    /// Dump info about a .osr
+    Replay {
+        replay: Option<String>,
+    },
+    /// Play a replay back without a window and compare the results with the replay's
+    Verify {
+        beatmap: Option<String>,
+        replay: Option<String>,
+        /// Which difficulty to use out of a folder or .osz
+        #[clap(long)]
+        difficulty: Option<String>,
+        /// Verify even if the replay was made on a different version of the beatmap
+        #[clap(long)]
+        ignore_hash: bool,
+    },
+    TestBass {
+        song: Option<String>,
+    },
+    /// Write out the config with every option filled in, so there's something to edit
+    SaveConfig,
+    /// Update the beatmap library with what's in the songs folder
+    Scan {
+        /// Songs folder to use instead of the one in the config
+        #[clap(long)]
+        songs: Option<String>,
+    },
+    /// Play a map, or pick one from the songs folder if there's no path
+    Play {
+        beatmap: Option<String>,
+        /// Which difficulty to play out of a folder or .osz
+        #[clap(long)]
+        difficulty: Option<String>,
+        /// Mods to play with, like "HDDT"
+        #[clap(long, parse(try_from_str = Mods::from_acronyms), default_value = "")]
+        mods: Mods,
+        /// Watch a perfect play instead of playing
+        #[clap(long)]
+        autoplay: bool,
+        /// Skin folder to use on top of the built-in one
+        #[clap(long)]
+        skin: Option<String>,
+        #[clap(long)]
+        width: Option<u32>,
+        #[clap(long)]
+        height: Option<u32>,
+        #[clap(long)]
+        fullscreen: Option<bool>,
+        #[clap(long)]
+        vsync: Option<bool>,
+        /// Max fps when vsync is off, 0 for unlimited
+        #[clap(long)]
+        frame_limit: Option<u32>,
+        /// 0 to 100
+        #[clap(long)]
+        volume: Option<u32>,
+        /// 0 to 100
+        #[clap(long)]
+        music_volume: Option<u32>,
+        /// 0 to 100
+        #[clap(long)]
+        effect_volume: Option<u32>,
+        /// Universal offset in ms, raise it if you're hitting early
+        #[clap(long, allow_hyphen_values = true)]
+        offset: Option<i32>,
+        /// Key names for the two osu keys, like "Z,X"
+        #[clap(long)]
+        keys: Option<String>,
+        /// Songs folder to use instead of the one in the config
+        #[clap(long)]
+        songs: Option<String>,
+        /// Loop a section of the map, given as "start,end" in ms
+        #[clap(long, parse(try_from_str = parse_time_range))]
+        practice: Option<(i32, i32)>,
+    },
+    /// Render an autoplay of a map to a folder of PNG frames
+    Render {
+        beatmap: Option<String>,
+        /// Folder to write the frames to
+        output: Option<String>,
+        /// Which difficulty to render out of a folder or .osz
+        #[clap(long)]
+        difficulty: Option<String>,
+        /// Mods to play with, like "HDDT"
+        #[clap(long, parse(try_from_str = Mods::from_acronyms), default_value = "")]
+        mods: Mods,
+        /// Skin folder to use on top of the built-in one
+        #[clap(long)]
+        skin: Option<String>,
+        #[clap(long, default_value_t = 60)]
+        fps: u32,
+        #[clap(long, default_value_t = 1920)]
+        width: u32,
+        #[clap(long, default_value_t = 1080)]
+        height: u32,
+        /// Also mix the song and hitsounds into this WAV file
+        #[clap(long)]
+        audio: Option<String>,
+    },
 }
 
-fn parse_map(path: &str) -> Result<(), std::io::Error> {
-    println!("Parsing {path}...");
-    let mut folder = PathBuf::from(path);
-    folder.pop();
-    let res = Beatmap::parse(
-        &folder.to_string_lossy(),
-        &mut BufReader::new(File::open(path)?),
-    );
+fn parse_mode(lenient: bool) -> ParseMode {
+    if lenient {
+        ParseMode::Lenient
+    } else {
+        ParseMode::Strict
+    }
+}
 
-    if let Ok(beatmap) = res {
-        dump_beatmap_info(beatmap);
+fn parse_map(path: &str, mode: ParseMode) -> Result<(), std::io::Error> {
+    let set = Arc::new(BeatmapSet::open(path)?);
+    let difficulties = if Path::new(path).is_dir() || set.is_archive() {
+        set.difficulties()
     } else {
-        println!("Failed to parse the beatmap!");
+        vec![Path::new(path)
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()]
+    };
+
+    for x in difficulties {
+        println!("Parsing {x}...");
+        match set.parse_difficulty_with_mode(&x, Mods::empty(), mode) {
+            Ok(beatmap) => {
+                for diagnostic in &beatmap.diagnostics {
+                    println!("{}", diagnostic);
+                }
+                dump_beatmap_info(beatmap);
+            }
+            Err(err) => println!("Failed to parse the beatmap: {}", err),
+        }
     }
 
     Ok(())
 }
 
-fn batch_parse_maps(path: &str) {
-    let ext = std::ffi::OsStr::new("osu");
-    let mut parsed = 0;
-    let mut skipped = 0;
-    let mut errored = 0;
-    let now = Instant::now();
-    for entry in walkdir::WalkDir::new(path) {
-        let entry = entry.unwrap();
-        let path = entry.path();
-        if path.extension() != Some(ext) {
-            continue;
+// runs the modding checks over a .osu, or every difficulty in a folder or .osz
+fn check_map(path: &str, json: bool) -> Result<(), String> {
+    let set = Arc::new(BeatmapSet::open(path).map_err(|e| e.to_string())?);
+    let difficulties = if Path::new(path).is_dir() || set.is_archive() {
+        set.difficulties()
+    } else {
+        vec![Path::new(path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()]
+    };
+
+    // broken difficulties still get reported, they just can't be checked against the rest
+    let mut names = Vec::new();
+    let mut beatmaps = Vec::new();
+    let mut failed = Vec::new();
+    for x in difficulties {
+        match set.parse_difficulty(&x) {
+            Ok(beatmap) => {
+                names.push(x);
+                beatmaps.push(beatmap);
+            }
+            Err(e) => failed.push((x, format!("{:?}", e))),
         }
+    }
+    let warnings = check_set(&beatmaps);
 
-        println!("Parsing {}...", entry.file_name().to_str().unwrap());
-        let mut folder = entry.path().to_path_buf();
-        folder.pop();
-        let beatmap = Beatmap::parse(
-            &folder.to_string_lossy(),
-            &mut BufReader::with_capacity(100_000, File::open(path).unwrap()),
-        );
+    if json {
+        let mut entries: Vec<String> = names
+            .iter()
+            .zip(&warnings)
+            .map(|(name, warnings)| {
+                let warnings: Vec<String> = warnings.iter().map(|x| x.to_json()).collect();
+                format!(
+                    "{{\"difficulty\":{},\"error\":null,\"warnings\":[{}]}}",
+                    json_string(name),
+                    warnings.join(",")
+                )
+            })
+            .collect();
+        entries.extend(failed.iter().map(|(name, err)| {
+            format!(
+                "{{\"difficulty\":{},\"error\":{},\"warnings\":[]}}",
+                json_string(name),
+                json_string(err)
+            )
+        }));
+        println!("[{}]", entries.join(","));
+        return Ok(());
+    }
 
-        if beatmap.is_err() {
-            let err = beatmap.err().unwrap();
-            if !matches!(err, ehh::BeatmapParseErr::UnsupportedMode) {
-                error!("Failed to parse {:?} because {:?}", path, err);
-                errored += 1;
-            } else {
-                skipped += 1;
+    for (name, warnings) in names.iter().zip(&warnings) {
+        println!("{}: {} warnings", name, warnings.len());
+        for x in warnings {
+            println!("    {}", x);
+        }
+    }
+    for (name, err) in &failed {
+        println!("{}: failed to parse because {}", name, err);
+    }
+    Ok(())
+}
+
+// writes the map out and parses it again, returning an error if anything changed
+fn round_trip_map(beatmap: &Beatmap) -> Result<(), String> {
+    let mut written = Vec::new();
+    beatmap.write(&mut written).map_err(|e| e.to_string())?;
+    let reparsed = Beatmap::parse(beatmap.set.clone(), &mut written.as_slice())
+        .map_err(|e| format!("failed to reparse: {:?}", e))?;
+
+    let mut rewritten = Vec::new();
+    reparsed.write(&mut rewritten).map_err(|e| e.to_string())?;
+    if written != rewritten {
+        return Err("written file changed after reparsing".to_string());
+    }
+    if beatmap.hit_objects.len() != reparsed.hit_objects.len() {
+        return Err("hit object count changed".to_string());
+    }
+    for (a, b) in beatmap.hit_objects.iter().zip(&reparsed.hit_objects) {
+        if a.start != b.start || a.end != b.end || a.end_pos != b.end_pos {
+            return Err(format!("hit object at {}ms changed", a.start));
+        }
+    }
+
+    Ok(())
+}
+
+fn rate_map(beatmap: &Beatmap, score: ScoreInfo) -> Result<(), String> {
+    let attributes = match rating::calculate(beatmap) {
+        Some(x) => x,
+        None => return Err(format!("{:?} beatmaps can't be rated yet", beatmap.mode)),
+    };
+    let performance = score.performance(&attributes);
+
+    println!(
+        "{} {:.2} stars ({:.2} aim, {:.2} speed), AR {:.2}, OD {:.2}, max combo {}",
+        attributes.mods,
+        attributes.stars,
+        attributes.aim,
+        attributes.speed,
+        attributes.approach_rate,
+        attributes.overall_difficulty,
+        attributes.max_combo
+    );
+    println!(
+        "{:.2}% {}x {} misses: {:.2}pp ({:.2} aim, {:.2} speed, {:.2} accuracy)",
+        score.accuracy * 100.0,
+        score.combo,
+        score.misses,
+        performance.total,
+        performance.aim,
+        performance.speed,
+        performance.accuracy
+    );
+    Ok(())
+}
+
+struct BatchOptions {
+    mode: ParseMode,
+    round_trip: bool,
+    rating: bool,
+    threads: usize,
+    json: Option<String>,
+    csv: Option<String>,
+}
+
+fn batch_parse_maps(path: &str, options: BatchOptions) -> Result<(), String> {
+    let report = batch_parse(path, options.mode, options.threads, |beatmap, report| {
+        if options.rating {
+            if let Some(attributes) = rating::calculate(beatmap) {
+                let pp = ScoreInfo {
+                    accuracy: 1.0,
+                    misses: 0,
+                    combo: attributes.max_combo,
+                }
+                .performance(&attributes);
+                report.stars = Some(attributes.stars);
+                report.pp = Some(pp.total);
+            }
+        }
+        if options.round_trip {
+            if let Err(err) = round_trip_map(beatmap) {
+                report
+                    .problems
+                    .push(format!("failed to round-trip because {}", err));
+            }
+        }
+    });
+
+    let mut diagnostics = 0;
+    for x in &report.files {
+        if x.status == ParseStatus::Parsed {
+            if !x.diagnostics.is_empty() || !x.problems.is_empty() || x.stars.is_some() {
+                println!("{} ({}):", x.path, x.difficulty);
             }
+            for diagnostic in &x.diagnostics {
+                println!("    {}", diagnostic);
+            }
+            diagnostics += x.diagnostics.len();
+            if let (Some(stars), Some(pp)) = (x.stars, x.pp) {
+                println!("    {:.2} stars, {:.2}pp", stars, pp);
+            }
+            for problem in &x.problems {
+                error!("{} ({}) {}", x.path, x.difficulty, problem);
+            }
+        } else if x.status != ParseStatus::Skipped {
+            let error = x.error.as_deref().unwrap_or_default();
+            error!(
+                "Failed to parse {} ({}) because {}",
+                x.path, x.difficulty, error
+            );
+        }
+    }
+
+    let parsed = report.files.len();
+    let elapsed_sec = report.elapsed.as_secs_f64();
+    println!(
+        "Parsed {} beatmaps in {:.3} seconds on {} threads ({:.1} beatmaps/sec)",
+        parsed,
+        elapsed_sec,
+        report.threads,
+        parsed as f64 / elapsed_sec
+    );
+    println!(
+        "{} ok, {} skipped due to unsupported mode, {} failed, {} unreadable",
+        report.count(ParseStatus::Parsed),
+        report.count(ParseStatus::Skipped),
+        report.count(ParseStatus::Failed),
+        report.count(ParseStatus::Unreadable)
+    );
+    println!("{} lines had to be skipped", diagnostics);
+    if options.round_trip {
+        let failed = report
+            .files
+            .iter()
+            .filter(|x| !x.problems.is_empty())
+            .count();
+        println!("{} beatmaps failed to round-trip", failed);
+    }
+
+    let errors = report.error_histogram();
+    if !errors.is_empty() {
+        println!("Errors:");
+        for (category, count) in errors {
+            println!("    {:<28} {}", category, count);
         }
-        parsed += 1;
-        // if parsed % 100 == 0 {
-        //     println!("Parsed {} beatmaps", parsed);
-        // }
     }
-    let elapsed_sec = now.elapsed().as_millis() as f64 / 1000.0;
-    println!("Parsed {} beatmaps in {} seconds ({} beatmaps/sec, errored on {}, and skipped {} due to unsupported mode)",
-        parsed, elapsed_sec, parsed as f64 / elapsed_sec, errored, skipped);
+    println!("Modes:");
+    for (mode, count) in report.mode_counts() {
+        println!("    {:<28} {}", format!("{:?}", mode), count);
+    }
+    let (circles, sliders, spinners, holds) = report.object_totals();
+    println!(
+        "Objects: {} circles, {} sliders, {} spinners, {} holds",
+        circles, sliders, spinners, holds
+    );
+    println!("Slowest:");
+    for x in report.slowest(10) {
+        println!(
+            "    {:>9.3}ms {} ({})",
+            x.parse_time.as_secs_f64() * 1000.0,
+            x.path,
+            x.difficulty
+        );
+    }
+
+    if let Some(json) = options.json {
+        let mut file = BufWriter::new(File::create(&json).map_err(|e| e.to_string())?);
+        report.write_json(&mut file).map_err(|e| e.to_string())?;
+        file.flush().map_err(|e| e.to_string())?;
+        info!("Wrote the report to {}", json);
+    }
+    if let Some(csv) = options.csv {
+        let mut file = BufWriter::new(File::create(&csv).map_err(|e| e.to_string())?);
+        report.write_csv(&mut file).map_err(|e| e.to_string())?;
+        file.flush().map_err(|e| e.to_string())?;
+        info!("Wrote the report to {}", csv);
+    }
+    Ok(())
 }
 
+#[cfg(not(feature = "bass"))]
+fn test_bass(_path: &str) {
+    println!("ehh was built without BASS");
+}
+
+#[cfg(feature = "bass")]
 fn test_bass(path: &str) {
     let bass = Bass::new(-1, 44100, 0).unwrap();
     /*
@@ -241,20 +777,99 @@ fn main() -> Result<(), std::io::Error> {
     );
 
     match &cli.command {
-        Commands::Parse { beatmap } => {
+        Commands::Parse { beatmap, lenient } => {
             if let Some(filename) = beatmap.as_ref() {
-                parse_map(filename).unwrap();
+                parse_map(filename, parse_mode(*lenient)).unwrap();
             } else {
                 println!("You must specify a beatmap path!");
             }
         }
-        Commands::BatchParse { beatmap_dir } => {
+        Commands::BatchParse {
+            beatmap_dir,
+            lenient,
+            round_trip,
+            rating,
+            threads,
+            json,
+            csv,
+        } => {
             if let Some(dir) = beatmap_dir.as_ref() {
-                batch_parse_maps(dir);
+                let threads = threads
+                    .unwrap_or_else(|| thread::available_parallelism().map_or(1, |x| x.get()));
+                let options = BatchOptions {
+                    mode: parse_mode(*lenient),
+                    round_trip: *round_trip,
+                    rating: *rating,
+                    threads,
+                    json: json.clone(),
+                    csv: csv.clone(),
+                };
+                if let Err(err) = batch_parse_maps(dir, options) {
+                    error!("Failed to write the report because {}", err);
+                }
             } else {
                 println!("You must specify a beatmap folder!");
             }
         }
+        Commands::Check { beatmap, json } => {
+            if let Some(filename) = beatmap.as_ref() {
+                if let Err(err) = check_map(filename, *json) {
+                    println!("{}", err);
+                }
+            } else {
+                println!("You must specify a beatmap path!");
+            }
+        }
+        Commands::Rating {
+            beatmap,
+            difficulty,
+            accuracy,
+            misses,
+            combo,
+            mods,
+        } => {
+            if let Some(filename) = beatmap.as_ref() {
+                match BeatmapSet::open_difficulty(filename, difficulty.as_deref(), *mods) {
+                    Ok(beatmap) => {
+                        let score = ScoreInfo {
+                            accuracy: accuracy / 100.0,
+                            misses: *misses,
+                            combo: combo.unwrap_or_else(|| beatmap.max_combo()),
+                        };
+                        if let Err(err) = rate_map(&beatmap, score) {
+                            println!("{}", err);
+                        }
+                    }
+                    Err(err) => println!("Failed to load beatmap: {:?}", err),
+                }
+            } else {
+                println!("You must specify a beatmap path!");
+            }
+        }
+        Commands::Replay { replay } => {
+            if let Some(filename) = replay.as_ref() {
+                if let Err(err) = dump_replay_info(filename) {
+                    println!("{}", err);
+                }
+            } else {
+                println!("You must specify a replay path!");
+            }
+        }
+        Commands::Verify {
+            beatmap,
+            replay,
+            difficulty,
+            ignore_hash,
+        } => match (beatmap.as_ref(), replay.as_ref()) {
+            (Some(beatmap), Some(replay)) => {
+                if let Err(err) =
+                    verify_replay(beatmap, difficulty.as_deref(), replay, *ignore_hash)
+                {
+                    println!("{}", err);
+                }
+            }
+            _ => println!("You must specify a beatmap and a replay path!"),
+        },
         Commands::TestBass { song } => {
             if let Some(song) = song.as_ref() {
                 test_bass(song);
@@ -262,11 +877,109 @@ fn main() -> Result<(), std::io::Error> {
                 println!("You must specify a song path!");
             }
         }
-        Commands::Play { beatmap } => {
-            if let Some(filename) = beatmap.as_ref() {
-                EhhApp::run(filename.clone());
+        Commands::SaveConfig => {
+            let config = Config::load(&cli.config);
+            match config.save(&cli.config) {
+                Ok(()) => println!("Wrote {}", cli.config),
+                Err(e) => println!("Failed to write {}: {}", cli.config, e),
+            }
+        }
+        Commands::Scan { songs } => {
+            let mut config = Config::load(&cli.config);
+            override_config(&mut config.songs_path, songs.clone().map(Some));
+            let library = scan_library(&config, &cli.config);
+            println!("{} beatmaps in the library", library.entries.len());
+        }
+        Commands::Play {
+            beatmap,
+            difficulty,
+            mods,
+            autoplay,
+            skin,
+            width,
+            height,
+            fullscreen,
+            vsync,
+            frame_limit,
+            volume,
+            music_volume,
+            effect_volume,
+            offset,
+            keys,
+            songs,
+            practice,
+        } => {
+            // flags only apply to this run, they don't get saved
+            let mut config = Config::load(&cli.config);
+            override_config(&mut config.skin_path, skin.clone().map(Some));
+            override_config(&mut config.songs_path, songs.clone().map(Some));
+            override_config(&mut config.width, *width);
+            override_config(&mut config.height, *height);
+            override_config(&mut config.fullscreen, *fullscreen);
+            override_config(&mut config.vsync, *vsync);
+            override_config(&mut config.frame_limit, *frame_limit);
+            override_config(&mut config.master_volume, volume.map(|x| x.min(100)));
+            override_config(&mut config.music_volume, music_volume.map(|x| x.min(100)));
+            override_config(&mut config.effect_volume, effect_volume.map(|x| x.min(100)));
+            override_config(&mut config.universal_offset, *offset);
+            if let Some(keys) = keys {
+                match keys.split_once(',') {
+                    Some((left, right)) => {
+                        config.keys = [left.trim().to_string(), right.trim().to_string()]
+                    }
+                    None => println!("Keys should look like \"Z,X\", ignoring them"),
+                }
+            }
+
+            let start = match beatmap.as_ref() {
+                Some(filename) => StartScene::Play(PlayOptions {
+                    beatmap_path: resolve_beatmap_path(&config, filename),
+                    difficulty: difficulty.clone(),
+                    mods: *mods,
+                    autoplay: *autoplay,
+                    practice: *practice,
+                }),
+                None => StartScene::SongSelect {
+                    library: scan_library(&config, &cli.config),
+                    mods: *mods,
+                    autoplay: *autoplay,
+                },
+            };
+            EhhApp::run(start, config);
+        }
+        Commands::Render {
+            beatmap,
+            output,
+            difficulty,
+            mods,
+            skin,
+            fps,
+            width,
+            height,
+            audio,
+        } => {
+            if let (Some(filename), Some(output)) = (beatmap.as_ref(), output.as_ref()) {
+                let mut config = Config::load(&cli.config);
+                override_config(&mut config.skin_path, skin.clone().map(Some));
+                let options = PlayOptions {
+                    beatmap_path: resolve_beatmap_path(&config, filename),
+                    difficulty: difficulty.clone(),
+                    mods: *mods,
+                    autoplay: true,
+                    practice: None,
+                };
+                let render = RenderOptions {
+                    output_dir: output.clone(),
+                    fps: (*fps).max(1),
+                    width: *width,
+                    height: *height,
+                    audio_path: audio.clone(),
+                };
+                if let Err(e) = render_play(&options, &render, &config) {
+                    error!("Failed to render: {}", e);
+                }
             } else {
-                println!("You must specify a beatmap path!");
+                println!("You must specify a beatmap path and an output folder!");
             }
         }
     }
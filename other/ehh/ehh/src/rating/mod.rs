@@ -0,0 +1,189 @@
+// osu!standard star rating and performance points, based on the 2019 ppv2 algorithm
+// see https://github.com/ppy/osu/tree/2019.1227.0/osu.Game.Rulesets.Osu/Difficulty
+
+mod objects;
+mod performance;
+mod skills;
+
+pub use objects::*;
+pub use performance::*;
+pub use skills::*;
+
+use crate::beatmap::{Beatmap, Gamemode, Mods};
+
+const DIFFICULTY_MULTIPLIER: f64 = 0.0675;
+
+#[derive(Debug, Clone, Default)]
+pub struct DifficultyAttributes {
+    pub stars: f64,
+    pub aim: f64,
+    pub speed: f64,
+
+    // the pp calculation needs these too
+    pub mods: Mods,
+    pub approach_rate: f64, // with the rate change applied
+    pub overall_difficulty: f64,
+    pub max_combo: usize,
+    pub circle_count: usize,
+    pub slider_count: usize,
+    pub spinner_count: usize,
+}
+
+impl DifficultyAttributes {
+    pub fn object_count(&self) -> usize {
+        self.circle_count + self.slider_count + self.spinner_count
+    }
+}
+
+// only standard is supported, every other mode returns None
+// mods come from the ones the beatmap was parsed with
+pub fn calculate(beatmap: &Beatmap) -> Option<DifficultyAttributes> {
+    if beatmap.mode != Gamemode::Osu {
+        return None;
+    }
+
+    let mods = beatmap.difficulty.mods;
+    let clock_rate = mods.clock_rate();
+    let objects = difficulty_objects(beatmap, clock_rate);
+    let aim = difficulty_value::<Aim>(&objects, clock_rate).sqrt() * DIFFICULTY_MULTIPLIER;
+    let speed = difficulty_value::<Speed>(&objects, clock_rate).sqrt() * DIFFICULTY_MULTIPLIER;
+
+    Some(DifficultyAttributes {
+        stars: aim + speed + (aim - speed).abs() / 2.0,
+        aim,
+        speed,
+        mods,
+        approach_rate: beatmap.difficulty.approach_rate_adjusted() as f64,
+        overall_difficulty: beatmap.difficulty.overall_difficulty_adjusted() as f64,
+        max_combo: beatmap.max_combo(),
+        circle_count: beatmap.circle_count,
+        slider_count: beatmap.slider_count,
+        spinner_count: beatmap.spinner_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{beatmap::Mods, math::Vector2, rating, Beatmap};
+
+    #[test]
+    fn test_star_rating() {
+        let mut beatmap = Beatmap::fixture("test/jumps.osu");
+        let attributes = rating::calculate(&beatmap).unwrap();
+        assert!(attributes.stars > 0.0);
+        assert!(attributes.aim > 0.0 && attributes.speed > 0.0);
+        assert_eq!(attributes.max_combo, beatmap.max_combo());
+
+        // spreading everything out only makes it harder to aim
+        for x in beatmap.hit_objects.iter_mut() {
+            let centre = Vector2::new(256.0, 192.0);
+            x.start_pos = centre + (x.start_pos - centre) * 1.5;
+        }
+        let spread = rating::calculate(&beatmap).unwrap();
+        assert!(spread.aim > attributes.aim);
+
+        assert!(rating::calculate(&Beatmap::fixture("test/mania.osu")).is_none());
+    }
+
+    #[test]
+    fn test_mods() {
+        let parse_with_mods = |mods: &str| {
+            let beatmap =
+                Beatmap::fixture_with_mods("test/jumps.osu", Mods::from_acronyms(mods).unwrap());
+            rating::calculate(&beatmap).unwrap()
+        };
+        let nomod = parse_with_mods("");
+        let dt = parse_with_mods("DT");
+        assert!(dt.stars > nomod.stars);
+        assert!(dt.approach_rate > nomod.approach_rate);
+        assert!(parse_with_mods("HT").stars < nomod.stars);
+
+        // smaller circles mean harder aim, the flip doesn't matter
+        let hr = parse_with_mods("HR");
+        assert!(hr.aim > nomod.aim);
+        assert_eq!(hr.approach_rate, 10.0);
+        assert!(parse_with_mods("EZ").aim < nomod.aim);
+    }
+
+    // reference values come from a separate port of lazer 2019.1227.0's calculator
+    // the only change is the radius, ehh uses stable's (see Difficulty::recalculate)
+    #[test]
+    fn test_reference_values() {
+        let close = |actual: f64, expected: f64, tolerance: f64| {
+            assert!(
+                (actual - expected).abs() <= tolerance,
+                "{} isn't within {} of {}",
+                actual,
+                tolerance,
+                expected
+            );
+        };
+        // stars, aim and speed, then pp for an ss and for a worse play
+        let check =
+            |path: &str, mods: &str, stars: (f64, f64, f64), max_combo: usize, pp: (f64, f64)| {
+                let beatmap = Beatmap::fixture_with_mods(path, Mods::from_acronyms(mods).unwrap());
+                let attributes = rating::calculate(&beatmap).unwrap();
+                assert_eq!(attributes.max_combo, max_combo);
+                close(attributes.stars, stars.0, 0.001);
+                close(attributes.aim, stars.1, 0.001);
+                close(attributes.speed, stars.2, 0.001);
+
+                let performance = |accuracy: f64, misses: usize, combo: usize| {
+                    rating::ScoreInfo {
+                        accuracy,
+                        misses,
+                        combo,
+                    }
+                    .performance(&attributes)
+                    .total
+                };
+                close(performance(1.0, 0, max_combo), pp.0, 0.01);
+                // one 100 and one miss, with half the combo
+                let total = attributes.object_count();
+                let accuracy = ((total - 2) * 6 + 2) as f64 / (total * 6) as f64;
+                close(performance(accuracy, 1, max_combo / 2), pp.1, 0.01);
+            };
+
+        check(
+            "test/jumps.osu",
+            "",
+            (5.5665, 2.9604, 2.2517),
+            38,
+            (171.528, 85.542),
+        );
+        check(
+            "test/jumps.osu",
+            "HDDT",
+            (7.1663, 3.7775, 3.0001),
+            38,
+            (404.636, 202.272),
+        );
+        check(
+            "test/hitsounds.osu",
+            "",
+            (0.7400, 0.2825, 0.3992),
+            9,
+            (4.6726, 0.0785),
+        );
+    }
+
+    #[test]
+    fn test_performance() {
+        let attributes = rating::calculate(&Beatmap::fixture("test/jumps.osu")).unwrap();
+        let pp = |accuracy: f64, misses: usize, combo: usize| {
+            rating::ScoreInfo {
+                accuracy,
+                misses,
+                combo,
+            }
+            .performance(&attributes)
+            .total
+        };
+
+        let ss = pp(1.0, 0, attributes.max_combo);
+        assert!(ss > 0.0);
+        assert!(pp(0.95, 0, attributes.max_combo) < ss);
+        assert!(pp(1.0, 1, attributes.max_combo) < ss);
+        assert!(pp(1.0, 0, attributes.max_combo / 2) < ss);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    beatmap::{Beatmap, HitObject, HitObjectType},
+    math::Vector2,
+};
+
+// everything gets scaled to what it would be with this radius, so cs doesn't change the distances
+pub const NORMALIZED_RADIUS: f32 = 52.0;
+
+// strains blow up if objects are too close together in time
+const MIN_DELTA_TIME: f64 = 50.0;
+
+// the cursor only has to stay inside the follow circle, not follow the ball exactly
+const FOLLOW_CIRCLE_SCALE: f32 = 3.0;
+
+// an object along with everything the skills need to know about how it's reached
+pub struct DifficultyObject<'a> {
+    pub base: &'a HitObject,
+    pub start: f64,
+    pub delta_time: f64,
+    pub strain_time: f64,     // delta time, but never too small
+    pub jump_distance: f64,   // from where the cursor left the last object
+    pub travel_distance: f64, // how far the cursor had to move through the last object
+    pub angle: Option<f64>,
+}
+
+// where the cursor ends up after a slider, moving as little as possible
+struct LazySlider {
+    end_pos: Vector2,
+    travel_distance: f32,
+}
+
+fn lazy_slider(obj: &HitObject, radius: f32) -> Option<LazySlider> {
+    let slider_info = obj.slider_info.as_ref()?;
+
+    // ticks and repeats land exactly on the path, but the tail uses the shortened legacy end time
+    // pos_at_time would snap back to the start for repeats, so use the tick positions directly
+    let mut nested: Vec<(i32, Vector2)> = slider_info
+        .small_ticks
+        .iter()
+        .chain(slider_info.end_ticks.iter().filter(|x| x.is_repeat))
+        .map(|x| (x.time, x.pos - obj.stack_offset))
+        .collect();
+    if let Some(&end) = slider_info.score_times.last() {
+        nested.push((end, obj.pos_at_time(end)));
+    }
+    nested.sort_by_key(|x| x.0);
+
+    let follow_radius = radius * FOLLOW_CIRCLE_SCALE;
+    let mut cursor = obj.start_pos;
+    let mut travel_distance = 0.0;
+    for (_, pos) in nested {
+        let diff = pos - cursor;
+        let dist = diff.length();
+        if dist > follow_radius {
+            cursor = cursor + diff * ((dist - follow_radius) / dist);
+            travel_distance += dist - follow_radius;
+        }
+    }
+
+    Some(LazySlider {
+        end_pos: cursor,
+        travel_distance,
+    })
+}
+
+fn angle_between(a: Vector2, b: Vector2) -> f64 {
+    let dot = (a.x * b.x + a.y * b.y) as f64;
+    let det = (a.x * b.y - a.y * b.x) as f64;
+    det.atan2(dot).abs()
+}
+
+// turns the beatmap into the list the skills work on
+// the first object gets skipped since there's nothing to move from
+pub fn difficulty_objects(beatmap: &Beatmap, clock_rate: f64) -> Vec<DifficultyObject<'_>> {
+    let radius = beatmap.difficulty.obj_radius;
+    let mut scaling_factor = NORMALIZED_RADIUS / radius;
+    // small circles are harder to hit than their size suggests
+    if radius < 30.0 {
+        scaling_factor *= 1.0 + (30.0 - radius).min(5.0) / 50.0;
+    }
+
+    let lazy: Vec<Option<LazySlider>> = beatmap
+        .hit_objects
+        .iter()
+        .map(|x| lazy_slider(x, radius))
+        .collect();
+    // where the cursor is once it's done with each object
+    let cursor_end = |i: usize| -> Vector2 {
+        match &lazy[i] {
+            Some(x) => x.end_pos,
+            None => beatmap.hit_objects[i].start_pos,
+        }
+    };
+
+    let mut out = Vec::with_capacity(beatmap.hit_objects.len());
+    for i in 1..beatmap.hit_objects.len() {
+        let obj = &beatmap.hit_objects[i];
+        let last = &beatmap.hit_objects[i - 1];
+
+        let start = obj.start as f64 / clock_rate;
+        let delta_time = (obj.start - last.start) as f64 / clock_rate;
+
+        // spinners don't need any aiming
+        let (jump_distance, travel_distance) = if obj.object_type == HitObjectType::Spinner
+            || last.object_type == HitObjectType::Spinner
+        {
+            (0.0, 0.0)
+        } else {
+            let jump =
+                (obj.start_pos * scaling_factor - cursor_end(i - 1) * scaling_factor).length();
+            let travel = lazy[i - 1]
+                .as_ref()
+                .map(|x| x.travel_distance * scaling_factor)
+                .unwrap_or(0.0);
+            (jump as f64, travel as f64)
+        };
+
+        let angle = if i >= 2 && obj.object_type != HitObjectType::Spinner {
+            Some(angle_between(
+                cursor_end(i - 2) - last.start_pos,
+                obj.start_pos - cursor_end(i - 1),
+            ))
+        } else {
+            None
+        };
+
+        out.push(DifficultyObject {
+            base: obj,
+            start,
+            delta_time,
+            strain_time: delta_time.max(MIN_DELTA_TIME),
+            jump_distance,
+            travel_distance,
+            angle,
+        });
+    }
+    out
+}
@@ -0,0 +1,112 @@
+use super::DifficultyAttributes;
+use crate::beatmap::Mods;
+
+// what the player got, the hit counts get worked out from the accuracy
+#[derive(Debug, Clone)]
+pub struct ScoreInfo {
+    pub accuracy: f64, // 0 to 1
+    pub misses: usize,
+    pub combo: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PerformanceAttributes {
+    pub aim: f64,
+    pub speed: f64,
+    pub accuracy: f64,
+    pub total: f64,
+}
+
+// n300, n100, n50
+// assumes no 50s since there's no way to tell them apart from 100s with just the accuracy
+fn hit_counts(total_hits: usize, accuracy: f64, misses: usize) -> (usize, usize, usize) {
+    let misses = misses.min(total_hits);
+    let max_100 = total_hits - misses;
+    let n100 = (-3.0 * ((accuracy - 1.0) * total_hits as f64 + misses as f64) * 0.5)
+        .round()
+        .clamp(0.0, max_100 as f64) as usize;
+    (total_hits - n100 - misses, n100, 0)
+}
+
+fn base_value(rating: f64) -> f64 {
+    (5.0 * (rating / 0.0675).max(1.0) - 4.0).powi(3) / 100000.0
+}
+
+impl ScoreInfo {
+    pub fn performance(&self, attributes: &DifficultyAttributes) -> PerformanceAttributes {
+        let total_hits = attributes.object_count();
+        if total_hits == 0 {
+            return PerformanceAttributes::default();
+        }
+        let (n300, n100, n50) = hit_counts(total_hits, self.accuracy, self.misses);
+        let accuracy = (n300 * 6 + n100 * 2 + n50) as f64 / (total_hits * 6) as f64;
+        let misses = (total_hits - n300 - n100 - n50) as i32;
+        let ar = attributes.approach_rate;
+        let od = attributes.overall_difficulty;
+        let hidden = attributes.mods.contains(Mods::HIDDEN);
+
+        let length_bonus = 0.95
+            + 0.4 * (total_hits as f64 / 2000.0).min(1.0)
+            + if total_hits > 2000 {
+                (total_hits as f64 / 2000.0).log10() * 0.5
+            } else {
+                0.0
+            };
+        let combo_scaling = if attributes.max_combo > 0 {
+            ((self.combo as f64).powf(0.8) / (attributes.max_combo as f64).powf(0.8)).min(1.0)
+        } else {
+            1.0
+        };
+
+        let mut aim =
+            base_value(attributes.aim) * length_bonus * 0.97f64.powi(misses) * combo_scaling;
+        let mut ar_factor = 1.0;
+        if ar > 10.33 {
+            ar_factor += 0.3 * (ar - 10.33);
+        } else if ar < 8.0 {
+            ar_factor += 0.01 * (8.0 - ar);
+        }
+        aim *= ar_factor;
+        // lower ar means the objects are gone for longer
+        if hidden {
+            aim *= 1.0 + 0.04 * (12.0 - ar);
+        }
+        aim *= 0.5 + accuracy / 2.0;
+        aim *= 0.98 + od * od / 2500.0;
+
+        let mut speed =
+            base_value(attributes.speed) * length_bonus * 0.97f64.powi(misses) * combo_scaling;
+        if ar > 10.33 {
+            speed *= 1.0 + 0.3 * (ar - 10.33);
+        }
+        if hidden {
+            speed *= 1.0 + 0.04 * (12.0 - ar);
+        }
+        speed *= 0.02 + accuracy;
+        speed *= 0.96 + od * od / 1600.0;
+
+        // only circles have a hit window that matters, so everything else is assumed to be a 300
+        let circles = attributes.circle_count;
+        let better_accuracy = if circles > 0 {
+            let n300 = n300 as f64 - (total_hits - circles) as f64;
+            ((n300 * 6.0 + n100 as f64 * 2.0 + n50 as f64) / (circles * 6) as f64).max(0.0)
+        } else {
+            0.0
+        };
+        let mut accuracy = 1.52163f64.powf(od)
+            * better_accuracy.powi(24)
+            * 2.83
+            * (circles as f64 / 1000.0).powf(0.3).min(1.15);
+        if hidden {
+            accuracy *= 1.08;
+        }
+
+        let total = (aim.powf(1.1) + speed.powf(1.1) + accuracy.powf(1.1)).powf(1.0 / 1.1) * 1.12;
+        PerformanceAttributes {
+            aim,
+            speed,
+            accuracy,
+            total,
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::f64::consts::PI;
+
+use super::objects::DifficultyObject;
+
+// strains are split into sections and only the hardest part of each one counts
+const SECTION_LENGTH: f64 = 400.0;
+// how much less each section counts than the one harder than it
+const DECAY_WEIGHT: f64 = 0.9;
+
+pub trait Skill {
+    const SKILL_MULTIPLIER: f64;
+    const STRAIN_DECAY_BASE: f64;
+
+    // how hard this object is on its own
+    fn strain_value_of(current: &DifficultyObject, previous: Option<&DifficultyObject>) -> f64;
+}
+
+fn strain_decay<S: Skill>(ms: f64) -> f64 {
+    S::STRAIN_DECAY_BASE.powf(ms / 1000.0)
+}
+
+// peak strains of every section in the map
+// sections line up with the first object in the map, not the first one that has a strain
+pub fn strain_peaks<S: Skill>(objects: &[DifficultyObject], clock_rate: f64) -> Vec<f64> {
+    let mut peaks = Vec::new();
+    let mut current_strain = 0.0;
+    let mut current_peak = 0.0;
+    let mut section_end = match objects.first() {
+        Some(x) => ((x.start - x.delta_time) / SECTION_LENGTH).ceil() * SECTION_LENGTH,
+        None => return peaks,
+    };
+
+    for (i, obj) in objects.iter().enumerate() {
+        let previous = if i > 0 { objects.get(i - 1) } else { None };
+
+        while obj.start > section_end {
+            peaks.push(current_peak);
+            // the next section starts with whatever strain is left over from the last object
+            // stable and lazer both decay this over the unscaled time, so rate changes keep more of it
+            current_peak = match previous {
+                Some(x) => current_strain * strain_decay::<S>((section_end - x.start) * clock_rate),
+                None => 0.0,
+            };
+            section_end += SECTION_LENGTH;
+        }
+
+        current_strain *= strain_decay::<S>(obj.delta_time);
+        current_strain += S::strain_value_of(obj, previous) * S::SKILL_MULTIPLIER;
+        current_peak = f64::max(current_peak, current_strain);
+    }
+    peaks.push(current_peak);
+    peaks
+}
+
+// harder sections count for more, and long maps count for more than short ones
+pub fn difficulty_value<S: Skill>(objects: &[DifficultyObject], clock_rate: f64) -> f64 {
+    let mut peaks = strain_peaks::<S>(objects, clock_rate);
+    peaks.sort_by(|a, b| b.total_cmp(a));
+
+    let mut weight = 1.0;
+    let mut out = 0.0;
+    for x in peaks {
+        out += x * weight;
+        weight *= DECAY_WEIGHT;
+    }
+    out
+}
+
+fn apply_diminishing_exp(x: f64) -> f64 {
+    x.powf(0.99)
+}
+
+// moving the cursor around
+pub struct Aim;
+
+impl Aim {
+    const ANGLE_BONUS_BEGIN: f64 = PI / 3.0;
+    const TIMING_THRESHOLD: f64 = 107.0;
+}
+
+impl Skill for Aim {
+    const SKILL_MULTIPLIER: f64 = 26.25;
+    const STRAIN_DECAY_BASE: f64 = 0.15;
+
+    fn strain_value_of(current: &DifficultyObject, previous: Option<&DifficultyObject>) -> f64 {
+        if current.base.object_type == crate::beatmap::HitObjectType::Spinner {
+            return 0.0;
+        }
+
+        // sharp angles between two big jumps are harder than the distance alone says
+        let mut result = 0.0;
+        if let (Some(previous), Some(angle)) = (previous, current.angle) {
+            if angle > Self::ANGLE_BONUS_BEGIN {
+                const SCALE: f64 = 90.0;
+                let angle_bonus = ((previous.jump_distance - SCALE).max(0.0)
+                    * (angle - Self::ANGLE_BONUS_BEGIN).sin().powi(2)
+                    * (current.jump_distance - SCALE).max(0.0))
+                .sqrt();
+                result = 1.5 * apply_diminishing_exp(angle_bonus.max(0.0))
+                    / previous.strain_time.max(Self::TIMING_THRESHOLD);
+            }
+        }
+
+        let jump = apply_diminishing_exp(current.jump_distance);
+        let travel = apply_diminishing_exp(current.travel_distance);
+        let combined = jump + travel + (jump * travel).sqrt();
+        f64::max(
+            result + combined / current.strain_time.max(Self::TIMING_THRESHOLD),
+            combined / current.strain_time,
+        )
+    }
+}
+
+// tapping fast
+pub struct Speed;
+
+impl Speed {
+    const SINGLE_SPACING_THRESHOLD: f64 = 125.0;
+    const ANGLE_BONUS_BEGIN: f64 = 5.0 * PI / 6.0;
+    const MIN_SPEED_BONUS: f64 = 75.0; // ~200bpm 1/4
+    const MAX_SPEED_BONUS: f64 = 45.0; // ~330bpm 1/4
+    const SPEED_BALANCING_FACTOR: f64 = 40.0;
+}
+
+impl Skill for Speed {
+    const SKILL_MULTIPLIER: f64 = 1400.0;
+    const STRAIN_DECAY_BASE: f64 = 0.3;
+
+    fn strain_value_of(current: &DifficultyObject, _previous: Option<&DifficultyObject>) -> f64 {
+        if current.base.object_type == crate::beatmap::HitObjectType::Spinner {
+            return 0.0;
+        }
+
+        let distance =
+            Self::SINGLE_SPACING_THRESHOLD.min(current.travel_distance + current.jump_distance);
+        let delta_time = current.strain_time.max(Self::MAX_SPEED_BONUS);
+
+        let mut speed_bonus = 1.0;
+        if delta_time < Self::MIN_SPEED_BONUS {
+            speed_bonus +=
+                ((Self::MIN_SPEED_BONUS - delta_time) / Self::SPEED_BALANCING_FACTOR).powi(2);
+        }
+
+        // streams are easier to tap when they don't turn much
+        let mut angle_bonus = 1.0;
+        if let Some(angle) = current.angle {
+            if angle < Self::ANGLE_BONUS_BEGIN {
+                angle_bonus = 1.0 + (1.5 * (Self::ANGLE_BONUS_BEGIN - angle)).sin().powi(2) / 3.57;
+                if angle < PI / 2.0 {
+                    angle_bonus = 1.28;
+                    if distance < 90.0 && angle < PI / 4.0 {
+                        angle_bonus += (1.0 - angle_bonus) * ((90.0 - distance) / 10.0).min(1.0);
+                    } else if distance < 90.0 {
+                        angle_bonus += (1.0 - angle_bonus)
+                            * ((90.0 - distance) / 10.0).min(1.0)
+                            * ((PI / 2.0 - angle) / (PI / 4.0)).sin();
+                    }
+                }
+            }
+        }
+
+        (1.0 + (speed_bonus - 1.0) * 0.75)
+            * angle_bonus
+            * (0.95 + speed_bonus * (distance / Self::SINGLE_SPACING_THRESHOLD).powf(3.5))
+            / current.strain_time
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{
+    beatmap::{HitObject, HitObjectType},
+    math::{interp_time, Easing, Vector2},
+    Beatmap,
+};
+
+use super::*;
+
+// how often the cursor position gets sampled while moving
+const FRAME_INTERVAL: i32 = 10;
+// how long circles get held for, same as stable's auto
+const KEY_UP_DELAY: i32 = 50;
+
+const SPINNER_CENTRE: Vector2 = Vector2 { x: 256.0, y: 192.0 };
+const SPIN_RADIUS: f32 = 50.0;
+// just under what counts, in radians per real time ms
+const SPIN_SPEED: f32 = 0.045;
+
+// where the cursor should be while the object is being played
+fn pos_in_object(obj: &HitObject, time: i32, rate: f32) -> Vector2 {
+    match obj.object_type {
+        HitObjectType::Slider => obj.ball_pos_at_time(time).0,
+        HitObjectType::Spinner => {
+            let angle = (time - obj.start).max(0) as f32 * SPIN_SPEED * rate;
+            SPINNER_CENTRE + Vector2::new(angle.cos(), angle.sin()) * SPIN_RADIUS
+        }
+        _ => obj.start_pos,
+    }
+}
+
+fn pos_at(objs: &[HitObject], time: i32, rate: f32) -> Vector2 {
+    // the latest object that started takes over, even if the last one isn't done yet
+    let idx = objs.partition_point(|x| x.start <= time);
+    if idx == 0 {
+        return objs
+            .first()
+            .map(|x| pos_in_object(x, x.start, rate))
+            .unwrap_or(SPINNER_CENTRE);
+    }
+
+    let current = &objs[idx - 1];
+    if time <= current.end {
+        return pos_in_object(current, time, rate);
+    }
+    let from = pos_in_object(current, current.end, rate);
+    match objs.get(idx) {
+        Some(next) => {
+            let to = pos_in_object(next, next.start, rate);
+            let (t1, t2) = (current.end as f32, next.start as f32);
+            Vector2::new(
+                interp_time(from.x, to.x, t1, t2, time as f32, Easing::OutQuad),
+                interp_time(from.y, to.y, t1, t2, time as f32, Easing::OutQuad),
+            )
+        }
+        None => from,
+    }
+}
+
+// a perfect play of the map, with the keys alternating between objects
+pub fn autoplay_frames(beatmap: &Beatmap) -> Vec<ReplayFrame> {
+    let objs = &beatmap.hit_objects;
+    let rate = beatmap.difficulty.mods.clock_rate() as f32;
+    if objs.is_empty() {
+        return Vec::new();
+    }
+
+    let keys = [OsuKeys::M1 | OsuKeys::K1, OsuKeys::M2 | OsuKeys::K2];
+    let presses: Vec<(i32, i32, OsuKeys)> = objs
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let release = match x.object_type {
+                HitObjectType::Spinner => x.end,
+                _ => x.end + KEY_UP_DELAY,
+            };
+            // let go before the same key is needed again
+            let release = match objs.get(i + 2) {
+                Some(next) => release.min(next.start - 1).max(x.start + 1),
+                None => release,
+            };
+            (x.start, release, keys[i % 2])
+        })
+        .collect();
+
+    let first = objs[0].start - objs[0].time_preempt;
+    let last = presses.iter().map(|x| x.1).max().unwrap_or(first);
+    let mut times: Vec<i32> = (first..=last).step_by(FRAME_INTERVAL as usize).collect();
+    times.extend(presses.iter().flat_map(|x| [x.0, x.1]));
+    times.extend(objs.iter().map(|x| x.end));
+    times.sort_unstable();
+    times.dedup();
+
+    // presses are in start order, so only the ones that are currently held need checking
+    let mut next_press = 0;
+    let mut held: Vec<(i32, i32, OsuKeys)> = Vec::new();
+    times
+        .into_iter()
+        .map(|time| {
+            while let Some(x) = presses.get(next_press).filter(|x| x.0 <= time) {
+                held.push(*x);
+                next_press += 1;
+            }
+            held.retain(|x| time < x.1);
+            ReplayFrame {
+                time,
+                pos: pos_at(objs, time, rate),
+                keys: held.iter().fold(OsuKeys::empty(), |acc, x| acc | x.2),
+            }
+        })
+        .collect()
+}
+
+impl Replay {
+    pub fn autoplay(beatmap: &Beatmap) -> Replay {
+        let mods = beatmap.difficulty.mods;
+        Replay {
+            mode: beatmap.mode,
+            version: Self::LATEST_VERSION,
+            beatmap_hash: beatmap.hash.clone(),
+            player_name: "ehh!".to_string(),
+            mods,
+            frames: autoplay_frames(beatmap),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,175 @@
+mod autoplay;
+mod parser;
+mod writer;
+
+use crate::{beatmap::Mods, math::Vector2};
+
+pub use autoplay::*;
+pub use parser::*;
+
+// same bits as replays
+bitflags::bitflags! {
+    #[derive(Default)]
+    pub struct OsuKeys: i32 {
+        const M1 = 1;
+        const M2 = 2;
+        const K1 = 4;
+        const K2 = 8;
+        const SMOKE = 16;
+    }
+}
+
+impl OsuKeys {
+    // everything that can hit objects
+    pub fn gameplay(&self) -> OsuKeys {
+        *self & (OsuKeys::M1 | OsuKeys::M2 | OsuKeys::K1 | OsuKeys::K2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayFrame {
+    pub time: i32, // absolute, the file stores the time since the previous frame
+    pub pos: Vector2,
+    pub keys: OsuKeys,
+}
+
+#[derive(Default)]
+pub struct Replay {
+    pub mode: crate::beatmap::Gamemode,
+    pub version: i32, // the game version as yyyymmdd
+    pub beatmap_hash: String,
+    pub player_name: String,
+    pub replay_hash: String,
+
+    pub count_300: u16,
+    pub count_100: u16,
+    pub count_50: u16,
+    pub count_geki: u16,
+    pub count_katu: u16,
+    pub count_miss: u16,
+    pub score: i32,
+    pub max_combo: u16,
+    pub perfect: bool,
+    pub mods: Mods,
+    pub unknown_mods: u32, // the bits ehh doesn't handle, kept so they survive a round trip
+
+    pub life_bar: Vec<(i32, f32)>, // time, hp from 0 to 1
+    pub timestamp: i64,            // .net ticks
+    pub frames: Vec<ReplayFrame>,
+    pub rng_seed: Option<i32>, // stored as a fake frame at the end
+    pub online_id: i64,
+    pub target_accuracy: Option<f64>, // only with target practice
+}
+
+impl Replay {
+    // the file's header and frames get written by stable's version from this date on
+    pub const LATEST_VERSION: i32 = 20220216;
+
+    const TARGET_PRACTICE: u32 = 1 << 23;
+    // old replays store the online id as an i32
+    const LONG_ONLINE_ID_VERSION: i32 = 20140721;
+    const RNG_SEED_TIME: i32 = -12345;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader};
+
+    use super::*;
+
+    fn check_jumps(replay: &Replay) {
+        assert_eq!(replay.mode, crate::beatmap::Gamemode::Osu);
+        assert_eq!(replay.version, 20220216);
+        assert_eq!(replay.beatmap_hash, "09395d16555437873774e4b674e218c8");
+        assert_eq!(replay.player_name, "khang");
+        assert_eq!(replay.count_300, 34);
+        assert_eq!(replay.count_geki, 3);
+        assert_eq!(replay.score, 123456);
+        assert_eq!(replay.max_combo, 37);
+        assert!(replay.perfect);
+        assert_eq!(replay.mods, Mods::HIDDEN | Mods::DOUBLE_TIME);
+        assert_eq!(replay.life_bar, &[(0, 1.0), (1000, 1.0), (2000, 0.95)]);
+        assert_eq!(replay.timestamp, 637900000000000000);
+        assert_eq!(replay.online_id, 4000000000);
+        assert_eq!(replay.rng_seed, Some(7));
+
+        // the two weird frames at the start, then a press on every object
+        assert_eq!(replay.frames.len(), 2 + 34 * 3);
+        assert_eq!(replay.frames[1].time, -1);
+        assert_eq!(replay.frames[2].time, 970);
+        assert_eq!(replay.frames[3].time, 1000);
+        assert_eq!(replay.frames[3].pos, Vector2::new(64.0, 64.0));
+        assert_eq!(replay.frames[3].keys, OsuKeys::M1 | OsuKeys::K1);
+        assert_eq!(replay.frames[4].keys, OsuKeys::empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        let replay =
+            Replay::parse(&mut BufReader::new(File::open("test/jumps.osr").unwrap())).unwrap();
+        check_jumps(&replay);
+    }
+
+    // laid out and compressed like stable's own replays: lzma with the real size in the header,
+    // frames recorded at 60hz with extra ones on key changes, and floats with 7 digits
+    #[test]
+    fn test_parse_stable() {
+        let replay =
+            Replay::parse(&mut BufReader::new(File::open("test/stable.osr").unwrap())).unwrap();
+        assert_eq!(replay.mode, crate::beatmap::Gamemode::Osu);
+        assert_eq!(replay.version, 20210520);
+        assert_eq!(
+            replay.beatmap_hash,
+            crate::Beatmap::fixture("test/jumps.osu").hash
+        );
+        assert_eq!(replay.player_name, "khangaroood");
+        assert_eq!(replay.replay_hash, "106b662ddcf452cc8e04918fcb8616a8");
+        assert_eq!(
+            (replay.count_300, replay.count_100, replay.count_50),
+            (34, 0, 0)
+        );
+        assert_eq!(
+            (replay.count_geki, replay.count_katu, replay.count_miss),
+            (4, 0, 0)
+        );
+        assert_eq!(replay.score, 132466);
+        assert_eq!(replay.max_combo, 38);
+        assert!(replay.perfect);
+        assert_eq!(replay.mods, Mods::HIDDEN);
+        assert_eq!(replay.unknown_mods, 0);
+        assert_eq!(replay.life_bar.len(), 35);
+        assert_eq!(replay.life_bar[..3], [(0, 1.0), (250, 1.0), (500, 0.9863)]);
+        assert_eq!(replay.timestamp, 637896816001234567);
+        assert_eq!(replay.online_id, 3912345678);
+        assert_eq!(replay.rng_seed, Some(1848312839));
+
+        assert_eq!(replay.frames.len(), 590);
+        assert_eq!(replay.frames[1].time, -1);
+        assert_eq!(replay.frames[1].pos, Vector2::new(256.0, -500.0));
+        assert_eq!(replay.frames[3].time, 17);
+        assert_eq!(replay.frames[3].pos, Vector2::new(86.79669, 79.19779));
+
+        // the first circle, pressed a bit early and let go a few frames later
+        assert_eq!(replay.frames[62].time, 993);
+        assert_eq!(replay.frames[62].keys, OsuKeys::M1 | OsuKeys::K1);
+        assert_eq!(replay.frames[63].pos, Vector2::new(64.0, 64.0));
+        assert_eq!(replay.frames[66].keys, OsuKeys::M1 | OsuKeys::K1);
+        assert_eq!(replay.frames[67].time, 1051);
+        assert_eq!(replay.frames[67].keys, OsuKeys::empty());
+        assert_eq!(replay.frames[589].time, 8683);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut replay =
+            Replay::parse(&mut BufReader::new(File::open("test/jumps.osr").unwrap())).unwrap();
+        replay.unknown_mods = 1 << 9; // nightcore, which always comes with dt
+
+        let mut out = Vec::new();
+        replay.write(&mut out).unwrap();
+        let written = Replay::parse(&mut out.as_slice()).unwrap();
+        check_jumps(&written);
+        assert_eq!(written.unknown_mods, 1 << 9);
+        assert_eq!(written.frames, replay.frames);
+    }
+}
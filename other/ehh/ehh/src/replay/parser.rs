@@ -0,0 +1,138 @@
+use std::io::{self, BufReader, Read};
+
+use crate::{
+    beatmap::{Gamemode, Mods},
+    binary_reader::{BinaryReadErr, BinaryReader},
+    math::Vector2,
+};
+
+use super::*;
+
+#[derive(Debug)]
+pub enum ReplayParseErr {
+    IoError(io::Error),
+    UnsupportedMode,
+    InvalidString,
+    InvalidLifeBar,
+    InvalidFrame(usize),
+    LzmaError(String),
+}
+impl From<io::Error> for ReplayParseErr {
+    fn from(x: io::Error) -> ReplayParseErr {
+        ReplayParseErr::IoError(x)
+    }
+}
+
+impl BinaryReadErr for ReplayParseErr {
+    fn invalid_string() -> ReplayParseErr {
+        ReplayParseErr::InvalidString
+    }
+}
+
+// "time|hp,time|hp,..."
+fn parse_life_bar(source: &str) -> Result<Vec<(i32, f32)>, ReplayParseErr> {
+    source
+        .split(',')
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            let (time, hp) = x.split_once('|').ok_or(ReplayParseErr::InvalidLifeBar)?;
+            match (time.parse::<i32>(), hp.parse::<f32>()) {
+                (Ok(time), Ok(hp)) => Ok((time, hp)),
+                _ => Err(ReplayParseErr::InvalidLifeBar),
+            }
+        })
+        .collect()
+}
+
+impl Replay {
+    pub fn parse(file: &mut impl Read) -> Result<Self, ReplayParseErr> {
+        // everything in the header is little endian
+        let mut file = BinaryReader::<_, ReplayParseErr>::new(file);
+        let mut replay = Replay {
+            mode: match file.read_u8()? {
+                0 => Gamemode::Osu,
+                1 => Gamemode::Taiko,
+                2 => Gamemode::CatchTheBeat,
+                3 => Gamemode::Mania,
+                _ => return Err(ReplayParseErr::UnsupportedMode),
+            },
+            version: file.read_i32()?,
+            beatmap_hash: file.read_dotnet_string()?,
+            player_name: file.read_dotnet_string()?,
+            replay_hash: file.read_dotnet_string()?,
+            count_300: file.read_u16()?,
+            count_100: file.read_u16()?,
+            count_50: file.read_u16()?,
+            count_geki: file.read_u16()?,
+            count_katu: file.read_u16()?,
+            count_miss: file.read_u16()?,
+            score: file.read_i32()?,
+            max_combo: file.read_u16()?,
+            perfect: file.read_u8()? != 0,
+            ..Default::default()
+        };
+
+        let mods = file.read_i32()? as u32;
+        replay.mods = Mods::from_bits_truncate(mods);
+        replay.unknown_mods = mods & !Mods::all().bits();
+        replay.life_bar = parse_life_bar(&file.read_dotnet_string()?)?;
+        replay.timestamp = file.read_i64()?;
+
+        let compressed_len = file.read_i32()?.max(0) as u64;
+        let mut data = Vec::new();
+        lzma_rs::lzma_decompress(
+            &mut BufReader::new(file.get_mut().take(compressed_len)),
+            &mut data,
+        )
+        .map_err(|x| ReplayParseErr::LzmaError(x.to_string()))?;
+        replay.parse_frames(&String::from_utf8_lossy(&data))?;
+
+        replay.online_id = if replay.version >= Self::LONG_ONLINE_ID_VERSION {
+            file.read_i64()?
+        } else {
+            file.read_i32()? as i64
+        };
+        if mods & Self::TARGET_PRACTICE != 0 {
+            replay.target_accuracy = Some(file.read_f64()?);
+        }
+
+        Ok(replay)
+    }
+
+    // "delta|x|y|keys,..."
+    fn parse_frames(&mut self, source: &str) -> Result<(), ReplayParseErr> {
+        let mut time = 0;
+        for (i, x) in source.split(',').filter(|x| !x.is_empty()).enumerate() {
+            let split: Vec<&str> = x.split('|').collect();
+            if split.len() != 4 {
+                return Err(ReplayParseErr::InvalidFrame(i));
+            }
+            // some old replays have floats for the keys, but the seed needs every digit so ints go first
+            let keys = split[3]
+                .parse::<i32>()
+                .or_else(|_| split[3].parse::<f32>().map(|x| x as i32));
+            let (delta, x, y, keys) = match (
+                split[0].parse::<i64>(),
+                split[1].parse::<f32>(),
+                split[2].parse::<f32>(),
+                keys,
+            ) {
+                (Ok(delta), Ok(x), Ok(y), Ok(keys)) => (delta as i32, x, y, keys),
+                _ => return Err(ReplayParseErr::InvalidFrame(i)),
+            };
+
+            if delta == Self::RNG_SEED_TIME {
+                self.rng_seed = Some(keys);
+                continue;
+            }
+            time += delta;
+            self.frames.push(ReplayFrame {
+                time,
+                pos: Vector2::new(x, y),
+                keys: OsuKeys::from_bits_truncate(keys),
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::{self, Write};
+
+use lzma_rs::compress::{Options, UnpackedSize};
+
+use super::*;
+
+fn write_string(out: &mut impl Write, source: &str) -> io::Result<()> {
+    if source.is_empty() {
+        return out.write_all(&[0x00]);
+    }
+
+    out.write_all(&[0x0B])?;
+    let mut len = source.len();
+    loop {
+        let byte = (len & 0x7F) as u8;
+        len >>= 7;
+        if len == 0 {
+            out.write_all(&[byte])?;
+            break;
+        }
+        out.write_all(&[byte | 0x80])?;
+    }
+    out.write_all(source.as_bytes())
+}
+
+impl Replay {
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&[self.mode as u8])?;
+        out.write_all(&self.version.to_le_bytes())?;
+        write_string(out, &self.beatmap_hash)?;
+        write_string(out, &self.player_name)?;
+        write_string(out, &self.replay_hash)?;
+        for x in [
+            self.count_300,
+            self.count_100,
+            self.count_50,
+            self.count_geki,
+            self.count_katu,
+            self.count_miss,
+        ] {
+            out.write_all(&x.to_le_bytes())?;
+        }
+        out.write_all(&self.score.to_le_bytes())?;
+        out.write_all(&self.max_combo.to_le_bytes())?;
+        out.write_all(&[self.perfect as u8])?;
+
+        let mods = self.mods.bits() | self.unknown_mods;
+        out.write_all(&mods.to_le_bytes())?;
+
+        let life_bar: String = self
+            .life_bar
+            .iter()
+            .map(|(time, hp)| format!("{time}|{hp},"))
+            .collect();
+        write_string(out, &life_bar)?;
+        out.write_all(&self.timestamp.to_le_bytes())?;
+
+        let frames = self.frames_string();
+        let mut compressed = Vec::new();
+        lzma_rs::lzma_compress_with_options(
+            &mut frames.as_bytes(),
+            &mut compressed,
+            &Options {
+                unpacked_size: UnpackedSize::WriteToHeader(Some(frames.len() as u64)),
+            },
+        )?;
+        out.write_all(&(compressed.len() as i32).to_le_bytes())?;
+        out.write_all(&compressed)?;
+
+        if self.version >= Self::LONG_ONLINE_ID_VERSION {
+            out.write_all(&self.online_id.to_le_bytes())?;
+        } else {
+            out.write_all(&(self.online_id as i32).to_le_bytes())?;
+        }
+        if mods & Self::TARGET_PRACTICE != 0 {
+            out.write_all(&self.target_accuracy.unwrap_or_default().to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn frames_string(&self) -> String {
+        let mut out = String::new();
+        let mut last_time = 0;
+        for x in &self.frames {
+            out += &format!(
+                "{}|{}|{}|{},",
+                x.time - last_time,
+                x.pos.x,
+                x.pos.y,
+                x.keys.bits()
+            );
+            last_time = x.time;
+        }
+        if let Some(seed) = self.rng_seed {
+            out += &format!("{}|0|0|{},", Self::RNG_SEED_TIME, seed);
+        }
+        out
+    }
+}